@@ -0,0 +1,404 @@
+// Authentication attack classification
+// Failed logins are split into campaigns (bursts with no gap longer than
+// CAMPAIGN_GAP_MINUTES) and each campaign is grouped by source IP and
+// account to tell the attack shapes apart:
+// - brute force: one IP, many attempts against one or a few accounts
+// - password spraying: one IP, few attempts each across many accounts
+// - credential stuffing: many IPs and many accounts, a handful of tries per pair
+// Successful logins are then checked against the failures that preceded them:
+// a success for an account or IP that just failed repeatedly is a probable
+// compromise.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use security_common::cvss::ThreatType;
+use security_common::parsers::parse_timestamp;
+use security_common::{AuthAttack, LogEntry, Login};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+/// Failed logins further apart than this belong to different campaigns
+const CAMPAIGN_GAP_MINUTES: i64 = 30;
+/// Failed logins from one IP before it is treated as an attack
+const MIN_ATTEMPTS_PER_IP: usize = 5;
+/// Distinct accounts from one IP before it is spraying rather than brute force
+const SPRAY_MIN_ACCOUNTS: usize = 5;
+/// Spraying tries few passwords per account; more than this is brute force
+const SPRAY_MAX_ATTEMPTS_PER_ACCOUNT: f64 = 3.0;
+/// Distinct source IPs and accounts for distributed credential stuffing
+const STUFFING_MIN_IPS: usize = 3;
+const STUFFING_MIN_ACCOUNTS: usize = 5;
+/// How far back a successful login is correlated with failures
+const COMPROMISE_WINDOW_MINUTES: i64 = 60;
+/// Failures for the same account or IP before a success looks like a compromise
+const COMPROMISE_MIN_FAILURES: usize = 3;
+
+/// Detection rule whose matches are successful logins
+pub const SUCCESSFUL_LOGIN_RULE: &str = "log.successful_login";
+
+struct Attempt<'a> {
+    index: usize,
+    entry: &'a LogEntry,
+    time: Option<DateTime<Utc>>,
+    ip: Option<&'a str>,
+    account: Option<String>,
+}
+
+fn attempts<'a>(entries: &'a [LogEntry], indices: &[usize]) -> Vec<Attempt<'a>> {
+    indices
+        .iter()
+        .map(|&index| {
+            let entry = &entries[index];
+            Attempt {
+                index,
+                entry,
+                time: parse_timestamp(&entry.timestamp),
+                ip: entry.ip_address.as_deref(),
+                account: account_name(entry),
+            }
+        })
+        .collect()
+}
+
+/// Classify the failed logins among `entries` (given by index). Each attack
+/// comes with the index of the entry that completed it.
+pub fn classify(entries: &[LogEntry], failed: &[usize]) -> Vec<(ThreatType, AuthAttack, usize)> {
+    campaigns(attempts(entries, failed)).iter().flat_map(|c| classify_campaign(c)).collect()
+}
+
+/// Successful logins preceded by repeated failures for the same account or
+/// IP. Each failure is attributed to at most one success, which is the
+/// returned entry index.
+pub fn correlate_compromises(
+    entries: &[LogEntry],
+    failed: &[usize],
+    succeeded: &[usize],
+) -> Vec<(ThreatType, AuthAttack, usize)> {
+    let failures = attempts(entries, failed);
+    let mut used = vec![false; failures.len()];
+    let mut findings = Vec::new();
+
+    for success in attempts(entries, succeeded) {
+        let related: Vec<usize> = failures
+            .iter()
+            .enumerate()
+            .filter(|(i, failure)| !used[*i] && precedes(failure, &success))
+            .filter(|(_, failure)| {
+                (failure.account.is_some() && failure.account == success.account)
+                    || (failure.ip.is_some() && failure.ip == success.ip)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if related.len() < COMPROMISE_MIN_FAILURES {
+            continue;
+        }
+
+        let mut sequence: Vec<&Attempt> = related.iter().map(|&i| &failures[i]).collect();
+        sequence.sort_by_key(|a| (a.time, a.index));
+        let first = sequence[0];
+        for &i in &related {
+            used[i] = true;
+        }
+
+        let account = success.account.as_deref().unwrap_or("unknown account");
+        let ip = success.ip.unwrap_or("unknown IP");
+        let description = format!(
+            "Login as {} from {} succeeded after {} failures (first at {})",
+            account,
+            ip,
+            sequence.len(),
+            first.entry.timestamp
+        );
+        let threat = ThreatType::AccountCompromise;
+        let mut finding = attack(threat.clone(), &sequence, description);
+        finding.failed_attempts = sequence.len();
+        // The compromised account and the IP that got in lead the lists
+        finding.accounts.retain(|a| Some(a.as_str()) != success.account.as_deref());
+        finding.source_ips.retain(|i| Some(i.as_str()) != success.ip);
+        finding.accounts.splice(0..0, success.account.clone());
+        finding.source_ips.splice(0..0, success.ip.map(str::to_string));
+        finding.last_seen = success.entry.timestamp.clone();
+        finding.evidence = sequence
+            .iter()
+            .chain(std::iter::once(&&success))
+            .map(|a| format!("{} {}", a.entry.timestamp, a.entry.message))
+            .collect();
+        findings.push((threat, finding, success.index));
+    }
+    findings
+}
+
+/// The successful logins among `entries` (given by index) that name both an
+/// account and a source IP
+pub fn logins(entries: &[LogEntry], succeeded: &[usize], line_numbers: &[usize]) -> Vec<Login> {
+    attempts(entries, succeeded)
+        .into_iter()
+        .filter_map(|attempt| {
+            Some(Login {
+                account: attempt.account?,
+                ip: attempt.ip?.to_string(),
+                timestamp: attempt.entry.timestamp.clone(),
+                line_number: line_numbers.get(attempt.index).copied().unwrap_or(attempt.index + 1),
+            })
+        })
+        .collect()
+}
+
+/// Whether `failure` happened before `success` and within the correlation
+/// window. Without timestamps on both, file order decides.
+fn precedes(failure: &Attempt, success: &Attempt) -> bool {
+    match (failure.time, success.time) {
+        (Some(f), Some(s)) => f <= s && (s - f).num_minutes() <= COMPROMISE_WINDOW_MINUTES,
+        _ => failure.index < success.index,
+    }
+}
+
+/// Split attempts into bursts. Attempts without a usable timestamp cannot be
+/// placed in time and form one campaign of their own.
+fn campaigns(attempts: Vec<Attempt>) -> Vec<Vec<Attempt>> {
+    let (mut timed, untimed): (Vec<Attempt>, Vec<Attempt>) = attempts.into_iter().partition(|a| a.time.is_some());
+    timed.sort_by_key(|a| a.time);
+
+    let mut campaigns: Vec<Vec<Attempt>> = Vec::new();
+    for attempt in timed {
+        let continues = campaigns
+            .last()
+            .and_then(|c| c.last())
+            .is_some_and(|prev| (attempt.time.unwrap() - prev.time.unwrap()).num_minutes() <= CAMPAIGN_GAP_MINUTES);
+        match campaigns.last_mut() {
+            Some(current) if continues => current.push(attempt),
+            _ => campaigns.push(vec![attempt]),
+        }
+    }
+    if !untimed.is_empty() {
+        campaigns.push(untimed);
+    }
+    campaigns
+}
+
+fn classify_campaign(campaign: &[Attempt]) -> Vec<(ThreatType, AuthAttack, usize)> {
+    let mut by_ip: BTreeMap<&str, Vec<&Attempt>> = BTreeMap::new();
+    for attempt in campaign {
+        if let Some(ip) = attempt.ip {
+            by_ip.entry(ip).or_default().push(attempt);
+        }
+    }
+
+    let mut attacks = Vec::new();
+    let mut remaining: Vec<&Attempt> = Vec::new();
+    for (ip, attempts) in by_ip {
+        if attempts.len() < MIN_ATTEMPTS_PER_IP {
+            remaining.extend(attempts);
+            continue;
+        }
+        let accounts: BTreeSet<&str> = attempts.iter().filter_map(|a| a.account.as_deref()).collect();
+        let per_account = attempts.len() as f64 / accounts.len().max(1) as f64;
+        let (threat, description) = if accounts.len() >= SPRAY_MIN_ACCOUNTS && per_account <= SPRAY_MAX_ATTEMPTS_PER_ACCOUNT {
+            (
+                ThreatType::PasswordSpraying,
+                format!(
+                    "{} failed logins across {} accounts from {} ({:.1} per account)",
+                    attempts.len(),
+                    accounts.len(),
+                    ip,
+                    per_account
+                ),
+            )
+        } else {
+            (
+                ThreatType::BruteForce,
+                format!(
+                    "{} failed logins against {} from {}",
+                    attempts.len(),
+                    match accounts.len() {
+                        0 => "unknown accounts".to_string(),
+                        1 => "1 account".to_string(),
+                        n => format!("{} accounts", n),
+                    },
+                    ip
+                ),
+            )
+        };
+        attacks.push((threat.clone(), attack(threat, &attempts, description), last_index(&attempts)));
+    }
+
+    // Whatever no single IP accounts for may still be a distributed attack
+    let ips: BTreeSet<&str> = remaining.iter().filter_map(|a| a.ip).collect();
+    let accounts: BTreeSet<&str> = remaining.iter().filter_map(|a| a.account.as_deref()).collect();
+    if ips.len() >= STUFFING_MIN_IPS && accounts.len() >= STUFFING_MIN_ACCOUNTS {
+        let description = format!(
+            "{} failed logins for {} accounts from {} IPs",
+            remaining.len(),
+            accounts.len(),
+            ips.len()
+        );
+        let threat = ThreatType::CredentialStuffing;
+        attacks.push((threat.clone(), attack(threat, &remaining, description), last_index(&remaining)));
+    }
+
+    attacks
+}
+
+fn attack(threat: ThreatType, attempts: &[&Attempt], description: String) -> AuthAttack {
+    let first = attempts.iter().min_by_key(|a| a.time).map(|a| a.entry.timestamp.clone());
+    let last = attempts.iter().max_by_key(|a| a.time).map(|a| a.entry.timestamp.clone());
+    AuthAttack {
+        pattern: threat.display_name().to_string(),
+        severity: threat.cvss_score().severity.as_str().to_string(),
+        failed_attempts: attempts.len(),
+        accounts: by_frequency(attempts.iter().filter_map(|a| a.account.as_deref())),
+        source_ips: by_frequency(attempts.iter().filter_map(|a| a.ip)),
+        first_seen: first.unwrap_or_default(),
+        last_seen: last.unwrap_or_default(),
+        description,
+        evidence: Vec::new(),
+    }
+}
+
+/// Entry index of the latest attempt
+fn last_index(attempts: &[&Attempt]) -> usize {
+    attempts.iter().max_by_key(|a| (a.time, a.index)).map_or(0, |a| a.index)
+}
+
+/// Distinct values, most frequent first (ties alphabetical)
+fn by_frequency<'a>(values: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts.into_iter().map(|(value, _)| value.to_string()).collect()
+}
+
+/// The targeted account. Parsers skip `root` when guessing usernames, but it
+/// is the most common brute-force target, so fall back to the message.
+fn account_name(entry: &LogEntry) -> Option<String> {
+    if let Some(user) = &entry.username {
+        return Some(user.clone());
+    }
+    static ACCOUNT: OnceLock<Regex> = OnceLock::new();
+    let re = ACCOUNT.get_or_init(|| {
+        Regex::new(r"(?i)(?:\bfor\s+(?:invalid user\s+)?|account name:\s*|\buser[=:\s]+)([A-Za-z0-9_.@-]+)")
+            .expect("account pattern")
+    });
+    re.captures(&entry.message)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(minute: u32, ip: &str, user: &str) -> LogEntry {
+        LogEntry {
+            timestamp: format!("2025-02-20T10:{:02}:00Z", minute),
+            level: "WARN".to_string(),
+            ip_address: Some(ip.to_string()),
+            username: Some(user.to_string()),
+            message: format!("Failed password for {} from {}", user, ip),
+            fields: Default::default(),
+        }
+    }
+
+    fn run(entries: &[LogEntry]) -> Vec<(ThreatType, AuthAttack, usize)> {
+        let all: Vec<usize> = (0..entries.len()).collect();
+        classify(entries, &all)
+    }
+
+    #[test]
+    fn many_passwords_for_one_account_is_brute_force() {
+        let entries: Vec<LogEntry> = (0..8).map(|m| failed(m, "203.0.113.5", "admin")).collect();
+        let attacks = run(&entries);
+        assert_eq!(attacks.len(), 1);
+        assert_eq!(attacks[0].0, ThreatType::BruteForce);
+        assert_eq!(attacks[0].1.accounts, vec!["admin"]);
+        assert_eq!(attacks[0].1.source_ips, vec!["203.0.113.5"]);
+        assert_eq!(attacks[0].1.first_seen, "2025-02-20T10:00:00Z");
+        assert_eq!(attacks[0].1.last_seen, "2025-02-20T10:07:00Z");
+    }
+
+    #[test]
+    fn one_password_across_many_accounts_is_spraying() {
+        let users = ["alice", "bob", "carol", "dave", "erin", "frank"];
+        let entries: Vec<LogEntry> = users.iter().enumerate().map(|(m, u)| failed(m as u32, "198.51.100.7", u)).collect();
+        let attacks = run(&entries);
+        assert_eq!(attacks.len(), 1);
+        assert_eq!(attacks[0].0, ThreatType::PasswordSpraying);
+        assert_eq!(attacks[0].1.accounts.len(), 6);
+        assert_eq!(attacks[0].1.severity, "High");
+    }
+
+    #[test]
+    fn many_user_ip_pairs_is_credential_stuffing() {
+        let entries: Vec<LogEntry> = (0..6)
+            .map(|i| failed(i, &format!("192.0.2.{}", i + 1), &format!("user{}", i)))
+            .collect();
+        let attacks = run(&entries);
+        assert_eq!(attacks.len(), 1);
+        assert_eq!(attacks[0].0, ThreatType::CredentialStuffing);
+        assert_eq!(attacks[0].1.source_ips.len(), 6);
+        assert_eq!(attacks[0].1.severity, "Critical");
+    }
+
+    #[test]
+    fn quiet_failures_and_separate_bursts_are_not_attacks() {
+        // Four failures is below the per-IP threshold; two bursts of three
+        // an hour apart do not add up either
+        let mut entries: Vec<LogEntry> = (0..4).map(|m| failed(m, "10.0.0.1", "bob")).collect();
+        entries.extend((0..3).map(|m| failed(m, "10.0.0.2", "carol")));
+        entries.extend((0..3).map(|m| {
+            let mut e = failed(m, "10.0.0.2", "carol");
+            e.timestamp = format!("2025-02-20T11:{:02}:00Z", m);
+            e
+        }));
+        assert!(run(&entries).is_empty());
+    }
+
+    fn accepted(minute: u32, ip: &str, user: &str) -> LogEntry {
+        let mut entry = failed(minute, ip, user);
+        entry.message = format!("Accepted password for {} from {}", user, ip);
+        entry
+    }
+
+    #[test]
+    fn success_after_failures_is_probable_compromise() {
+        let mut entries: Vec<LogEntry> = (0..4).map(|m| failed(m, "203.0.113.5", "admin")).collect();
+        entries.push(accepted(5, "203.0.113.5", "admin"));
+        let failures: Vec<usize> = (0..4).collect();
+        let findings = correlate_compromises(&entries, &failures, &[4]);
+        assert_eq!(findings.len(), 1);
+        let (threat, finding, anchor) = &findings[0];
+        assert_eq!(*anchor, 4);
+        assert_eq!(*threat, ThreatType::AccountCompromise);
+        assert_eq!(finding.severity, "Critical");
+        assert_eq!(finding.failed_attempts, 4);
+        assert_eq!(finding.accounts, vec!["admin"]);
+        assert_eq!(finding.last_seen, "2025-02-20T10:05:00Z");
+        assert_eq!(finding.evidence.len(), 5);
+        assert!(finding.evidence[4].contains("Accepted password for admin"));
+    }
+
+    #[test]
+    fn success_after_a_typo_or_long_ago_is_not_compromise() {
+        // One failure then success is a typo; failures two hours earlier are
+        // outside the window
+        let entries = vec![
+            failed(0, "10.0.0.1", "bob"),
+            accepted(1, "10.0.0.1", "bob"),
+            failed(0, "10.0.0.2", "carol"),
+            failed(1, "10.0.0.2", "carol"),
+            failed(2, "10.0.0.2", "carol"),
+            LogEntry { timestamp: "2025-02-20T12:30:00Z".to_string(), ..accepted(0, "10.0.0.2", "carol") },
+        ];
+        assert!(correlate_compromises(&entries, &[0, 2, 3, 4], &[1, 5]).is_empty());
+    }
+
+    #[test]
+    fn root_account_is_recovered_from_message() {
+        let mut entry = failed(0, "203.0.113.5", "root");
+        entry.username = None;
+        assert_eq!(account_name(&entry).as_deref(), Some("root"));
+    }
+}
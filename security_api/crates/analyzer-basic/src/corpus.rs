@@ -0,0 +1,358 @@
+// Labeled detection corpus and rule accuracy
+// A corpus file is a log with ground truth: every line carries the threat
+// types it should raise, `-` for a line that should raise nothing.
+//
+//     # comment
+//     sql_injection | 203.0.113.9 - - [15/Dec/2025:10:15:23 +0000] "GET /item?id=1' UNION SELECT ..."
+//     -             | 192.168.1.10 - - [15/Dec/2025:10:15:24 +0000] "GET /index.html HTTP/1.1" 200 512
+//
+// Each file is parsed and analyzed as one log, so the behavioral detectors
+// (brute force, scans, exfiltration) see the whole sequence. A line's
+// detections are the threat types of the findings on it; comparing them with
+// the labels gives per-threat-type precision, recall and F1. A baseline of
+// those scores is kept alongside the corpus, and a rule change that lowers
+// any threat type's recall below it is a regression.
+
+use crate::BasicAnalyzer;
+use security_common::cvss::ThreatType;
+use security_common::parsers;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Extension of corpus files when loading a directory
+pub const CORPUS_EXTENSION: &str = "corpus";
+/// Label for a line that should raise nothing
+const NO_THREAT: &str = "-";
+/// Recall drop tolerated before a threat type counts as regressed
+/// (rounding in a saved baseline)
+const RECALL_TOLERANCE: f64 = 1e-6;
+
+/// One log line with the threat types it should raise
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledLine {
+    /// 1-based line in the corpus file
+    pub line_number: usize,
+    pub text: String,
+    pub expected: BTreeSet<ThreatType>,
+}
+
+/// A labeled corpus file
+#[derive(Debug, Clone)]
+pub struct Corpus {
+    pub name: String,
+    pub lines: Vec<LabeledLine>,
+}
+
+impl Corpus {
+    /// Parse corpus text; unknown threat types and lines without a label are
+    /// errors naming the line
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let mut lines = Vec::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line_number = index + 1;
+            if raw.trim().is_empty() || raw.trim_start().starts_with('#') {
+                continue;
+            }
+            let Some((labels, log_line)) = raw.split_once('|') else {
+                return Err(format!("{}:{}: expected '<threat types> | <log line>'", name, line_number));
+            };
+            let log_line = log_line.strip_prefix(' ').unwrap_or(log_line);
+            if log_line.trim().is_empty() {
+                return Err(format!("{}:{}: empty log line", name, line_number));
+            }
+            let mut expected = BTreeSet::new();
+            for label in labels.split(',').map(str::trim).filter(|l| !l.is_empty() && *l != NO_THREAT) {
+                let threat = ThreatType::from_name(label)
+                    .ok_or_else(|| format!("{}:{}: unknown threat type '{}'", name, line_number, label))?;
+                expected.insert(threat);
+            }
+            lines.push(LabeledLine { line_number, text: log_line.to_string(), expected });
+        }
+        if lines.is_empty() {
+            return Err(format!("{}: no labeled lines", name));
+        }
+        Ok(Corpus { name: name.to_string(), lines })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Corpus::parse(&path.display().to_string(), &text)
+    }
+
+    /// Load corpus files; directories contribute their `*.corpus` files in
+    /// name order
+    pub fn load_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<Corpus>, String> {
+        let mut corpora = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            if path.is_dir() {
+                let mut files: Vec<_> = std::fs::read_dir(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == CORPUS_EXTENSION))
+                    .collect();
+                files.sort();
+                for file in files {
+                    corpora.push(Corpus::from_file(&file)?);
+                }
+            } else {
+                corpora.push(Corpus::from_file(path)?);
+            }
+        }
+        Ok(corpora)
+    }
+
+    /// The log lines without their labels
+    pub fn content(&self) -> String {
+        self.lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Detection accuracy for one threat type
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreatAccuracy {
+    pub threat_type: String,
+    /// Labeled lines it was detected on
+    pub true_positives: usize,
+    /// Lines it was detected on without the label
+    pub false_positives: usize,
+    /// Labeled lines it was missed on
+    pub false_negatives: usize,
+    /// 1.0 when it was never detected
+    pub precision: f64,
+    /// 1.0 when no line is labeled with it
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl ThreatAccuracy {
+    fn new(threat_type: &str, true_positives: usize, false_positives: usize, false_negatives: usize) -> Self {
+        let ratio = |hits: usize, misses: usize| if hits + misses == 0 { 1.0 } else { hits as f64 / (hits + misses) as f64 };
+        let precision = ratio(true_positives, false_positives);
+        let recall = ratio(true_positives, false_negatives);
+        let f1 = if precision + recall == 0.0 { 0.0 } else { 2.0 * precision * recall / (precision + recall) };
+        ThreatAccuracy {
+            threat_type: threat_type.to_string(),
+            true_positives,
+            false_positives,
+            false_negatives,
+            precision,
+            recall,
+            f1,
+        }
+    }
+}
+
+/// A line whose detections differ from its labels
+#[derive(Debug, Clone, Serialize)]
+pub struct Mismatch {
+    pub corpus: String,
+    pub line_number: usize,
+    pub expected: Vec<String>,
+    pub detected: Vec<String>,
+    /// The log line (first 200 characters)
+    pub text: String,
+}
+
+/// Accuracy of the analyzer over one or more corpora
+#[derive(Debug, Clone, Serialize)]
+pub struct AccuracyReport {
+    pub corpora: usize,
+    pub lines: usize,
+    /// Labeled lines that no parser produced an entry for
+    pub unparsed_lines: usize,
+    /// Per threat type, by name
+    pub threats: Vec<ThreatAccuracy>,
+    /// Micro-average over all threat types
+    pub overall: ThreatAccuracy,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Run the parsers and `analyzer` over each corpus and score the findings
+/// against the labels
+pub fn evaluate(analyzer: &BasicAnalyzer, corpora: &[Corpus]) -> AccuracyReport {
+    // threat type -> (true positives, false positives, false negatives)
+    let mut counts: BTreeMap<&'static str, (usize, usize, usize)> = BTreeMap::new();
+    let mut mismatches = Vec::new();
+    let mut lines = 0;
+    let mut unparsed_lines = 0;
+
+    for corpus in corpora {
+        let parsed = parsers::parse_content(&corpus.content());
+        let parsed_lines: BTreeSet<usize> = parsed.line_numbers.iter().copied().collect();
+        let result = analyzer.analyze_lines(&parsed.entries, &parsed.line_numbers);
+        let mut detected: BTreeMap<usize, BTreeSet<ThreatType>> = BTreeMap::new();
+        for finding in &result.findings {
+            detected.entry(finding.line_number).or_default().insert(finding.threat_type.clone());
+        }
+
+        for (index, line) in corpus.lines.iter().enumerate() {
+            lines += 1;
+            if !parsed_lines.contains(&(index + 1)) {
+                unparsed_lines += 1;
+            }
+            let found = detected.remove(&(index + 1)).unwrap_or_default();
+            for threat in line.expected.union(&found) {
+                let count = counts.entry(threat.as_str()).or_default();
+                match (line.expected.contains(threat), found.contains(threat)) {
+                    (true, true) => count.0 += 1,
+                    (false, true) => count.1 += 1,
+                    _ => count.2 += 1,
+                }
+            }
+            if found != line.expected {
+                mismatches.push(Mismatch {
+                    corpus: corpus.name.clone(),
+                    line_number: line.line_number,
+                    expected: line.expected.iter().map(|t| t.as_str().to_string()).collect(),
+                    detected: found.iter().map(|t| t.as_str().to_string()).collect(),
+                    text: line.text.chars().take(200).collect(),
+                });
+            }
+        }
+    }
+
+    let threats: Vec<ThreatAccuracy> =
+        counts.iter().map(|(threat, &(tp, fp, fn_))| ThreatAccuracy::new(threat, tp, fp, fn_)).collect();
+    let (tp, fp, fn_) = counts.values().fold((0, 0, 0), |acc, c| (acc.0 + c.0, acc.1 + c.1, acc.2 + c.2));
+    AccuracyReport {
+        corpora: corpora.len(),
+        lines,
+        unparsed_lines,
+        threats,
+        overall: ThreatAccuracy::new("overall", tp, fp, fn_),
+        mismatches,
+    }
+}
+
+/// Saved scores of one threat type
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BaselineScore {
+    pub precision: f64,
+    pub recall: f64,
+}
+
+/// Per-threat-type scores a corpus is expected to keep, as JSON
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub threats: BTreeMap<String, BaselineScore>,
+}
+
+impl Baseline {
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("Invalid baseline: {}", e))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Baseline::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// A threat type whose recall fell below the baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Regression {
+    pub threat_type: String,
+    pub baseline_recall: f64,
+    pub recall: f64,
+}
+
+impl AccuracyReport {
+    /// Scores to save as the new baseline (threat types with labeled lines)
+    pub fn baseline(&self) -> Baseline {
+        let threats = self
+            .threats
+            .iter()
+            .filter(|t| t.true_positives + t.false_negatives > 0)
+            .map(|t| (t.threat_type.clone(), BaselineScore { precision: t.precision, recall: t.recall }))
+            .collect();
+        Baseline { threats }
+    }
+
+    /// Threat types whose recall is below the baseline's; a baseline threat
+    /// type missing from the report was never detected nor labeled, and
+    /// counts as recall 0
+    pub fn recall_regressions(&self, baseline: &Baseline) -> Vec<Regression> {
+        baseline
+            .threats
+            .iter()
+            .filter_map(|(threat_type, score)| {
+                let recall = self.threats.iter().find(|t| &t.threat_type == threat_type).map_or(0.0, |t| t.recall);
+                (recall + RECALL_TOLERANCE < score.recall).then(|| Regression {
+                    threat_type: threat_type.clone(),
+                    baseline_recall: score.recall,
+                    recall,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use security_common::rules::RuleSet;
+
+    const BUNDLED: &[(&str, &str)] = &[
+        ("web.corpus", include_str!("../corpus/web.corpus")),
+        ("auth.corpus", include_str!("../corpus/auth.corpus")),
+    ];
+
+    fn bundled() -> Vec<Corpus> {
+        BUNDLED.iter().map(|(name, text)| Corpus::parse(name, text).unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_labels_and_rejects_unknown_threat_types() {
+        let corpus = Corpus::parse("t", "# header\n\nsql_injection, xss | GET /a\n- | GET /b\n").unwrap();
+        assert_eq!(corpus.lines.len(), 2);
+        assert_eq!(corpus.lines[0].line_number, 3);
+        assert_eq!(corpus.lines[0].expected, BTreeSet::from([ThreatType::SQLInjection, ThreatType::XSS]));
+        assert!(corpus.lines[1].expected.is_empty());
+        assert_eq!(corpus.content(), "GET /a\nGET /b");
+
+        let err = Corpus::parse("t", "sqli_typo | GET /a\n").unwrap_err();
+        assert!(err.contains("t:1") && err.contains("sqli_typo"), "{}", err);
+        assert!(Corpus::parse("t", "GET /a\n").is_err());
+    }
+
+    #[test]
+    fn scores_and_flags_recall_regressions() {
+        let mut report = AccuracyReport {
+            corpora: 1,
+            lines: 4,
+            unparsed_lines: 0,
+            threats: vec![ThreatAccuracy::new("sql_injection", 3, 1, 1)],
+            overall: ThreatAccuracy::new("overall", 3, 1, 1),
+            mismatches: Vec::new(),
+        };
+        assert_eq!(report.threats[0].precision, 0.75);
+        assert_eq!(report.threats[0].recall, 0.75);
+        assert_eq!(report.threats[0].f1, 0.75);
+
+        let baseline = report.baseline();
+        assert!(report.recall_regressions(&baseline).is_empty());
+        report.threats[0] = ThreatAccuracy::new("sql_injection", 2, 0, 2);
+        let regressions = report.recall_regressions(&baseline);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].recall, 0.5);
+        // Better precision does not hide lost recall, and lost labels count as 0
+        report.threats.clear();
+        assert_eq!(report.recall_regressions(&baseline)[0].recall, 0.0);
+        assert_eq!(Baseline::from_json(&baseline.to_json()).unwrap(), baseline);
+    }
+
+    #[test]
+    fn bundled_corpus_holds_its_baseline() {
+        let baseline = Baseline::from_json(include_str!("../corpus/baseline.json")).unwrap();
+        let report = evaluate(&BasicAnalyzer::with_rules(RuleSet::default_pack()), &bundled());
+        assert_eq!(report.unparsed_lines, 0);
+        let regressions = report.recall_regressions(&baseline);
+        assert!(regressions.is_empty(), "recall regressed: {:?}\nmismatches: {:#?}", regressions, report.mismatches);
+    }
+}
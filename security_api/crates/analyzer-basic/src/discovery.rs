@@ -0,0 +1,227 @@
+// Forced browsing / content discovery detection
+// Tools such as dirbuster, ffuf and gobuster walk a wordlist against the
+// server: hundreds of distinct paths in quick succession, most of them not
+// found. The detector looks at behavior only, per client IP, so a tool that
+// sends a browser user agent is still caught:
+// - enumeration: many requests, mostly 404/410, at a high distinct-path rate
+// - wordlist probing: several requests for paths on the bundled
+//   sensitive-path list (`sensitive_paths.txt`)
+// Sensitive probes that were answered with a 2xx status are exposures.
+
+use security_common::parsers::parse_timestamp;
+use security_common::{ContentDiscovery, LogEntry};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+/// The bundled sensitive-path wordlist
+pub const SENSITIVE_PATHS: &str = include_str!("sensitive_paths.txt");
+
+/// Requests from one client before its not-found ratio means anything
+const MIN_PROBES: usize = 20;
+/// Share of not-found responses for an enumeration
+const MIN_NOT_FOUND_RATIO: f64 = 0.5;
+/// Distinct paths per minute for an enumeration
+const MIN_PATHS_PER_MINUTE: f64 = 10.0;
+/// Distinct wordlist paths that make wordlist probing on their own
+const MIN_SENSITIVE_PROBES: usize = 5;
+
+struct Request<'a> {
+    index: usize,
+    path: &'a str,
+    status: Option<u16>,
+    user_agent: Option<&'a str>,
+}
+
+/// A content discovery run: the report, the index of its last request and
+/// the indices of the requests that exposed a sensitive path
+pub struct Run {
+    pub report: ContentDiscovery,
+    pub last_index: usize,
+    pub exposures: Vec<usize>,
+}
+
+/// Find content discovery runs among the web requests in `entries`, most
+/// requests first
+pub fn detect(entries: &[LogEntry]) -> Vec<Run> {
+    let mut by_ip: BTreeMap<&str, Vec<Request>> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let (Some(ip), Some(path)) = (entry.ip_address.as_deref(), entry.fields.get("http.path")) else {
+            continue;
+        };
+        by_ip.entry(ip).or_default().push(Request {
+            index,
+            path: path.split('?').next().unwrap_or(path),
+            status: entry.fields.get("http.status").and_then(|s| s.parse().ok()),
+            user_agent: entry.fields.get("http.user_agent").map(String::as_str),
+        });
+    }
+
+    let mut runs: Vec<Run> = by_ip
+        .into_iter()
+        .filter_map(|(ip, requests)| run(ip, &requests, entries))
+        .collect();
+    runs.sort_by(|a, b| b.report.requests.cmp(&a.report.requests).then_with(|| a.report.source_ip.cmp(&b.report.source_ip)));
+    runs
+}
+
+fn run(ip: &str, requests: &[Request], entries: &[LogEntry]) -> Option<Run> {
+    let paths: BTreeSet<&str> = requests.iter().map(|r| r.path).collect();
+    let not_found = requests.iter().filter(|r| matches!(r.status, Some(404 | 410))).count();
+    let not_found_ratio = not_found as f64 / requests.len() as f64;
+
+    let times: Vec<_> = requests.iter().filter_map(|r| parse_timestamp(&entries[r.index].timestamp)).collect();
+    let minutes = match (times.iter().min(), times.iter().max()) {
+        (Some(first), Some(last)) => ((*last - *first).num_seconds() as f64 / 60.0).max(1.0),
+        _ => 1.0,
+    };
+    let paths_per_minute = paths.len() as f64 / minutes;
+
+    let mut sensitive: Vec<&str> = Vec::new();
+    let mut exposures = Vec::new();
+    for request in requests.iter().filter(|r| is_sensitive(r.path)) {
+        if !sensitive.contains(&request.path) {
+            sensitive.push(request.path);
+        }
+        if request.status.is_some_and(|s| (200..300).contains(&s)) {
+            exposures.push(request.index);
+        }
+    }
+
+    let enumeration = requests.len() >= MIN_PROBES
+        && not_found_ratio >= MIN_NOT_FOUND_RATIO
+        && paths_per_minute >= MIN_PATHS_PER_MINUTE;
+    if !enumeration && sensitive.len() < MIN_SENSITIVE_PROBES {
+        return None;
+    }
+
+    let mut exposed: Vec<String> = Vec::new();
+    for &index in &exposures {
+        let path = requests.iter().find(|r| r.index == index).map(|r| r.path).unwrap_or_default();
+        if !exposed.iter().any(|p| p == path) {
+            exposed.push(path.to_string());
+        }
+    }
+    let mut user_agents: Vec<String> = Vec::new();
+    for agent in requests.iter().filter_map(|r| r.user_agent) {
+        if !user_agents.iter().any(|a| a == agent) {
+            user_agents.push(agent.to_string());
+        }
+    }
+
+    let mut description = format!(
+        "{} requested {} distinct paths ({:.0}% not found, {:.0} paths/min), {} on the sensitive-path list",
+        ip,
+        paths.len(),
+        not_found_ratio * 100.0,
+        paths_per_minute,
+        sensitive.len()
+    );
+    if !exposed.is_empty() {
+        description.push_str(&format!("; exposed: {}", exposed.join(", ")));
+    }
+
+    let first = requests[0].index;
+    let last = requests[requests.len() - 1].index;
+    Some(Run {
+        report: ContentDiscovery {
+            source_ip: ip.to_string(),
+            requests: requests.len(),
+            distinct_paths: paths.len(),
+            not_found_ratio,
+            paths_per_minute,
+            sensitive_probes: sensitive.into_iter().map(str::to_string).collect(),
+            exposed,
+            user_agents,
+            first_seen: entries[first].timestamp.clone(),
+            last_seen: entries[last].timestamp.clone(),
+            description,
+        },
+        last_index: last,
+        exposures,
+    })
+}
+
+/// Whether a request path (without query) is on the sensitive-path wordlist
+pub fn is_sensitive(path: &str) -> bool {
+    static WORDLIST: OnceLock<Vec<String>> = OnceLock::new();
+    let wordlist = WORDLIST.get_or_init(|| {
+        SENSITIVE_PATHS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_lowercase)
+            .collect()
+    });
+
+    let path = path.to_lowercase();
+    wordlist.iter().any(|entry| {
+        if entry.ends_with('/') {
+            path.contains(entry.as_str()) || path == entry[..entry.len() - 1]
+        } else {
+            path.ends_with(entry.as_str())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::request;
+
+    fn visit(second: u32, ip: &str, path: &str, status: u16, agent: &str) -> LogEntry {
+        request(second, ip, "GET", path, status, &[("http.user_agent", agent)])
+    }
+
+    const BROWSER: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64)";
+
+    #[test]
+    fn detects_enumeration_with_a_browser_user_agent() {
+        let mut entries: Vec<LogEntry> = (0..200)
+            .map(|i| visit(i / 4, "203.0.113.5", &format!("/dir{}", i), 404, BROWSER))
+            .collect();
+        entries.push(visit(50, "203.0.113.5", "/.git/config", 200, BROWSER));
+        entries.push(visit(51, "203.0.113.5", "/admin", 403, BROWSER));
+
+        let runs = detect(&entries);
+        assert_eq!(runs.len(), 1);
+        let report = &runs[0].report;
+        assert_eq!(report.requests, 202);
+        assert!(report.not_found_ratio > 0.9);
+        assert!(report.paths_per_minute >= MIN_PATHS_PER_MINUTE);
+        assert_eq!(report.sensitive_probes, vec!["/.git/config", "/admin"]);
+        assert_eq!(report.exposed, vec!["/.git/config"]);
+        assert_eq!(runs[0].exposures, vec![200]);
+        assert_eq!(report.user_agents, vec![BROWSER]);
+    }
+
+    #[test]
+    fn slow_wordlist_probing_is_caught() {
+        let probes = ["/.env", "/wp-config.php.bak", "/backup.zip", "/phpinfo.php", "/.git/HEAD", "/server-status"];
+        let entries: Vec<LogEntry> =
+            probes.iter().enumerate().map(|(i, p)| visit(i as u32 * 9, "198.51.100.3", p, 404, "curl/8.0")).collect();
+        let runs = detect(&entries);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].report.sensitive_probes.len(), 6);
+        assert!(runs[0].exposures.is_empty());
+    }
+
+    #[test]
+    fn ordinary_visitors_are_not_flagged() {
+        // A visitor with a few broken links, and an administrator using /admin
+        let mut entries: Vec<LogEntry> = (0..30)
+            .map(|i| visit(i * 5, "192.0.2.10", &format!("/post/{}", i % 12), if i % 10 == 0 { 404 } else { 200 }, BROWSER))
+            .collect();
+        entries.extend((0..10).map(|i| visit(i * 20, "192.0.2.11", "/admin/", 200, BROWSER)));
+        assert!(detect(&entries).is_empty());
+    }
+
+    #[test]
+    fn wordlist_matches_tails_and_directories() {
+        assert!(is_sensitive("/.env"));
+        assert!(is_sensitive("/app/.ENV"));
+        assert!(is_sensitive("/.git/refs/heads/main"));
+        assert!(is_sensitive("/phpmyadmin"));
+        assert!(!is_sensitive("/environment"));
+        assert!(!is_sensitive("/blog/admin-tips"));
+    }
+}
@@ -0,0 +1,308 @@
+// Data exfiltration detection
+// Works on web responses with a size (`http.size`). Three signals, each
+// reported at most once per client:
+// - volume: a client was served far more bytes than the others (robust
+//   z-score against the per-client totals, with an absolute floor)
+// - outsized responses: successful responses far above their endpoint's
+//   usual size, either repeated or after a SQL injection request from the
+//   same client was answered successfully
+// - archive downloads: backups, database dumps and archives served
+//   successfully
+
+use crate::stats::Baseline;
+use security_common::{Exfiltration, LogEntry};
+use std::collections::{BTreeMap, HashSet};
+
+/// Robust z-score from which a size counts as unusual
+const MIN_Z_SCORE: f64 = 3.5;
+/// Bytes served to one client below which its volume is never flagged
+const MIN_VOLUME_BYTES: u64 = 50 * 1024 * 1024;
+/// Responses an endpoint needs before its usual size is known
+const MIN_BASELINE_RESPONSES: usize = 5;
+/// How many times the usual size a response (or a client's volume) must be
+const OUTSIZED_FACTOR: f64 = 10.0;
+/// Size below which a response is never outsized
+const MIN_OUTSIZED_BYTES: u64 = 100 * 1024;
+/// Outsized responses to one client that make a finding on their own
+const REPEATED_OUTSIZED: usize = 3;
+/// Paths ending in these are backups, dumps or archives
+const ARCHIVE_EXTENSIONS: &[&str] = &[".sql", ".zip", ".tar", ".gz", ".tgz", ".bz2", ".7z", ".rar", ".bak", ".dump"];
+
+struct Response<'a> {
+    index: usize,
+    ip: &'a str,
+    /// Path without the query string
+    endpoint: &'a str,
+    path: &'a str,
+    status: u16,
+    size: u64,
+}
+
+impl Response<'_> {
+    fn succeeded(&self) -> bool {
+        (200..400).contains(&self.status)
+    }
+}
+
+/// Find exfiltration signals among the web responses in `entries`, each with
+/// the index of the entry that completed it. `sql_injections` are the indices
+/// of entries flagged as SQL injection.
+pub fn detect(entries: &[LogEntry], sql_injections: &[usize]) -> Vec<(Exfiltration, usize)> {
+    let responses: Vec<Response> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let path = entry.fields.get("http.path")?;
+            Some(Response {
+                index,
+                ip: entry.ip_address.as_deref()?,
+                endpoint: path.split('?').next().unwrap_or(path),
+                path,
+                status: entry.fields.get("http.status")?.parse().ok()?,
+                size: entry.fields.get("http.size")?.parse().ok()?,
+            })
+        })
+        .collect();
+
+    // First SQL injection per client that the server answered without error
+    let sql_injections: HashSet<usize> = sql_injections.iter().copied().collect();
+    let mut injected: BTreeMap<&str, usize> = BTreeMap::new();
+    for response in responses.iter().filter(|r| r.succeeded() && sql_injections.contains(&r.index)) {
+        injected.entry(response.ip).or_insert(response.index);
+    }
+
+    let mut found = volume(&responses, entries);
+    found.extend(outsized(&responses, &injected, entries));
+    found.extend(archives(&responses, entries));
+    found.sort_by(|(a, _), (b, _)| b.bytes.cmp(&a.bytes).then_with(|| a.source_ip.cmp(&b.source_ip)));
+    found
+}
+
+fn volume(responses: &[Response], entries: &[LogEntry]) -> Vec<(Exfiltration, usize)> {
+    let mut by_ip: BTreeMap<&str, Vec<&Response>> = BTreeMap::new();
+    for response in responses {
+        by_ip.entry(response.ip).or_default().push(response);
+    }
+    let totals: BTreeMap<&str, u64> =
+        by_ip.iter().map(|(ip, responses)| (*ip, responses.iter().map(|r| r.size).sum())).collect();
+    let Some(baseline) = Baseline::of(totals.values().map(|&t| t as f64).collect(), 0) else {
+        return Vec::new();
+    };
+
+    totals
+        .iter()
+        .filter(|&(_, &total)| {
+            total >= MIN_VOLUME_BYTES
+                && total as f64 >= OUTSIZED_FACTOR * baseline.median
+                && baseline.z_score(total as f64) >= MIN_Z_SCORE
+        })
+        .map(|(ip, total)| {
+            let description = format!(
+                "{} downloaded {} in {} responses (typical client: {})",
+                ip,
+                human_bytes(*total),
+                by_ip[ip].len(),
+                human_bytes(baseline.median as u64)
+            );
+            report("volume", ip, &by_ip[ip], false, description, entries)
+        })
+        .collect()
+}
+
+fn outsized(
+    responses: &[Response],
+    injected: &BTreeMap<&str, usize>,
+    entries: &[LogEntry],
+) -> Vec<(Exfiltration, usize)> {
+    let mut by_endpoint: BTreeMap<&str, Vec<&Response>> = BTreeMap::new();
+    for response in responses.iter().filter(|r| r.succeeded()) {
+        by_endpoint.entry(response.endpoint).or_default().push(response);
+    }
+
+    let mut by_ip: BTreeMap<&str, Vec<&Response>> = BTreeMap::new();
+    for endpoint in by_endpoint.values().filter(|r| r.len() >= MIN_BASELINE_RESPONSES) {
+        let Some(baseline) = Baseline::of(endpoint.iter().map(|r| r.size as f64).collect(), 0) else {
+            continue;
+        };
+        for response in endpoint {
+            let size = response.size as f64;
+            if response.size >= MIN_OUTSIZED_BYTES
+                && size >= OUTSIZED_FACTOR * baseline.median
+                && baseline.z_score(size) >= MIN_Z_SCORE
+            {
+                by_ip.entry(response.ip).or_default().push(response);
+            }
+        }
+    }
+
+    by_ip
+        .into_iter()
+        .filter_map(|(ip, mut pulled)| {
+            pulled.sort_by_key(|r| r.index);
+            let after_injection = injected.get(ip).is_some_and(|&first| pulled.iter().any(|r| r.index > first));
+            if pulled.len() < REPEATED_OUTSIZED && !after_injection {
+                return None;
+            }
+            let mut description = format!(
+                "{} received {} outsized response{} ({}) from {}",
+                ip,
+                pulled.len(),
+                if pulled.len() == 1 { "" } else { "s" },
+                human_bytes(pulled.iter().map(|r| r.size).sum()),
+                distinct(pulled.iter().map(|r| r.endpoint)).join(", ")
+            );
+            if after_injection {
+                description.push_str(" after a successful SQL injection");
+            }
+            Some(report("outsized_responses", ip, &pulled, after_injection, description, entries))
+        })
+        .collect()
+}
+
+fn archives(responses: &[Response], entries: &[LogEntry]) -> Vec<(Exfiltration, usize)> {
+    let mut by_ip: BTreeMap<&str, Vec<&Response>> = BTreeMap::new();
+    for response in responses.iter().filter(|r| (200..300).contains(&r.status) && is_archive(r.endpoint)) {
+        by_ip.entry(response.ip).or_default().push(response);
+    }
+
+    by_ip
+        .into_iter()
+        .map(|(ip, downloads)| {
+            let description = format!(
+                "{} downloaded {} backup or archive file{} ({}): {}",
+                ip,
+                downloads.len(),
+                if downloads.len() == 1 { "" } else { "s" },
+                human_bytes(downloads.iter().map(|r| r.size).sum()),
+                distinct(downloads.iter().map(|r| r.endpoint)).join(", ")
+            );
+            report("archive_download", ip, &downloads, false, description, entries)
+        })
+        .collect()
+}
+
+fn is_archive(path: &str) -> bool {
+    let path = path.to_lowercase();
+    ARCHIVE_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+fn distinct<'a>(items: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen: Vec<String> = Vec::new();
+    for item in items {
+        if !seen.iter().any(|s| s == item) {
+            seen.push(item.to_string());
+        }
+    }
+    seen
+}
+
+fn report(
+    kind: &str,
+    ip: &str,
+    responses: &[&Response],
+    after_sql_injection: bool,
+    description: String,
+    entries: &[LogEntry],
+) -> (Exfiltration, usize) {
+    let first = responses.iter().map(|r| r.index).min().unwrap_or(0);
+    let last = responses.iter().map(|r| r.index).max().unwrap_or(0);
+    let exfiltration = Exfiltration {
+        source_ip: ip.to_string(),
+        kind: kind.to_string(),
+        requests: responses.len(),
+        bytes: responses.iter().map(|r| r.size).sum(),
+        paths: distinct(responses.iter().map(|r| r.path)),
+        after_sql_injection,
+        first_seen: entries[first].timestamp.clone(),
+        last_seen: entries[last].timestamp.clone(),
+        description,
+    };
+    (exfiltration, last)
+}
+
+/// Byte count in binary units (`512 B`, `1.5 KiB`, `20.0 MiB`)
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::request;
+
+    fn response(index: u32, ip: &str, path: &str, status: u16, size: u64) -> LogEntry {
+        request(index, ip, "GET", path, status, &[("http.size", &size.to_string())])
+    }
+
+    /// Ten clients reading a small JSON API
+    fn background() -> Vec<LogEntry> {
+        (0..100).map(|i| response(i, &format!("192.0.2.{}", i % 10 + 1), "/api/users?page=1", 200, 2048 + i as u64)).collect()
+    }
+
+    fn kinds(found: &[(Exfiltration, usize)]) -> Vec<(&str, &str)> {
+        found.iter().map(|(e, _)| (e.kind.as_str(), e.source_ip.as_str())).collect()
+    }
+
+    #[test]
+    fn normal_traffic_is_quiet() {
+        assert!(detect(&background(), &[]).is_empty());
+    }
+
+    #[test]
+    fn outsized_responses_after_sql_injection() {
+        let mut entries = background();
+        let injection = entries.len();
+        entries.push(response(100, "203.0.113.5", "/api/users?id=1%27%20UNION%20SELECT", 200, 3000));
+        entries.push(response(101, "203.0.113.5", "/api/users?id=1%20OR%201=1", 200, 4 * 1024 * 1024));
+
+        // A single outsized response is only reported after the injection
+        assert!(detect(&entries, &[]).is_empty());
+        let found = detect(&entries, &[injection]);
+        assert_eq!(kinds(&found), vec![("outsized_responses", "203.0.113.5")]);
+        let (exfil, index) = &found[0];
+        assert!(exfil.after_sql_injection);
+        assert_eq!(exfil.bytes, 4 * 1024 * 1024);
+        assert_eq!(*index, injection + 1);
+        assert!(exfil.description.ends_with("after a successful SQL injection"));
+    }
+
+    #[test]
+    fn repeated_outsized_and_archive_downloads() {
+        let mut entries = background();
+        for i in 0..3 {
+            entries.push(response(100 + i, "203.0.113.6", &format!("/api/users?page={}&size=100000", i), 200, 900_000));
+        }
+        entries.push(response(110, "198.51.100.4", "/backup/db.sql.gz", 200, 20_000_000));
+        entries.push(response(111, "198.51.100.4", "/site.tar.gz", 200, 5_000_000));
+        // Missing files are not downloads
+        entries.push(response(112, "198.51.100.9", "/backup.zip", 404, 300));
+
+        let found = detect(&entries, &[]);
+        assert_eq!(
+            kinds(&found),
+            vec![("archive_download", "198.51.100.4"), ("outsized_responses", "203.0.113.6")]
+        );
+        assert_eq!(found[0].0.paths, vec!["/backup/db.sql.gz", "/site.tar.gz"]);
+        assert_eq!(found[1].0.requests, 3);
+    }
+
+    #[test]
+    fn flags_client_volume_far_above_the_rest() {
+        let mut entries = background();
+        entries.extend((0..60).map(|i| response(200 + i, "203.0.113.7", &format!("/media/{}.mp4", i), 200, 2_000_000)));
+        let found = detect(&entries, &[]);
+        assert!(kinds(&found).contains(&("volume", "203.0.113.7")));
+        assert_eq!(human_bytes(120_000_000), "114.4 MiB");
+        assert_eq!(human_bytes(512), "512 B");
+    }
+}
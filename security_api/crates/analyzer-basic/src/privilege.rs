@@ -0,0 +1,133 @@
+// First-time privilege escalation
+// Administrators use sudo every day; an account that has never escalated
+// suddenly doing so is what a compromised or newly backdoored account looks
+// like. The first part of the log's escalation timeline is the baseline:
+// users seen escalating there are known. A user whose first successful
+// sudo, su or pkexec comes after it is escalating for the first time. A log
+// that covers too little time has no meaningful baseline and is skipped.
+
+use chrono::{DateTime, Duration, Utc};
+use security_common::parsers::parse_timestamp;
+use security_common::LogEntry;
+use std::collections::BTreeSet;
+
+/// Share of the escalation timeline that makes up the baseline
+const BASELINE_SHARE: f64 = 0.25;
+/// Time the escalations must span before there is a baseline
+const MIN_SPAN_HOURS: i64 = 12;
+
+/// A user's first escalation, after the baseline period
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirstEscalation {
+    pub user: String,
+    pub target_user: String,
+    /// `sudo`, `su` or `pkexec`
+    pub tool: String,
+    pub index: usize,
+}
+
+/// Find users whose first successful escalation comes after the baseline
+pub fn detect(entries: &[LogEntry]) -> Vec<FirstEscalation> {
+    let mut escalations: Vec<(usize, DateTime<Utc>, &str)> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| {
+            matches!(entry.fields.get("privilege.outcome").map(String::as_str), Some("success" | "session_opened"))
+        })
+        .filter_map(|(index, entry)| {
+            let user = entry.fields.get("privilege.user")?;
+            if entry.fields.get("privilege.target_user") == Some(user) {
+                return None;
+            }
+            Some((index, parse_timestamp(&entry.timestamp)?, user.as_str()))
+        })
+        .collect();
+    // Merged logs are not in time order; a user's first escalation is the
+    // earliest one, wherever it sits in the file
+    escalations.sort_by_key(|&(index, time, _)| (time, index));
+
+    let (Some(&(_, first, _)), Some(&(_, last, _))) = (escalations.first(), escalations.last()) else {
+        return Vec::new();
+    };
+    let span = last - first;
+    if span < Duration::hours(MIN_SPAN_HOURS) {
+        return Vec::new();
+    }
+    let baseline_end = first + Duration::seconds((span.num_seconds() as f64 * BASELINE_SHARE) as i64);
+
+    let mut known: BTreeSet<&str> =
+        escalations.iter().filter(|(_, time, _)| *time <= baseline_end).map(|(_, _, user)| *user).collect();
+    let mut found = Vec::new();
+    for &(index, time, user) in &escalations {
+        if time > baseline_end && known.insert(user) {
+            let field = |name: &str| entries[index].fields.get(name).cloned().unwrap_or_default();
+            found.push(FirstEscalation {
+                user: user.to_string(),
+                target_user: field("privilege.target_user"),
+                tool: field("privilege.tool"),
+                index,
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::entry;
+
+    fn sudo(hour: u32, user: &str, outcome: &str) -> LogEntry {
+        let fields = [
+            ("privilege.tool", "sudo"),
+            ("privilege.user", user),
+            ("privilege.target_user", "root"),
+            ("privilege.command", "/usr/bin/systemctl restart nginx"),
+            ("privilege.outcome", outcome),
+        ];
+        entry(
+            format!("2024-03-{:02}T{:02}:00:00Z", 1 + hour / 24, hour % 24),
+            None,
+            Some(user),
+            format!("web01 sudo: {} : COMMAND=/usr/bin/systemctl restart nginx", user),
+            &fields,
+        )
+    }
+
+    #[test]
+    fn flags_users_escalating_after_the_baseline() {
+        let mut entries: Vec<LogEntry> = (0..48).step_by(4).map(|h| sudo(h, "alice", "success")).collect();
+        entries.push(sudo(5, "bob", "success"));
+        // mallory was refused at first, then got in
+        entries.push(sudo(30, "mallory", "not_in_sudoers"));
+        entries.push(sudo(40, "mallory", "success"));
+        entries.push(sudo(41, "mallory", "success"));
+
+        let found = detect(&entries);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].user, "mallory");
+        assert_eq!((found[0].tool.as_str(), found[0].target_user.as_str()), ("sudo", "root"));
+        assert_eq!(found[0].index, entries.len() - 2);
+    }
+
+    #[test]
+    fn merged_logs_are_read_in_time_order() {
+        // A second host's log appended after the first: mallory's earlier
+        // escalation sits at the end of the file
+        let mut entries: Vec<LogEntry> = (0..48).step_by(4).map(|h| sudo(h, "alice", "success")).collect();
+        entries.push(sudo(44, "mallory", "success"));
+        entries.push(sudo(2, "alice", "success"));
+        entries.push(sudo(30, "mallory", "success"));
+
+        let found = detect(&entries);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].user, "mallory");
+        assert_eq!(found[0].index, entries.len() - 1);
+    }
+
+    #[test]
+    fn short_logs_have_no_baseline() {
+        let entries = vec![sudo(0, "alice", "success"), sudo(2, "mallory", "success"), sudo(4, "alice", "success")];
+        assert!(detect(&entries).is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+// Request rate anomaly detection
+// Web requests (entries with `http.path` and a usable timestamp) are counted
+// in one-minute buckets and compared with a robust baseline (median and MAD):
+// - per IP, against the per-minute rates of every client in the log, so a
+//   client far busier than the rest stands out. A short run of such minutes
+//   is a burst, a run of FLOOD_MINUTES or more a flood.
+// - per endpoint, 5xx responses against that endpoint's own history over the
+//   whole time span of the log, quiet minutes included
+// Each check also has an absolute floor, so a small log in which one visitor
+// loads a handful of pages flags nothing.
+
+use crate::stats::Baseline;
+use security_common::parsers::parse_timestamp;
+use security_common::{LogEntry, RateAnomaly};
+use std::collections::{BTreeMap, BTreeSet};
+
+const BUCKET_SECONDS: i64 = 60;
+/// Robust z-score from which a minute counts as anomalous
+const MIN_Z_SCORE: f64 = 3.5;
+/// Requests per minute from one IP below which nothing is flagged
+const MIN_IP_REQUESTS_PER_MINUTE: usize = 30;
+/// 5xx responses per minute on one endpoint below which nothing is flagged
+const MIN_ERRORS_PER_MINUTE: usize = 5;
+/// Anomalous minutes in a row that make a flood rather than a burst
+const FLOOD_MINUTES: usize = 5;
+/// Share of distinct paths among a client's requests from which it looks
+/// like a crawler rather than a flood of the same few pages
+const SCRAPING_PATH_RATIO: f64 = 0.5;
+
+struct Request<'a> {
+    index: usize,
+    minute: i64,
+    ip: Option<&'a str>,
+    /// Path with the query string, as requested
+    path: &'a str,
+    /// Path without the query string
+    endpoint: &'a str,
+    status: Option<u16>,
+}
+
+/// Requests per one-minute bucket
+type Buckets<'a, 'r> = BTreeMap<i64, Vec<&'r Request<'a>>>;
+
+/// Find per-IP bursts and floods and per-endpoint 5xx spikes among the web
+/// requests in `entries`, strongest first
+pub fn detect(entries: &[LogEntry]) -> Vec<RateAnomaly> {
+    let requests: Vec<Request> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let path = entry.fields.get("http.path")?;
+            let time = parse_timestamp(&entry.timestamp)?;
+            Some(Request {
+                index,
+                minute: time.timestamp().div_euclid(BUCKET_SECONDS),
+                ip: entry.ip_address.as_deref(),
+                path,
+                endpoint: path.split('?').next().unwrap_or(path),
+                status: entry.fields.get("http.status").and_then(|s| s.parse().ok()),
+            })
+        })
+        .collect();
+
+    let mut anomalies = client_anomalies(&requests, entries);
+    anomalies.extend(error_spikes(&requests, entries));
+    anomalies.sort_by(|a, b| b.z_score.total_cmp(&a.z_score).then_with(|| a.subject.cmp(&b.subject)));
+    anomalies
+}
+
+fn client_anomalies(requests: &[Request], entries: &[LogEntry]) -> Vec<RateAnomaly> {
+    let mut by_ip: BTreeMap<&str, Buckets> = BTreeMap::new();
+    for request in requests {
+        if let Some(ip) = request.ip {
+            by_ip.entry(ip).or_default().entry(request.minute).or_default().push(request);
+        }
+    }
+    // Every active (client, minute) pair is one sample of normal traffic
+    let samples = by_ip.values().flat_map(|buckets| buckets.values().map(|b| b.len() as f64)).collect();
+    let Some(baseline) = Baseline::of(samples, 0) else {
+        return Vec::new();
+    };
+
+    let mut anomalies = Vec::new();
+    for (ip, buckets) in &by_ip {
+        let hot = buckets.iter().filter(|(_, bucket)| {
+            bucket.len() >= MIN_IP_REQUESTS_PER_MINUTE && baseline.z_score(bucket.len() as f64) >= MIN_Z_SCORE
+        });
+        for run in consecutive(hot) {
+            let requests: Vec<&Request> = run.iter().flat_map(|(_, bucket)| bucket.iter().copied()).collect();
+            let paths: BTreeSet<&str> = requests.iter().map(|r| r.path).collect();
+            let errors = requests.iter().filter(|r| r.status.is_some_and(|s| s >= 400)).count();
+            let pattern = if paths.len() as f64 >= SCRAPING_PATH_RATIO * requests.len() as f64 {
+                "scraping"
+            } else if errors * 2 >= requests.len() {
+                "exploitation_loop"
+            } else {
+                "dos"
+            };
+            let mut anomaly = report(&run, &requests, &baseline, entries);
+            anomaly.kind = if run.len() >= FLOOD_MINUTES { "flood" } else { "burst" }.to_string();
+            anomaly.pattern = pattern.to_string();
+            anomaly.scope = "ip".to_string();
+            anomaly.subject = ip.to_string();
+            anomaly.distinct = paths.len();
+            anomaly.description = format!(
+                "{} sent {} requests in {} min (peak {}/min, baseline {:.1}/min, z={:.1}) to {} distinct paths, {} errors",
+                ip,
+                anomaly.requests,
+                anomaly.minutes,
+                anomaly.peak_per_minute,
+                anomaly.baseline_per_minute,
+                anomaly.z_score,
+                paths.len(),
+                errors
+            );
+            anomalies.push(anomaly);
+        }
+    }
+    anomalies
+}
+
+fn error_spikes(requests: &[Request], entries: &[LogEntry]) -> Vec<RateAnomaly> {
+    let (Some(start), Some(end)) = (
+        requests.iter().map(|r| r.minute).min(),
+        requests.iter().map(|r| r.minute).max(),
+    ) else {
+        return Vec::new();
+    };
+    let span = (end - start + 1) as usize;
+
+    let mut by_endpoint: BTreeMap<&str, Buckets> = BTreeMap::new();
+    for request in requests.iter().filter(|r| r.status.is_some_and(|s| s >= 500)) {
+        by_endpoint.entry(request.endpoint).or_default().entry(request.minute).or_default().push(request);
+    }
+
+    let mut anomalies = Vec::new();
+    for (endpoint, buckets) in &by_endpoint {
+        let samples = buckets.values().map(|b| b.len() as f64).collect();
+        let Some(baseline) = Baseline::of(samples, span - buckets.len()) else {
+            continue;
+        };
+        let hot = buckets.iter().filter(|(_, bucket)| {
+            bucket.len() >= MIN_ERRORS_PER_MINUTE && baseline.z_score(bucket.len() as f64) >= MIN_Z_SCORE
+        });
+        for run in consecutive(hot) {
+            let errors: Vec<&Request> = run.iter().flat_map(|(_, bucket)| bucket.iter().copied()).collect();
+            let clients: BTreeSet<&str> = errors.iter().filter_map(|r| r.ip).collect();
+            let mut anomaly = report(&run, &errors, &baseline, entries);
+            anomaly.kind = "error_spike".to_string();
+            anomaly.pattern = "server_errors".to_string();
+            anomaly.scope = "endpoint".to_string();
+            anomaly.subject = endpoint.to_string();
+            anomaly.distinct = clients.len();
+            anomaly.description = format!(
+                "{} returned {} server errors in {} min (peak {}/min, baseline {:.1}/min, z={:.1}) to {} clients",
+                endpoint,
+                anomaly.requests,
+                anomaly.minutes,
+                anomaly.peak_per_minute,
+                anomaly.baseline_per_minute,
+                anomaly.z_score,
+                clients.len()
+            );
+            anomalies.push(anomaly);
+        }
+    }
+    anomalies
+}
+
+/// Split minutes (in ascending order) into runs of adjacent minutes
+fn consecutive<'b, T>(minutes: impl Iterator<Item = (&'b i64, T)>) -> Vec<Vec<(i64, T)>> {
+    let mut runs: Vec<Vec<(i64, T)>> = Vec::new();
+    for (&minute, bucket) in minutes {
+        match runs.last_mut() {
+            Some(run) if run.last().is_some_and(|(last, _)| *last + 1 == minute) => run.push((minute, bucket)),
+            _ => runs.push(vec![(minute, bucket)]),
+        }
+    }
+    runs
+}
+
+/// The measurements shared by every kind of anomaly; the caller fills in
+/// what it is and what it looks like
+fn report(
+    run: &[(i64, &Vec<&Request>)],
+    requests: &[&Request],
+    baseline: &Baseline,
+    entries: &[LogEntry],
+) -> RateAnomaly {
+    let peak = run.iter().map(|(_, bucket)| bucket.len()).max().unwrap_or(0);
+    let first = requests.iter().map(|r| r.index).min().unwrap_or(0);
+    let last = requests.iter().map(|r| r.index).max().unwrap_or(0);
+    RateAnomaly {
+        kind: String::new(),
+        pattern: String::new(),
+        scope: String::new(),
+        subject: String::new(),
+        requests: requests.len(),
+        minutes: run.len(),
+        peak_per_minute: peak,
+        baseline_per_minute: round2(baseline.median),
+        z_score: round2(baseline.z_score(peak as f64)),
+        distinct: 0,
+        first_seen: entries[first].timestamp.clone(),
+        last_seen: entries[last].timestamp.clone(),
+        description: String::new(),
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::get;
+
+    /// Twenty visitors browsing a few pages a minute for half an hour
+    fn background() -> Vec<LogEntry> {
+        (0..600u32)
+            .map(|i| get(i * 3, &format!("192.0.2.{}", i % 20 + 1), &format!("/page/{}", i % 7), 200))
+            .collect()
+    }
+
+    #[test]
+    fn normal_browsing_is_not_anomalous() {
+        assert!(detect(&background()).is_empty());
+        // The old "three requests" rule would have flagged this visitor
+        let few: Vec<LogEntry> = (0..5).map(|i| get(i * 10, "198.51.100.9", "/", 200)).collect();
+        assert!(detect(&few).is_empty());
+    }
+
+    #[test]
+    fn flags_sustained_flood_and_short_burst() {
+        let mut entries = background();
+        // 80 requests a minute to the same page for six minutes
+        entries.extend((0..480u32).map(|i| get(300 + i * 3 / 4, "203.0.113.5", "/", 200)));
+        // A crawler: 60 distinct pages within one minute
+        entries.extend((0..60u32).map(|i| get(1200 + i, "203.0.113.6", &format!("/item/{}", i), 200)));
+
+        let anomalies = detect(&entries);
+        let flood = anomalies.iter().find(|a| a.subject == "203.0.113.5").unwrap();
+        assert_eq!(flood.kind, "flood");
+        assert_eq!(flood.pattern, "dos");
+        assert_eq!(flood.scope, "ip");
+        assert_eq!(flood.minutes, 6);
+        assert_eq!(flood.requests, 480);
+        assert!(flood.z_score >= MIN_Z_SCORE);
+
+        let crawl = anomalies.iter().find(|a| a.subject == "203.0.113.6").unwrap();
+        assert_eq!(crawl.kind, "burst");
+        assert_eq!(crawl.pattern, "scraping");
+        assert_eq!(crawl.distinct, 60);
+        assert_eq!(anomalies.len(), 2);
+    }
+
+    #[test]
+    fn flags_exploitation_loop_and_5xx_spike() {
+        let mut entries = background();
+        // One client hammering a search endpoint that keeps failing
+        entries.extend((0..120u32).map(|i| get(900 + i / 2, "203.0.113.7", &format!("/search?q={}", i % 3), 500)));
+
+        let anomalies = detect(&entries);
+        let client = anomalies.iter().find(|a| a.scope == "ip").unwrap();
+        assert_eq!(client.subject, "203.0.113.7");
+        assert_eq!(client.pattern, "exploitation_loop");
+
+        let spike = anomalies.iter().find(|a| a.scope == "endpoint").unwrap();
+        assert_eq!(spike.kind, "error_spike");
+        assert_eq!(spike.subject, "/search");
+        assert_eq!(spike.requests, 120);
+        assert_eq!(spike.distinct, 1);
+        assert_eq!(spike.baseline_per_minute, 0.0);
+    }
+}
@@ -0,0 +1,263 @@
+// Port scan detection
+// Works on firewall records (entries with `firewall.*` fields). Probes are
+// grouped per source IP and counted in a sliding time window:
+// - vertical scan: many destination ports on one host
+// - horizontal scan: one destination port across many hosts
+// Once a scan is found, later probes of the same host (or port) within the
+// window extend it rather than opening a new one.
+
+use chrono::{DateTime, Duration, Utc};
+use security_common::parsers::parse_timestamp;
+use security_common::{LogEntry, PortScan};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Probes further apart than this are not counted together
+const SCAN_WINDOW_MINUTES: i64 = 10;
+/// Distinct ports on one host within the window for a vertical scan
+const VERTICAL_MIN_PORTS: usize = 15;
+/// Distinct hosts on one port within the window for a horizontal scan
+const HORIZONTAL_MIN_HOSTS: usize = 10;
+
+struct Probe<'a> {
+    index: usize,
+    time: Option<DateTime<Utc>>,
+    dst: &'a str,
+    port: u16,
+    blocked: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Kind {
+    Vertical,
+    Horizontal,
+}
+
+/// A scan being assembled: the probes that belong to it, by position
+struct OpenScan {
+    kind: Kind,
+    probes: BTreeSet<usize>,
+    last: Option<DateTime<Utc>>,
+}
+
+/// Find port scans among the firewall records in `entries`, each with the
+/// index of its last probe
+pub fn detect(entries: &[LogEntry]) -> Vec<(PortScan, usize)> {
+    let mut by_source: BTreeMap<&str, Vec<Probe>> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let (Some(src), Some(dst), Some(port)) = (
+            entry.fields.get("firewall.src_ip"),
+            entry.fields.get("firewall.dst_ip"),
+            entry.fields.get("firewall.dst_port").and_then(|p| p.parse().ok()),
+        ) else {
+            continue;
+        };
+        by_source.entry(src).or_default().push(Probe {
+            index,
+            time: parse_timestamp(&entry.timestamp),
+            dst,
+            port,
+            blocked: entry.fields.get("firewall.action").is_some_and(|a| a == "block"),
+        });
+    }
+
+    let mut scans: Vec<(PortScan, usize)> = by_source
+        .into_iter()
+        .flat_map(|(source, probes)| {
+            // Probes without a timestamp (bare kernel records) are a group of
+            // their own; mixed into the timed ones they would pin the window
+            let (mut timed, untimed): (Vec<Probe>, Vec<Probe>) = probes.into_iter().partition(|p| p.time.is_some());
+            timed.sort_by_key(|p| (p.time, p.index));
+            let mut scans = scans_from(source, &timed, entries);
+            scans.extend(scans_from(source, &untimed, entries));
+            scans
+        })
+        .collect();
+    scans.sort_by(|(a, _), (b, _)| b.events.cmp(&a.events).then_with(|| a.source_ip.cmp(&b.source_ip)));
+    scans
+}
+
+fn within_window(earlier: Option<DateTime<Utc>>, later: Option<DateTime<Utc>>) -> bool {
+    match (earlier, later) {
+        (Some(a), Some(b)) => b - a <= Duration::minutes(SCAN_WINDOW_MINUTES),
+        // Untimed probes are grouped apart and can only be taken together
+        _ => true,
+    }
+}
+
+fn scans_from(source: &str, probes: &[Probe], entries: &[LogEntry]) -> Vec<(PortScan, usize)> {
+    // Window contents: ports seen per host and hosts seen per port, with counts
+    let mut ports_by_host: HashMap<&str, HashMap<u16, usize>> = HashMap::new();
+    let mut hosts_by_port: HashMap<u16, HashMap<&str, usize>> = HashMap::new();
+    let mut start = 0;
+
+    let mut open: HashMap<(Kind, String), OpenScan> = HashMap::new();
+    let mut finished: Vec<OpenScan> = Vec::new();
+
+    for (end, probe) in probes.iter().enumerate() {
+        while !within_window(probes[start].time, probe.time) {
+            let old = &probes[start];
+            decrement(ports_by_host.get_mut(old.dst), &old.port);
+            decrement(hosts_by_port.get_mut(&old.port), &old.dst);
+            start += 1;
+        }
+        *ports_by_host.entry(probe.dst).or_default().entry(probe.port).or_insert(0) += 1;
+        *hosts_by_port.entry(probe.port).or_default().entry(probe.dst).or_insert(0) += 1;
+
+        let candidates = [
+            (Kind::Vertical, probe.dst.to_string(), ports_by_host[probe.dst].len() >= VERTICAL_MIN_PORTS),
+            (Kind::Horizontal, probe.port.to_string(), hosts_by_port[&probe.port].len() >= HORIZONTAL_MIN_HOSTS),
+        ];
+        for (kind, key, over_threshold) in candidates {
+            let slot = (kind, key);
+            // A scan that went quiet for a whole window is over
+            if open.get(&slot).is_some_and(|scan| !within_window(scan.last, probe.time)) {
+                finished.extend(open.remove(&slot));
+            }
+            if let Some(scan) = open.get_mut(&slot) {
+                scan.probes.insert(end);
+                scan.last = probe.time;
+            } else if over_threshold {
+                let members = (start..=end).filter(|&i| match kind {
+                    Kind::Vertical => probes[i].dst == probe.dst,
+                    Kind::Horizontal => probes[i].port == probe.port,
+                });
+                open.insert(slot, OpenScan { kind, probes: members.collect(), last: probe.time });
+            }
+        }
+    }
+    finished.extend(open.into_values());
+    finished.sort_by_key(|scan| scan.probes.first().copied());
+
+    finished
+        .iter()
+        .map(|scan| (report(source, scan, probes, entries), probes[*scan.probes.last().unwrap()].index))
+        .collect()
+}
+
+fn decrement<K: std::hash::Hash + Eq>(counts: Option<&mut HashMap<K, usize>>, key: &K) {
+    if let Some(counts) = counts
+        && let Some(count) = counts.get_mut(key)
+    {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+fn report(source: &str, scan: &OpenScan, probes: &[Probe], entries: &[LogEntry]) -> PortScan {
+    let members: Vec<&Probe> = scan.probes.iter().map(|&i| &probes[i]).collect();
+    let targets: BTreeSet<&str> = members.iter().map(|p| p.dst).collect();
+    let ports: BTreeSet<u16> = members.iter().map(|p| p.port).collect();
+    let blocked = members.iter().filter(|p| p.blocked).count();
+    // Probes are in time order, so the first and last members bound the scan
+    let first = &entries[members[0].index].timestamp;
+    let last = &entries[members[members.len() - 1].index].timestamp;
+
+    let (kind, description) = match scan.kind {
+        Kind::Vertical => (
+            "vertical",
+            format!("{} probed {} ports on {} ({} blocked)", source, ports.len(), members[0].dst, blocked),
+        ),
+        Kind::Horizontal => (
+            "horizontal",
+            format!("{} probed port {} on {} hosts ({} blocked)", source, members[0].port, targets.len(), blocked),
+        ),
+    };
+
+    PortScan {
+        source_ip: source.to_string(),
+        kind: kind.to_string(),
+        targets: targets.into_iter().map(str::to_string).collect(),
+        ports: ports.into_iter().collect(),
+        events: members.len(),
+        blocked,
+        first_seen: first.clone(),
+        last_seen: last.clone(),
+        description,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use security_common::parsers::parse_log_line_unified;
+
+    fn scans(entries: &[LogEntry]) -> Vec<PortScan> {
+        detect(entries).into_iter().map(|(scan, _)| scan).collect()
+    }
+
+    fn probe(second: u32, src: &str, dst: &str, port: u16) -> LogEntry {
+        let line = format!(
+            "Feb 20 10:{:02}:{:02} gw kernel: [UFW BLOCK] IN=eth0 OUT= SRC={} DST={} PROTO=TCP SPT=40000 DPT={}",
+            second / 60,
+            second % 60,
+            src,
+            dst,
+            port
+        );
+        parse_log_line_unified(&line).unwrap()
+    }
+
+    #[test]
+    fn detects_vertical_scan() {
+        let entries: Vec<LogEntry> = (0..20).map(|i| probe(i, "203.0.113.5", "10.0.0.2", 20 + i as u16)).collect();
+        let scans = scans(&entries);
+        assert_eq!(scans.len(), 1);
+        assert_eq!(scans[0].kind, "vertical");
+        assert_eq!(scans[0].targets, vec!["10.0.0.2"]);
+        assert_eq!(scans[0].ports.len(), 20);
+        assert_eq!(scans[0].events, 20);
+        assert_eq!(scans[0].blocked, 20);
+        assert_eq!(scans[0].first_seen, "Feb 20 10:00:00");
+        assert_eq!(scans[0].last_seen, "Feb 20 10:00:19");
+    }
+
+    #[test]
+    fn detects_horizontal_scan() {
+        let entries: Vec<LogEntry> = (0..12)
+            .map(|i| probe(i * 5, "198.51.100.7", &format!("10.0.1.{}", i + 1), 445))
+            .collect();
+        let scans = scans(&entries);
+        assert_eq!(scans.len(), 1);
+        assert_eq!(scans[0].kind, "horizontal");
+        assert_eq!(scans[0].ports, vec![445]);
+        assert_eq!(scans[0].targets.len(), 12);
+    }
+
+    #[test]
+    fn untimed_probes_do_not_pin_the_window() {
+        // Bare kernel records without a timestamp, then one timed probe an hour
+        let mut entries: Vec<LogEntry> = (0..10)
+            .map(|i| {
+                let line = format!(
+                    "[UFW BLOCK] IN=eth0 OUT= SRC=203.0.113.5 DST=10.0.0.2 PROTO=TCP SPT=40000 DPT={}",
+                    20 + i
+                );
+                parse_log_line_unified(&line).unwrap()
+            })
+            .collect();
+        assert!(entries.iter().all(|e| parse_timestamp(&e.timestamp).is_none()));
+        entries.extend((0..10).map(|i| {
+            let line = format!(
+                "Feb 20 {:02}:00:00 gw kernel: [UFW BLOCK] IN=eth0 OUT= SRC=203.0.113.5 DST=10.0.0.2 PROTO=TCP SPT=40000 DPT={}",
+                i + 1,
+                30 + i
+            );
+            parse_log_line_unified(&line).unwrap()
+        }));
+        assert!(scans(&entries).is_empty());
+    }
+
+    #[test]
+    fn slow_or_sparse_probing_is_not_a_scan() {
+        // 20 ports, but one every two minutes: never 15 within ten minutes
+        let slow: Vec<LogEntry> = (0..20).map(|i| probe(i * 120, "203.0.113.5", "10.0.0.2", 20 + i as u16)).collect();
+        assert!(scans(&slow).is_empty());
+        // Normal traffic: a few ports on a few hosts
+        let normal: Vec<LogEntry> = (0..10)
+            .map(|i| probe(i, "192.0.2.1", &format!("10.0.0.{}", i % 3), [80, 443][i as usize % 2]))
+            .collect();
+        assert!(scans(&normal).is_empty());
+    }
+}
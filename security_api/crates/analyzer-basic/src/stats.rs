@@ -0,0 +1,52 @@
+// Robust statistics shared by the behavioral detectors
+// Baselines use the median and the median absolute deviation (MAD), which a
+// minority of outliers (the traffic being looked for) cannot drag along.
+
+/// Median of a series and the spread deviations from it are measured in
+pub(crate) struct Baseline {
+    pub median: f64,
+    pub scale: f64,
+}
+
+impl Baseline {
+    /// Baseline of `samples` plus `quiet` zero samples
+    pub fn of(samples: Vec<f64>, quiet: usize) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let median = padded_median(samples.clone(), 0.0, quiet);
+        let deviations = samples.iter().map(|x| (x - median).abs()).collect();
+        let mad = padded_median(deviations, median, quiet);
+        // 1.4826 x MAD estimates the standard deviation of normal data; counts
+        // are at least as noisy as a Poisson process, and a deviation of one
+        // is never significant on its own
+        let scale = (1.4826 * mad).max(median.sqrt()).max(1.0);
+        Some(Self { median, scale })
+    }
+
+    pub fn z_score(&self, value: f64) -> f64 {
+        (value - self.median) / self.scale
+    }
+}
+
+/// Median of `values` plus `padding` copies of `pad`, without building the
+/// padded list (a history can span months of empty minutes)
+fn padded_median(mut values: Vec<f64>, pad: f64, padding: usize) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let below = values.partition_point(|v| *v < pad);
+    let nth = |k: usize| {
+        if k < below {
+            values[k]
+        } else if k < below + padding {
+            pad
+        } else {
+            values[k - padding]
+        }
+    };
+    let n = values.len() + padding;
+    if n.is_multiple_of(2) {
+        (nth(n / 2 - 1) + nth(n / 2)) / 2.0
+    } else {
+        nth(n / 2)
+    }
+}
@@ -0,0 +1,41 @@
+// Log entry fixtures shared by the detector tests
+
+use security_common::LogEntry;
+
+/// An INFO entry carrying `fields`
+pub fn entry(
+    timestamp: String,
+    ip: Option<&str>,
+    username: Option<&str>,
+    message: String,
+    fields: &[(&str, &str)],
+) -> LogEntry {
+    LogEntry {
+        timestamp,
+        level: "INFO".to_string(),
+        ip_address: ip.map(str::to_string),
+        username: username.map(str::to_string),
+        message,
+        fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+    }
+}
+
+/// A web request `second` seconds after 2024-02-20 10:00 UTC, with `extra`
+/// fields besides the method, path and status
+pub fn request(second: u32, ip: &str, method: &str, path: &str, status: u16, extra: &[(&str, &str)]) -> LogEntry {
+    let status_text = status.to_string();
+    let mut fields = vec![("http.method", method), ("http.path", path), ("http.status", status_text.as_str())];
+    fields.extend_from_slice(extra);
+    entry(
+        format!("2024-02-20T{:02}:{:02}:{:02}+00:00", 10 + second / 3600, second / 60 % 60, second % 60),
+        Some(ip),
+        None,
+        format!("{} {} {}", method, path, status),
+        &fields,
+    )
+}
+
+/// A plain GET request, see [`request`]
+pub fn get(second: u32, ip: &str, path: &str, status: u16) -> LogEntry {
+    request(second, ip, "GET", path, status, &[])
+}
@@ -0,0 +1,147 @@
+// Webshell use by access pattern
+// The `http.webshell_*` rules catch shells by name and by parameters. A shell
+// with an innocent name and POSTed commands leaves a different trace: one
+// client repeatedly and successfully requesting a script nobody else touches.
+// That only means something in a log with enough other clients, and the
+// usual single-user entry points (login pages, AJAX handlers) are ignored.
+
+use security_common::LogEntry;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Successful requests from the one client before a script is suspicious
+const MIN_HITS: usize = 5;
+/// Web clients the log needs before "nobody else requests it" means anything
+const MIN_CLIENTS: usize = 5;
+/// Server-side script extensions
+const SCRIPT_EXTENSIONS: &[&str] = &[
+    "php", "php3", "php4", "php5", "php7", "phtml", "phar", "jsp", "jspx", "asp", "aspx", "ashx", "cgi", "pl",
+];
+/// Scripts a single administrator or integration legitimately hammers
+const COMMON_SCRIPTS: &[&str] = &[
+    "index.php", "wp-login.php", "admin-ajax.php", "wp-cron.php", "xmlrpc.php", "admin.php", "login.php",
+    "default.aspx", "index.jsp", "index.aspx",
+];
+
+/// One client repeatedly using a script no other client requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct RareScript {
+    pub ip: String,
+    /// Script path without the query string
+    pub script: String,
+    /// Successful requests to it
+    pub hits: usize,
+    /// Index of the last successful request
+    pub last_index: usize,
+}
+
+/// Find scripts only one client uses, with repeated 2xx responses
+pub fn detect(entries: &[LogEntry]) -> Vec<RareScript> {
+    let mut clients: BTreeSet<&str> = BTreeSet::new();
+    let mut by_script: BTreeMap<&str, BTreeMap<&str, Vec<usize>>> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let (Some(ip), Some(path)) = (entry.ip_address.as_deref(), entry.fields.get("http.path")) else {
+            continue;
+        };
+        clients.insert(ip);
+        let script = path.split(['?', '#']).next().unwrap_or(path);
+        if !is_script(script) {
+            continue;
+        }
+        let succeeded = entry.fields.get("http.status").and_then(|s| s.parse::<u16>().ok()).is_some_and(|s| (200..300).contains(&s));
+        let hits = by_script.entry(script).or_default().entry(ip).or_default();
+        if succeeded {
+            hits.push(index);
+        }
+    }
+    if clients.len() < MIN_CLIENTS {
+        return Vec::new();
+    }
+
+    by_script
+        .into_iter()
+        .filter(|(_, users)| users.len() == 1)
+        .filter_map(|(script, users)| {
+            let (ip, hits) = users.into_iter().next()?;
+            (hits.len() >= MIN_HITS).then(|| RareScript {
+                ip: ip.to_string(),
+                script: script.to_string(),
+                hits: hits.len(),
+                last_index: hits[hits.len() - 1],
+            })
+        })
+        .collect()
+}
+
+fn is_script(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+    if COMMON_SCRIPTS.contains(&name.as_str()) {
+        return false;
+    }
+    name.rsplit_once('.').is_some_and(|(stem, ext)| !stem.is_empty() && SCRIPT_EXTENSIONS.contains(&ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::request;
+    use security_common::parsers::ApacheLog;
+    use security_common::rules::RuleSet;
+
+    fn hit(ip: &str, method: &str, path: &str, status: u16) -> LogEntry {
+        request(0, ip, method, path, status, &[])
+    }
+
+    fn visitors() -> Vec<LogEntry> {
+        (1..=6).flat_map(|i| {
+            let ip = format!("192.0.2.{}", i);
+            vec![hit(&ip, "GET", "/index.php", 200), hit(&ip, "GET", "/wp-login.php", 200)]
+        })
+        .collect()
+    }
+
+    #[test]
+    fn flags_a_script_only_one_client_uses() {
+        let mut entries = visitors();
+        entries.extend((0..6).map(|_| hit("203.0.113.5", "POST", "/wp-content/plugins/hello/about.php", 200)));
+        // The administrator alone logging in repeatedly is normal
+        entries.extend((0..6).map(|_| hit("192.0.2.1", "POST", "/wp-login.php", 200)));
+
+        let found = detect(&entries);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].ip, "203.0.113.5");
+        assert_eq!(found[0].script, "/wp-content/plugins/hello/about.php");
+        assert_eq!(found[0].hits, 6);
+        assert_eq!(found[0].last_index, entries.len() - 7);
+    }
+
+    #[test]
+    fn shared_or_failing_scripts_are_ignored() {
+        let mut entries = visitors();
+        entries.extend((0..6).map(|_| hit("203.0.113.5", "GET", "/missing.php", 404)));
+        entries.push(hit("192.0.2.2", "GET", "/report.php", 200));
+        entries.extend((0..6).map(|_| hit("203.0.113.6", "GET", "/report.php", 200)));
+        assert!(detect(&entries).is_empty());
+    }
+
+    #[test]
+    fn webshell_rules_label_requests() {
+        let rules = RuleSet::default_pack();
+        let label = |method: &str, path: &str| {
+            let mut log = ApacheLog::from_entry(&hit("203.0.113.5", method, path, 200)).unwrap();
+            log.method = method.to_string();
+            log.analyze_with(&rules);
+            log.threat_type
+        };
+        let webshell = Some("Webshell".to_string());
+        assert_eq!(label("GET", "/images/c99.php"), webshell);
+        assert_eq!(label("POST", "/uploads/2024/avatar.php"), webshell);
+        assert_eq!(label("GET", "/x.php?cmd=whoami"), webshell);
+        assert_eq!(label("GET", "/tmp/s.jsp?exec=id"), webshell);
+        assert_eq!(label("GET", "/up/a.aspx?c=whoami"), webshell);
+        assert_eq!(label("POST", "/help.php?z0=QGluaV9zZXQ"), webshell);
+        // Ordinary requests
+        assert_eq!(label("GET", "/index.php?c=category&id=4"), None);
+        assert_eq!(label("GET", "/uploads/avatar.png"), None);
+        assert_eq!(label("GET", "/shelly-theme.php"), None);
+    }
+}
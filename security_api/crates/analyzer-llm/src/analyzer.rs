@@ -326,7 +326,7 @@ mod tests {
     fn test_analyzer_default() {
         let analyzer = LlmAnalyzer::default();
         // Will not be configured without env vars
-        assert!(!analyzer.is_configured() || analyzer.config.api_key.is_empty() == false);
+        assert!(!analyzer.is_configured() || !analyzer.config.api_key.is_empty());
     }
 
     #[test]
@@ -18,6 +18,7 @@
 
 use std::env;
 use std::fmt;
+use std::str::FromStr;
 
 /// Supported LLM providers
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -40,18 +41,22 @@ impl fmt::Display for LlmProvider {
     }
 }
 
-impl LlmProvider {
+impl FromStr for LlmProvider {
+    type Err = ConfigError;
+
     /// Parse provider from string (case-insensitive)
-    pub fn from_str(s: &str) -> Option<Self> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "openai" | "gpt" => Some(LlmProvider::OpenAI),
-            "anthropic" | "claude" => Some(LlmProvider::Anthropic),
-            "groq" | "llama" => Some(LlmProvider::Groq),
-            "gemini" | "google" => Some(LlmProvider::Gemini),
-            _ => None,
+            "openai" | "gpt" => Ok(LlmProvider::OpenAI),
+            "anthropic" | "claude" => Ok(LlmProvider::Anthropic),
+            "groq" | "llama" => Ok(LlmProvider::Groq),
+            "gemini" | "google" => Ok(LlmProvider::Gemini),
+            _ => Err(ConfigError::InvalidProvider(s.to_string())),
         }
     }
+}
 
+impl LlmProvider {
     /// Get the default model for this provider
     pub fn default_model(&self) -> &'static str {
         match self {
@@ -109,8 +114,7 @@ impl LlmConfig {
 
         // Parse provider
         let provider = match env::var("LLM_PROVIDER") {
-            Ok(p) => LlmProvider::from_str(&p)
-                .ok_or_else(|| ConfigError::InvalidProvider(p))?,
+            Ok(p) => p.parse()?,
             Err(_) => LlmProvider::default(),
         };
 
@@ -229,14 +233,14 @@ mod tests {
 
     #[test]
     fn test_provider_parsing() {
-        assert_eq!(LlmProvider::from_str("openai"), Some(LlmProvider::OpenAI));
-        assert_eq!(LlmProvider::from_str("OPENAI"), Some(LlmProvider::OpenAI));
-        assert_eq!(LlmProvider::from_str("gpt"), Some(LlmProvider::OpenAI));
-        assert_eq!(LlmProvider::from_str("anthropic"), Some(LlmProvider::Anthropic));
-        assert_eq!(LlmProvider::from_str("claude"), Some(LlmProvider::Anthropic));
-        assert_eq!(LlmProvider::from_str("groq"), Some(LlmProvider::Groq));
-        assert_eq!(LlmProvider::from_str("gemini"), Some(LlmProvider::Gemini));
-        assert_eq!(LlmProvider::from_str("invalid"), None);
+        assert_eq!("openai".parse::<LlmProvider>().ok(), Some(LlmProvider::OpenAI));
+        assert_eq!("OPENAI".parse::<LlmProvider>().ok(), Some(LlmProvider::OpenAI));
+        assert_eq!("gpt".parse::<LlmProvider>().ok(), Some(LlmProvider::OpenAI));
+        assert_eq!("anthropic".parse::<LlmProvider>().ok(), Some(LlmProvider::Anthropic));
+        assert_eq!("claude".parse::<LlmProvider>().ok(), Some(LlmProvider::Anthropic));
+        assert_eq!("groq".parse::<LlmProvider>().ok(), Some(LlmProvider::Groq));
+        assert_eq!("gemini".parse::<LlmProvider>().ok(), Some(LlmProvider::Gemini));
+        assert_eq!("invalid".parse::<LlmProvider>().ok(), None);
     }
 
     #[test]
@@ -4,6 +4,10 @@
 //! to produce structured, actionable security analysis reports.
 
 use security_common::parsers::ApacheLog;
+use security_common::sessions::{sessionize, WebSession};
+
+/// Sessions described in the analysis prompt
+pub const MAX_SESSIONS_IN_PROMPT: usize = 20;
 
 /// System prompt that establishes the LLM's role as a security analyst
 pub const SYSTEM_PROMPT: &str = r#"You are a senior cybersecurity analyst specializing in web server log analysis and threat detection. Your expertise includes:
@@ -36,10 +40,41 @@ pub fn format_logs_for_analysis(logs: &[ApacheLog], max_logs: usize) -> String {
         .join("\n")
 }
 
+/// Describe visitor sessions for analysis: automated visitors first, then
+/// the busiest, each with the bot signals seen and its first requests
+pub fn format_sessions_for_analysis(sessions: &[WebSession], max_sessions: usize) -> String {
+    let mut ordered: Vec<&WebSession> = sessions.iter().collect();
+    ordered.sort_by(|a, b| a.is_human().cmp(&b.is_human()).then_with(|| b.requests.cmp(&a.requests)));
+
+    ordered
+        .iter()
+        .take(max_sessions)
+        .map(|session| {
+            let signals = if session.bot_signals.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", session.bot_signals.join("; "))
+            };
+            format!(
+                "- [{}] {} => {}{}\n    {}",
+                session.start,
+                session.summary(),
+                session.classification,
+                signals,
+                session.activity.join("; ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Build the main security analysis prompt
 pub fn build_analysis_prompt(logs: &[ApacheLog], max_sample_logs: usize) -> String {
     let log_sample = format_logs_for_analysis(logs, max_sample_logs);
     let total_logs = logs.len();
+    let sessions = sessionize(logs);
+    let total_sessions = sessions.len();
+    let session_sample = format_sessions_for_analysis(&sessions, MAX_SESSIONS_IN_PROMPT);
 
     format!(
         r#"Analyze the following Apache web server logs and provide a comprehensive security assessment.
@@ -48,13 +83,18 @@ pub fn build_analysis_prompt(logs: &[ApacheLog], max_sample_logs: usize) -> Stri
 
 {log_sample}
 
+## VISITOR SESSIONS ({total_sessions} sessions by IP and user agent, showing up to {MAX_SESSIONS_IN_PROMPT}, automated visitors first):
+
+{session_sample}
+
 ## ANALYSIS REQUIREMENTS:
 
 1. **Count Suspicious Activity**: Identify and count all suspicious log entries
 2. **Detect Attack Chains**: Group related malicious requests by IP and time
-3. **Map to MITRE ATT&CK**: Identify techniques with their T-codes and explanations
-4. **Extract IOCs**: List all malicious IPs, user-agents, and attack patterns
-5. **Provide Recommendations**: Give specific, actionable remediation steps
+3. **Describe Visitors**: Use the sessions to explain what each suspicious visitor did, and whether it is a person, a crawler, a tool or a bot
+4. **Map to MITRE ATT&CK**: Identify techniques with their T-codes and explanations
+5. **Extract IOCs**: List all malicious IPs, user-agents, and attack patterns
+6. **Provide Recommendations**: Give specific, actionable remediation steps
 
 ## RESPONSE FORMAT:
 
@@ -270,6 +310,25 @@ mod tests {
         assert!(prompt.contains("192.168.1.1"));
         assert!(prompt.contains("MITRE ATT&CK"));
         assert!(prompt.contains("threat_level"));
+        assert!(prompt.contains("VISITOR SESSIONS (1 sessions"));
+    }
+
+    #[test]
+    fn test_format_sessions_puts_automation_first() {
+        let mut logs = vec![
+            create_test_log("192.168.1.1", "GET", "/index.html", 200),
+            create_test_log("192.168.1.1", "GET", "/about.html", 200),
+        ];
+        let mut scanner = create_test_log("10.0.0.1", "GET", "/wp-login.php", 404);
+        scanner.user_agent = "sqlmap/1.7".to_string();
+        logs.push(scanner);
+
+        let formatted = format_sessions_for_analysis(&sessionize(&logs), 10);
+        let lines: Vec<&str> = formatted.lines().filter(|l| l.starts_with("- ")).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("10.0.0.1") && lines[0].contains("=> tool"));
+        assert!(lines[1].contains("=> human"));
+        assert!(formatted.contains("GET /wp-login.php 404"));
     }
 
     #[test]
@@ -0,0 +1,190 @@
+//! Analyst feedback on findings
+//!
+//! `POST /api/feedback` records a true/false positive verdict against a
+//! finding's rule id; `GET /api/feedback` returns per-rule precision.
+//! Verdicts lower the severity of every later analysis, so recording them is
+//! off unless `LOGR_FEEDBACK_TOKEN` is set, and then needs that token as a
+//! bearer token. Verdicts go to the process-wide feedback store (kept in
+//! memory, or in `LOGR_FEEDBACK_FILE`) and take effect on the next analysis.
+//! With a database, the rule's false-positive rate is also written to
+//! `threat_patterns`.
+
+use axum::{
+    extract::Json,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use security_common::cvss::Severity;
+use security_common::database::queries;
+use security_common::feedback::{self, FeedbackRecord, RulePrecision, Verdict};
+use std::sync::OnceLock;
+
+static TOKEN: OnceLock<String> = OnceLock::new();
+
+/// Accept verdicts from clients presenting `token`
+pub fn enable(token: String) -> Result<(), String> {
+    TOKEN.set(token).map_err(|_| "feedback token is already set".to_string())
+}
+
+/// Request payload for a verdict on a finding
+#[derive(Debug, Deserialize)]
+pub struct FeedbackRequest {
+    /// Rule id of the finding (`http.scanner`, `auth.brute_force`, ...)
+    pub rule_id: String,
+    /// `true_positive`/`tp` or `false_positive`/`fp`
+    pub verdict: String,
+    #[serde(default)]
+    pub line_number: Option<usize>,
+    #[serde(default)]
+    pub matched: Option<String>,
+    #[serde(default)]
+    pub analyst: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Compare without stopping at the first differing byte
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+pub async fn record_feedback(headers: HeaderMap, Json(request): Json<FeedbackRequest>) -> Response {
+    let Some(token) = TOKEN.get() else {
+        return error(
+            StatusCode::FORBIDDEN,
+            "Recording feedback is disabled; set LOGR_FEEDBACK_TOKEN to enable it".to_string(),
+        );
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !token_matches(given.trim(), token) {
+        return error(StatusCode::UNAUTHORIZED, "Missing or invalid feedback token".to_string());
+    }
+
+    let Some(verdict) = Verdict::from_name(&request.verdict) else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid verdict '{}': expected true_positive or false_positive", request.verdict),
+        );
+    };
+    let Some(rule) = feedback::known_rule(&request.rule_id) else {
+        return error(StatusCode::BAD_REQUEST, format!("Unknown rule id '{}'", request.rule_id.trim()));
+    };
+
+    let mut record = FeedbackRecord::new(&rule.id, verdict);
+    record.threat_type = rule.threat_type.clone();
+    record.line_number = request.line_number;
+    record.matched = request.matched;
+    record.analyst = request.analyst;
+    record.note = request.note;
+
+    let precision = match feedback::record(record) {
+        Ok(precision) => precision,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+
+    // The rule's base severity, as lowered by the feedback so far
+    let severity = feedback::lower(&rule.severity, precision.downgrade);
+    if let Some(pool) = super::db_pool() {
+        let pattern_type = rule.threat_type.clone().unwrap_or_else(|| {
+            rule.id.split('.').next().unwrap_or_default().to_string()
+        });
+        let severity_name = match &severity {
+            Severity::None => "low".to_string(),
+            other => other.as_str().to_lowercase(),
+        };
+        if let Err(e) = queries::save_pattern_feedback(
+            pool,
+            &pattern_type,
+            &precision.rule_id,
+            &severity_name,
+            precision.false_positive_rate() * 100.0,
+        )
+        .await
+        {
+            eprintln!("[WARN] Failed to save feedback for {}: {}", precision.rule_id, e);
+        }
+    }
+
+    Json(serde_json::json!({
+        "rule": precision,
+        "severity": severity.as_str(),
+    }))
+    .into_response()
+}
+
+pub async fn feedback_stats() -> Json<Vec<RulePrecision>> {
+    Json(feedback::precision())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use security_common::cvss::ThreatType;
+
+    fn request(rule_id: &str, extra: serde_json::Value) -> Json<FeedbackRequest> {
+        let mut body = serde_json::json!({ "rule_id": rule_id, "verdict": "fp" });
+        body.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        Json(serde_json::from_value(body).unwrap())
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token)).unwrap());
+        headers
+    }
+
+    async fn body(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn verdicts_need_the_token_and_a_known_rule() {
+        let _ = enable("feedback-secret".to_string());
+        let none = serde_json::json!({});
+
+        let response = record_feedback(HeaderMap::new(), request("http.sql_injection", none.clone())).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = record_feedback(bearer("guess"), request("http.sql_injection", none.clone())).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = record_feedback(bearer("feedback-secret"), request("http.no_such_rule", none)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(feedback::precision().iter().all(|p| p.rule_id != "http.no_such_rule"));
+
+        // The rule id is trimmed and the threat type comes from the rule
+        let spoofed = serde_json::json!({ "threat_type": "made_up" });
+        let response = record_feedback(bearer("feedback-secret"), request(" http.sql_injection ", spoofed)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body(response).await;
+        assert_eq!(json["rule"]["rule_id"], "http.sql_injection");
+        assert_eq!(json["severity"], "Critical");
+
+        // Behavioral detectors are not rules but take verdicts too
+        let none = serde_json::json!({});
+        let response = record_feedback(bearer("feedback-secret"), request("auth.brute_force", none)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body(response).await;
+        let severity = ThreatType::BruteForce.cvss_score().severity;
+        assert_eq!(json["severity"], severity.as_str());
+    }
+
+    #[test]
+    fn token_comparison() {
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secreT", "secret"));
+        assert!(!token_matches("secret2", "secret"));
+        assert!(!token_matches("", "secret"));
+    }
+}
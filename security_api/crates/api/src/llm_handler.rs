@@ -9,7 +9,7 @@ use axum::{
     response::{IntoResponse, Json},
 };
 use security_analyzer_llm::{LlmAnalyzer, AnalyzerError};
-use security_common::{geolocation, parsers::apache::parse_apache_combined};
+use security_common::{parsers::{ParseOptions, apache::parse_apache_combined}};
 
 /// Analyze logs using the configured LLM provider
 ///
@@ -67,9 +67,9 @@ pub async fn analyze_logs_with_llm(
     let analyzer = if let Some(provider_name) = provider_override {
         use security_analyzer_llm::{LlmConfig, LlmProvider};
 
-        let provider = match LlmProvider::from_str(&provider_name) {
-            Some(p) => p,
-            None => {
+        let provider = match provider_name.parse::<LlmProvider>() {
+            Ok(p) => p,
+            Err(_) => {
                 return Json(serde_json::json!({
                     "error": format!("Unsupported provider: {}", provider_name),
                     "suggestion": "Valid options: openai, anthropic, groq, gemini"
@@ -211,30 +211,10 @@ pub async fn analyze_logs_with_llm(
     );
 
     // Also get basic analysis for additional context
-    let mut basic_result = super::process_logs(&content);
+    let mut basic_result = super::process_logs(&content, &ParseOptions::default());
 
     // Enrich IPs with geolocation data
-    let all_ip_strings: Vec<String> = basic_result.ip_analysis.all_ips.iter().map(|ip| ip.ip.clone()).collect();
-    if !all_ip_strings.is_empty() {
-        println!("[INFO] Looking up geolocation for {} IPs...", all_ip_strings.len());
-        let geo_data = geolocation::lookup_batch(&all_ip_strings).await;
-        
-        for ip_info in &mut basic_result.ip_analysis.all_ips {
-            if let Some(geo) = geo_data.get(&ip_info.ip) {
-                ip_info.country = geo.country.clone();
-                ip_info.city = geo.city.clone();
-                ip_info.is_vpn = geo.is_proxy || geo.is_hosting;
-            }
-        }
-        
-        for ip_info in &mut basic_result.ip_analysis.high_risk_ips {
-            if let Some(geo) = geo_data.get(&ip_info.ip) {
-                ip_info.country = geo.country.clone();
-                ip_info.city = geo.city.clone();
-                ip_info.is_vpn = geo.is_proxy || geo.is_hosting;
-            }
-        }
-    }
+    super::enrich_with_geolocation(&mut basic_result).await;
 
     // Combine results - flatten structure for UI compatibility
     Json(serde_json::json!({
@@ -244,6 +224,18 @@ pub async fn analyze_logs_with_llm(
         "risk_assessment": basic_result.risk_assessment,
         "parsing_info": basic_result.parsing_info,
         "alerts": basic_result.alerts,
+        "findings": basic_result.findings,
+        "auth_attacks": basic_result.auth_attacks,
+        "port_scans": basic_result.port_scans,
+        "rate_anomalies": basic_result.rate_anomalies,
+        "exfiltration": basic_result.exfiltration,
+        "content_discovery": basic_result.content_discovery,
+        "sessions": basic_result.sessions,
+        "logins": basic_result.logins,
+        "impossible_travel": basic_result.impossible_travel,
+        "integrity": basic_result.integrity,
+        "suppressed": basic_result.suppressed,
+        "threat_intel": basic_result.threat_intel,
 
         // AI analysis data
         "ai_report": ai_report,
@@ -19,6 +19,13 @@ const MAX_UPLOAD_BYTES: usize = 50 * 1024 * 1024;
 const RATE_LIMIT_MAX_REQUESTS: usize = 30;
 const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
 
+static DB_POOL: OnceLock<DbPool> = OnceLock::new();
+
+/// The database pool, when a database is configured and reachable
+pub fn db_pool() -> Option<&'static DbPool> {
+    DB_POOL.get()
+}
+
 fn rate_limit_store() -> &'static Mutex<HashMap<String, Vec<Instant>>> {
     static STORE: OnceLock<Mutex<HashMap<String, Vec<Instant>>>> = OnceLock::new();
     STORE.get_or_init(|| Mutex::new(HashMap::new()))
@@ -63,14 +70,26 @@ async fn rate_limit_middleware(
 
 // Import from workspace crates
 use security_common::{
-    database::{init_db, test_connection},
+    database::{
+        init_db, test_connection, DbPool, queries,
+        models::{NewAnalysisResult, NewLogUpload},
+    },
     cvss,
     geolocation,
-    AnalysisResult, ThreatStats, IpAnalysis, IpInfo, RiskAssessment, 
-    ParsingInfo, ParseError, FormatQuality,
+    feedback::{self, FeedbackStore},
+    integrity,
+    ioc::{self, IocSet},
+    travel,
+    parsers::{JsonProfile, ParseOptions},
+    rules::{
+        self, RuleSet, alerts::{self, AlertEngine}, allowlist::{self, Allowlist, SuppressionReport},
+        sigma::{self, SigmaRuleSet},
+    },
+    AnalysisResult, ThreatStats, IpAnalysis, IpInfo, RiskAssessment,
 };
 use security_analyzer_basic::BasicAnalyzer;
 
+mod feedback_handler;
 mod llm_handler;
 mod simple_handler;
 
@@ -78,9 +97,113 @@ mod simple_handler;
 async fn main() {
     // Load environment variables
     dotenv::dotenv().ok();
+
+    // Extra detection rules: LOGR_RULE_FILES=/etc/logr/web.toml,/etc/logr/auth.yaml
+    if let Ok(files) = std::env::var("LOGR_RULE_FILES") {
+        let paths: Vec<&str> = files.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+        match RuleSet::with_files(&paths).and_then(rules::install) {
+            Ok(()) => println!("[INFO] Loaded {} detection rules", rules::active().len()),
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load detection rules: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Extra alert rules: LOGR_ALERT_FILES=/etc/logr/alerts.toml
+    if let Ok(files) = std::env::var("LOGR_ALERT_FILES") {
+        let paths: Vec<&str> = files.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+        let installed = AlertEngine::with_files(&paths)
+            .and_then(|engine| engine.check(rules::active()).map(|_| engine))
+            .and_then(alerts::install);
+        match installed {
+            Ok(()) => println!("[INFO] Loaded {} alert rules", alerts::active().len()),
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load alert rules: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Allowlists: LOGR_ALLOWLIST_FILES=/etc/logr/allowlist.toml
+    if let Ok(files) = std::env::var("LOGR_ALLOWLIST_FILES") {
+        let paths: Vec<&str> = files.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+        match Allowlist::with_files(&paths).and_then(allowlist::install) {
+            Ok(()) => {
+                println!("[INFO] Loaded {} allowlist entries", allowlist::active().len());
+                for name in allowlist::active().expired() {
+                    eprintln!("[WARN] Allowlist entry '{}' has expired and no longer applies", name);
+                }
+            }
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load allowlist: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Threat-intelligence feeds: LOGR_IOC_PATHS=/var/lib/logr/feeds,/etc/logr/blocklist.txt
+    if let Ok(paths) = std::env::var("LOGR_IOC_PATHS") {
+        let paths: Vec<&str> = paths.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+        match IocSet::load_paths(&paths).and_then(ioc::install) {
+            Ok(()) => println!(
+                "[INFO] Loaded {} indicators from {} threat-intelligence feeds",
+                ioc::active().len(),
+                ioc::active().feeds().len()
+            ),
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load threat-intelligence feeds: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Analyst verdicts: LOGR_FEEDBACK_FILE=/var/lib/logr/feedback.jsonl, kept
+    // in memory otherwise
+    if let Ok(feedback_file) = std::env::var("LOGR_FEEDBACK_FILE") {
+        match FeedbackStore::open(&feedback_file).and_then(feedback::install) {
+            Ok(()) => println!("[INFO] Recording analyst feedback in {}", feedback_file),
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load analyst feedback: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // Recording verdicts is opt-in: LOGR_FEEDBACK_TOKEN=<bearer token>
+    match std::env::var("LOGR_FEEDBACK_TOKEN").map(|t| t.trim().to_string()) {
+        Ok(token) if !token.is_empty() => {
+            // Cannot fail: nothing has set the token yet
+            let _ = feedback_handler::enable(token);
+        }
+        _ => println!("[INFO] POST /api/feedback is disabled (set LOGR_FEEDBACK_TOKEN to enable it)"),
+    }
+
+    // Sigma rules: LOGR_SIGMA_PATHS=/opt/sigma/rules/web,/etc/logr/sigma/local.yml
+    if let Ok(paths) = std::env::var("LOGR_SIGMA_PATHS") {
+        let paths: Vec<&str> = paths.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+        match SigmaRuleSet::load_paths(&paths) {
+            Ok(set) => {
+                println!("[INFO] Loaded {} Sigma rules", set.rules.len());
+                for skipped in &set.unsupported {
+                    eprintln!(
+                        "[WARN] Skipping Sigma rule {} ({}): {}",
+                        skipped.title.as_deref().unwrap_or("<untitled>"),
+                        skipped.source,
+                        skipped.reason
+                    );
+                }
+                // Cannot fail: nothing has read the Sigma rules yet
+                let _ = sigma::install(set);
+            }
+            Err(e) => {
+                eprintln!("[ERROR] Failed to load Sigma rules: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
     
-    // Database is optional and currently unused by any route handler
-    // (kept for future persistence features); this is a startup diagnostic only.
+    // Database is optional; when connected, analyst feedback is also written
+    // to threat_patterns
     println!("Starting Security API Server...");
     match init_db().await {
         Ok(pool) => {
@@ -88,6 +211,7 @@ async fn main() {
                 eprintln!("[ERROR] Database connection test failed: {}", e);
             } else {
                 println!("[INFO] Database connected");
+                let _ = DB_POOL.set(pool);
             }
         }
         Err(e) => {
@@ -102,6 +226,10 @@ async fn main() {
         .route("/api/analyze-with-llm", post(llm_handler::analyze_logs_with_llm))
         .route("/api/llm-health", axum::routing::get(llm_handler::llm_health_check))
         .route("/api/explain-logs", post(simple_handler::explain_logs))
+        .route(
+            "/api/feedback",
+            post(feedback_handler::record_feedback).get(feedback_handler::feedback_stats),
+        )
         .layer(middleware::from_fn(rate_limit_middleware))
         .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES));
 
@@ -115,6 +243,7 @@ async fn main() {
     println!("[INFO] Upload logs at: http://localhost:3000");
     println!("[INFO] LLM Analysis: POST /api/analyze-with-llm");
     println!("[INFO] LLM Health:   GET  /api/llm-health");
+    println!("[INFO] Feedback:     POST /api/feedback, GET /api/feedback");
     
     axum::serve(
         listener,
@@ -130,6 +259,8 @@ async fn analyze_logs(
 ) -> Response {
     let mut content = String::new();
     let mut filename = String::from("unknown");
+    let mut json_profile: Option<String> = None;
+    let mut options = ParseOptions::default();
     
     loop {
         match multipart.next_field().await {
@@ -152,6 +283,30 @@ async fn analyze_logs(
                                 .into_response();
                         }
                     }
+                } else if name == "json_profile" {
+                    // Built-in profile name or an inline TOML/JSON field mapping
+                    if let Ok(data) = field.bytes().await {
+                        json_profile = Some(String::from_utf8_lossy(&data).to_string());
+                    }
+                } else if name == "strict" {
+                    if let Ok(data) = field.bytes().await {
+                        let value = String::from_utf8_lossy(&data).trim().to_lowercase();
+                        options.strict = matches!(value.as_str(), "true" | "1" | "yes" | "on");
+                    }
+                } else if name == "error_samples" {
+                    let value = field.text().await.unwrap_or_default();
+                    match value.trim().parse::<usize>() {
+                        Ok(limit) => options.error_sample_limit = limit,
+                        Err(_) => {
+                            return (
+                                StatusCode::BAD_REQUEST,
+                                Json(serde_json::json!({
+                                    "error": format!("Invalid error_samples value: {}", value.trim())
+                                })),
+                            )
+                                .into_response();
+                        }
+                    }
                 }
             }
             Ok(None) => break,
@@ -175,32 +330,31 @@ async fn analyze_logs(
             .into_response();
     }
     
+    if let Some(spec) = json_profile.filter(|s| !s.trim().is_empty()) {
+        // Inline only: a path here would let clients read server files
+        match JsonProfile::from_inline(&spec) {
+            Ok(profile) => options.json_profile = Some(profile),
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({ "error": e })),
+                )
+                    .into_response();
+            }
+        }
+    }
+    
     println!("[INFO] Processing log file: {}", filename);
     
     // Parse logs and analyze
-    let mut result = process_logs(&content);
+    let started = Instant::now();
+    let mut result = process_logs(&content, &options);
     
     // Enrich IPs with geolocation data
-    let all_ip_strings: Vec<String> = result.ip_analysis.all_ips.iter().map(|ip| ip.ip.clone()).collect();
-    if !all_ip_strings.is_empty() {
-        println!("[INFO] Looking up geolocation for {} IPs...", all_ip_strings.len());
-        let geo_data = geolocation::lookup_batch(&all_ip_strings).await;
-        
-        for ip_info in &mut result.ip_analysis.all_ips {
-            if let Some(geo) = geo_data.get(&ip_info.ip) {
-                ip_info.country = geo.country.clone();
-                ip_info.city = geo.city.clone();
-                ip_info.is_vpn = geo.is_proxy || geo.is_hosting;
-            }
-        }
-        
-        for ip_info in &mut result.ip_analysis.high_risk_ips {
-            if let Some(geo) = geo_data.get(&ip_info.ip) {
-                ip_info.country = geo.country.clone();
-                ip_info.city = geo.city.clone();
-                ip_info.is_vpn = geo.is_proxy || geo.is_hosting;
-            }
-        }
+    enrich_with_geolocation(&mut result).await;
+    
+    if let Some(pool) = db_pool() {
+        save_analysis(pool, &filename, content.len(), started.elapsed(), &result).await;
     }
     
     println!("[INFO] Analysis complete");
@@ -208,57 +362,82 @@ async fn analyze_logs(
     Json(result).into_response()
 }
 
-// Process logs with basic analyzer
-pub fn process_logs(content: &str) -> AnalysisResult {
-    use security_common::parsers::{parse_log_line_unified, parse_apache_combined};
-    
-    let mut entries = Vec::new();
-    let mut total_lines = 0;
-    let mut parsed_lines = 0;
-    let mut parse_errors: Vec<ParseError> = Vec::new();
-    let mut perfect_format = 0;
-    let mut alternative_format = 0;
-    let mut fallback_format = 0;
-    
-    // Parse all lines with unified parser (supports multiple formats)
-    for line in content.lines() {
-        total_lines += 1;
-        
-        if line.trim().is_empty() {
-            continue;
+// Record the upload and its threat counts; a failed write is logged, not
+// returned, so the analysis still reaches the client
+async fn save_analysis(
+    pool: &DbPool,
+    filename: &str,
+    size: usize,
+    elapsed: Duration,
+    result: &AnalysisResult,
+) {
+    let info = &result.parsing_info;
+    let upload = NewLogUpload {
+        filename: filename.to_string(),
+        file_size_bytes: size as i64,
+        total_lines: info.total_lines as i32,
+        parsed_lines: info.parsed_lines as i32,
+        failed_lines: info.skipped_lines as i32,
+        analysis_mode: "standard".to_string(),
+        processing_time_ms: elapsed.as_millis() as i32,
+        user_ip: None,
+    };
+    let saved = match queries::save_log_upload(pool, &upload).await {
+        Ok(upload_id) => {
+            queries::save_analysis_result(pool, &NewAnalysisResult::from_analysis(upload_id, result)).await
         }
-        
-        // Try unified parser - supports Apache, generic, and fallback formats
-        if let Some(entry) = parse_log_line_unified(line) {
-            parsed_lines += 1;
-            
-            // Track format quality
-            if parse_apache_combined(line).is_ok() {
-                perfect_format += 1; // Apache format
-            } else if entry.timestamp.contains('-') && !entry.level.is_empty() {
-                alternative_format += 1; // Generic structured format
-            } else {
-                fallback_format += 1; // Minimal parsing
-            }
-            
-            entries.push(entry);
-        } else if parse_errors.len() < 10 {
-            parse_errors.push(ParseError {
-                line_number: total_lines,
-                line_content: if line.len() > 100 {
-                    format!("{}...", &line[..100])
-                } else {
-                    line.to_string()
-                },
-                error_type: "Parse failed".to_string(),
-                suggestion: "Line was empty or invalid".to_string(),
-            });
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        eprintln!("[WARN] Failed to save analysis of {}: {}", filename, e);
+    }
+}
+
+// Look up where the IPs in a result are, fill in their location and check the
+// account logins for impossible travel
+pub async fn enrich_with_geolocation(result: &mut AnalysisResult) {
+    let mut all_ip_strings: Vec<String> = result.ip_analysis.all_ips.iter().map(|ip| ip.ip.clone()).collect();
+    for login in &result.logins {
+        if !all_ip_strings.contains(&login.ip) {
+            all_ip_strings.push(login.ip.clone());
+        }
+    }
+    if all_ip_strings.is_empty() {
+        return;
+    }
+
+    println!("[INFO] Looking up geolocation for {} IPs...", all_ip_strings.len());
+    let geo_data = geolocation::lookup_batch(&all_ip_strings).await;
+
+    for ip_info in result.ip_analysis.all_ips.iter_mut().chain(result.ip_analysis.high_risk_ips.iter_mut()) {
+        if let Some(geo) = geo_data.get(&ip_info.ip) {
+            ip_info.country = geo.country.clone();
+            ip_info.city = geo.city.clone();
+            ip_info.is_vpn = geo.is_proxy || geo.is_hosting;
         }
     }
+
+    result.impossible_travel = travel::detect(&result.logins, &geo_data);
+}
+
+// Process logs with basic analyzer
+pub fn process_logs(content: &str, options: &ParseOptions) -> AnalysisResult {
+    use security_common::parsers::parse_content_with;
+    
+    // Parse all lines with unified parser (supports multiple formats,
+    // including container runtime records split across lines)
+    let parsed = parse_content_with(content, options);
+    let integrity = integrity::check(content, &parsed);
+    let mut entries = parsed.entries;
+    let threat_intel = ioc::active().tag(&mut entries, &parsed.line_numbers);
+    let sigma = rules::sigma::active().scan(&entries, &parsed.line_numbers);
+    let mut alert_suppressions = SuppressionReport::default();
+    let alerts = rules::alerts::active().evaluate(&entries, &mut alert_suppressions);
     
     // Run basic analysis
     let analyzer = BasicAnalyzer::new();
-    let analysis = analyzer.analyze(&entries);
+    let mut analysis = analyzer.analyze_lines(&entries, &parsed.line_numbers);
+    analysis.suppressed.merge(alert_suppressions);
     let cvss_scores = analyzer.generate_cvss_scores(&analysis);
     
     // Build IP analysis
@@ -266,14 +445,16 @@ pub fn process_logs(content: &str) -> AnalysisResult {
     ip_vec.sort_by(|a, b| b.1.cmp(a.1));
     
     let high_risk_ips: Vec<IpInfo> = ip_vec.iter()
-        .filter(|(_, count)| **count >= 3)
+        .filter(|(ip, _)| analysis.is_high_risk(ip))
         .map(|(ip, count)| IpInfo {
             ip: ip.to_string(),
             count: **count,
             risk_level: "high".to_string(),
+            total_bytes: analysis.ip_bytes.get(ip.as_str()).copied().unwrap_or(0),
             country: None,
             city: None,
             is_vpn: false,
+            threat_intel: ioc::active().match_ip(ip),
         })
         .collect();
     
@@ -281,43 +462,21 @@ pub fn process_logs(content: &str) -> AnalysisResult {
         .map(|(ip, count)| IpInfo {
             ip: ip.to_string(),
             count: **count,
-            risk_level: if **count >= 3 { "high" } else { "low" }.to_string(),
+            risk_level: if analysis.is_high_risk(ip) { "high" } else { "low" }.to_string(),
+            total_bytes: analysis.ip_bytes.get(ip.as_str()).copied().unwrap_or(0),
             country: None,
             city: None,
             is_vpn: false,
+            threat_intel: ioc::active().match_ip(ip),
         })
         .collect();
     
     // Calculate aggregate CVSS
-    let total_threats = analysis.failed_logins + analysis.root_attempts + 
-                        analysis.suspicious_file_access + analysis.critical_alerts + 
-                        analysis.sql_injection_attempts + analysis.port_scanning_attempts + 
-                        analysis.malware_detections;
-    
-    let mut threat_types_for_aggregate = Vec::new();
-    if analysis.sql_injection_attempts > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::SQLInjection, analysis.sql_injection_attempts));
-    }
-    if analysis.failed_logins > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::FailedLogin, analysis.failed_logins));
-    }
-    if analysis.root_attempts > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::RootAccess, analysis.root_attempts));
-    }
-    if analysis.suspicious_file_access > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::SuspiciousFileAccess, analysis.suspicious_file_access));
-    }
-    if analysis.port_scanning_attempts > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::PortScanning, analysis.port_scanning_attempts));
-    }
-    if analysis.malware_detections > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::Malware, analysis.malware_detections));
-    }
-    if analysis.critical_alerts > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::CriticalAlert, analysis.critical_alerts));
-    }
-    
-    let aggregate_cvss = cvss::calculate_aggregate_score(&threat_types_for_aggregate);
+    let total_threats = analysis.total_threats();
+    // Per threat type and per CVE, so exploit signatures weigh in with their own scores
+    let scores_for_aggregate: Vec<(f32, usize)> = cvss_scores.iter().map(|s| (s.cvss_score, s.count)).collect();
+
+    let aggregate_cvss = cvss::aggregate_scores(&scores_for_aggregate);
     
     let (level, description) = if total_threats > 20 {
         ("CRITICAL", "Immediate action required")
@@ -336,6 +495,11 @@ pub fn process_logs(content: &str) -> AnalysisResult {
             suspicious_file_access: analysis.suspicious_file_access,
             critical_alerts: analysis.critical_alerts,
             sql_injection_attempts: analysis.sql_injection_attempts,
+            xss_attempts: analysis.xss_attempts,
+            path_traversal_attempts: analysis.path_traversal_attempts,
+            command_injection_attempts: analysis.command_injection_attempts,
+            scanner_detections: analysis.scanner_detections,
+            unauthorized_access_attempts: analysis.unauthorized_access_attempts,
             port_scanning_attempts: analysis.port_scanning_attempts,
             malware_detections: analysis.malware_detections,
             cvss_scores,
@@ -351,18 +515,68 @@ pub fn process_logs(content: &str) -> AnalysisResult {
             cvss_aggregate_score: aggregate_cvss.base_score,
             cvss_severity: aggregate_cvss.severity.as_str().to_string(),
         },
-        parsing_info: ParsingInfo {
-            total_lines,
-            parsed_lines,
-            skipped_lines: total_lines - parsed_lines,
-            errors: parse_errors,
-            format_quality: FormatQuality {
-                perfect_format,
-                alternative_format,
-                fallback_format,
-            },
-        },
-        alerts: Vec::new(),
+        parsing_info: parsed.parsing_info,
+        alerts,
+        auth_attacks: analysis.auth_attacks,
+        port_scans: analysis.port_scans,
+        rate_anomalies: analysis.rate_anomalies,
+        exfiltration: analysis.exfiltration,
+        content_discovery: analysis.content_discovery,
+        sessions: analysis.sessions,
+        logins: analysis.logins,
+        // Filled in by enrich_with_geolocation
+        impossible_travel: Vec::new(),
+        sigma,
+        integrity,
+        suppressed: analysis.suppressed,
+        threat_intel,
+        rule_precision: feedback::for_findings(&analysis.findings),
+        findings: analysis.findings,
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analysis_row_carries_each_threat_count() {
+        let content = [
+            "10.0.0.1 - - [15/Dec/2025:17:19:00 +0000] \"GET /item?id=1%27%20UNION%20SELECT%20password%20FROM%20users-- HTTP/1.1\" 200 512 \"-\" \"Mozilla/5.0\"",
+            "10.0.0.2 - - [15/Dec/2025:17:19:01 +0000] \"GET /search?q=<script>alert(1)</script> HTTP/1.1\" 200 512 \"-\" \"Mozilla/5.0\"",
+            "10.0.0.3 - - [15/Dec/2025:17:19:02 +0000] \"GET /download?file=../../../../etc/passwd HTTP/1.1\" 200 512 \"-\" \"Mozilla/5.0\"",
+            "10.0.0.4 - - [15/Dec/2025:17:19:03 +0000] \"GET /ping?host=127.0.0.1;cat%20/etc/shadow HTTP/1.1\" 200 512 \"-\" \"Mozilla/5.0\"",
+            "10.0.0.5 - - [15/Dec/2025:17:19:04 +0000] \"GET /index.html HTTP/1.1\" 200 512 \"-\" \"Mozilla/5.0\"",
+        ]
+        .join("\n");
+        let result = process_logs(&content, &ParseOptions::default());
+        let stats = &result.threat_statistics;
+        let row = NewAnalysisResult::from_analysis(7, &result);
+
+        assert_eq!(row.upload_id, 7);
+        assert_eq!(row.risk_level, result.risk_assessment.level.to_lowercase());
+        assert_eq!(row.total_threats as usize, result.risk_assessment.total_threats);
+        assert!(stats.sql_injection_attempts > 0);
+        assert_eq!(row.sql_injection_count as usize, stats.sql_injection_attempts);
+        assert!(stats.xss_attempts > 0);
+        assert_eq!(row.xss_count as usize, stats.xss_attempts);
+        assert!(stats.path_traversal_attempts > 0);
+        assert_eq!(row.path_traversal_count as usize, stats.path_traversal_attempts);
+        assert!(stats.command_injection_attempts > 0);
+        assert_eq!(row.command_injection_count as usize, stats.command_injection_attempts);
+        let dedicated = stats.sql_injection_attempts
+            + stats.xss_attempts
+            + stats.path_traversal_attempts
+            + stats.command_injection_attempts;
+        assert_eq!(
+            row.suspicious_patterns_count as usize,
+            result.risk_assessment.total_threats - dedicated
+        );
+        let quality = &result.parsing_info.format_quality;
+        assert_eq!(row.perfect_format_count as usize, quality.perfect_format);
+        assert_eq!(row.minor_issues_count as usize, quality.alternative_format);
+        assert_eq!(row.major_issues_count as usize, quality.fallback_format);
+        assert_eq!(row.format_quality_percentage, 100.0);
+    }
+}
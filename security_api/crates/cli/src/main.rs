@@ -1,5 +1,6 @@
 use std::fs;
 use std::io::{self, Read};
+use std::path::Path;
 use std::process;
 
 use clap::{Parser, ValueEnum};
@@ -7,10 +8,18 @@ use colored::Colorize;
 
 use security_common::{
     AnalysisResult, ThreatStats, IpAnalysis, IpInfo,
-    RiskAssessment, ParsingInfo, FormatQuality, ParseError,
-    cvss,
+    RiskAssessment, cvss, integrity, ioc::{self, IocSet},
+    feedback::{self, FeedbackRecord, FeedbackStore, Verdict},
+};
+use security_common::rules::{
+    self, RuleSet, alerts::{self, AlertEngine}, allowlist::{self, Allowlist, SuppressionReport},
+    sigma::{self, SigmaRuleSet},
+};
+use security_common::parsers::{
+    DEFAULT_ERROR_SAMPLE_LIMIT, JsonProfile, ParseOptions, parse_content_with,
 };
 use security_analyzer_basic::BasicAnalyzer;
+use security_analyzer_basic::corpus::{self, Baseline, Corpus};
 
 mod output;
 
@@ -19,7 +28,7 @@ mod output;
     name = "logr",
     about = "Security log analyzer — detect threats, score risks, and audit logs from the terminal",
     version,
-    after_help = "EXAMPLES:\n  logr analyze access.log\n  logr analyze /var/log/auth.log --format json\n  cat syslog | logr analyze -\n  logr analyze access.log --severity high --ci"
+    after_help = "EXAMPLES:\n  logr analyze access.log\n  logr analyze /var/log/auth.log --format json\n  cat syslog | logr analyze -\n  logr analyze access.log --severity high --ci\n  logr analyze app.ndjson --json-profile ecs\n  logr analyze access.log --rules my-rules.toml\n  logr analyze access.log --sigma sigma/rules/web\n  logr analyze auth.log --alerts my-alerts.toml\n  logr analyze access.log --allowlist allowlist.toml\n  logr analyze access.log --ioc /var/lib/logr/feeds\n  logr feedback mark http.scanner fp --note \"uptime monitor\"\n  logr analyze access.log --feedback logr-feedback.jsonl\n  logr eval crates/analyzer-basic/corpus --baseline crates/analyzer-basic/corpus/baseline.json"
 )]
 struct Cli {
     #[command(subcommand)]
@@ -48,9 +57,142 @@ enum Commands {
         /// Show full details including all IPs and parse errors
         #[arg(long)]
         verbose: bool,
+
+        /// Field mapping for JSON logs: ecs, bunyan, pino, zap, serilog,
+        /// or a path to a TOML/JSON mapping file
+        #[arg(long, value_name = "PROFILE")]
+        json_profile: Option<String>,
+
+        /// Only accept recognized formats; report every other line as a parse error
+        #[arg(long)]
+        strict: bool,
+
+        /// Maximum parse error samples kept per error category
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_ERROR_SAMPLE_LIMIT)]
+        error_samples: usize,
+
+        /// Extra detection rule file (TOML or YAML); repeat to load several.
+        /// Rules with the id of a built-in rule replace it
+        #[arg(long = "rules", value_name = "FILE")]
+        rule_files: Vec<String>,
+
+        /// Sigma rule file or directory (searched recursively); repeat to load several
+        #[arg(long = "sigma", value_name = "PATH")]
+        sigma_paths: Vec<String>,
+
+        /// Alert rule file (.toml, .yaml); repeat to load several
+        #[arg(long = "alerts", value_name = "FILE")]
+        alert_files: Vec<String>,
+
+        /// Allowlist file (.toml, .yaml) of traffic to suppress; repeat to
+        /// load several. Suppressed counts are still reported
+        #[arg(long = "allowlist", value_name = "FILE")]
+        allowlist_files: Vec<String>,
+
+        /// Threat-intelligence feed (IP/CIDR/domain list, CSV or STIX 2.1
+        /// JSON) or directory of feeds; repeat to load several
+        #[arg(long = "ioc", value_name = "PATH")]
+        ioc_paths: Vec<String>,
+
+        /// Analyst verdicts (from `logr feedback mark`); findings of rules
+        /// that keep producing false positives are lowered in severity
+        #[arg(long = "feedback", value_name = "FILE")]
+        feedback_file: Option<String>,
+    },
+    /// Record analyst verdicts on findings and show per-rule precision
+    Feedback {
+        #[command(subcommand)]
+        action: FeedbackAction,
+    },
+    /// Measure detection accuracy (per threat type precision, recall and F1)
+    /// on a labeled corpus
+    Eval {
+        /// Corpus file or directory of `*.corpus` files; repeat to load several
+        #[arg(required = true)]
+        corpus: Vec<String>,
+
+        /// Extra detection rule file (TOML or YAML) to evaluate with the
+        /// built-in rules; repeat to load several
+        #[arg(long = "rules", value_name = "FILE")]
+        rule_files: Vec<String>,
+
+        /// Scores to hold: exit with code 1 if any threat type's recall falls
+        /// below its baseline recall
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<String>,
+
+        /// Write this run's scores as the new baseline
+        #[arg(long = "save-baseline", value_name = "FILE")]
+        save_baseline: Option<String>,
+
+        /// Output format (table or json)
+        #[arg(short, long, default_value = "table")]
+        format: OutputFormat,
+
+        /// List every line whose detections differ from its labels
+        #[arg(long)]
+        verbose: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum FeedbackAction {
+    /// Mark findings of a rule as a true or false positive
+    Mark {
+        /// Rule id of the finding, as shown in verbose output or JSON
+        /// (`http.scanner`, `auth.brute_force`, ...)
+        rule: String,
+
+        /// Verdict: tp (true positive) or fp (false positive)
+        #[arg(value_parser = parse_verdict)]
+        verdict: Verdict,
+
+        /// Source line of the finding
+        #[arg(long)]
+        line: Option<usize>,
+
+        /// Text the finding matched
+        #[arg(long)]
+        matched: Option<String>,
+
+        /// Who made the call
+        #[arg(long)]
+        analyst: Option<String>,
+
+        /// Why
+        #[arg(long)]
+        note: Option<String>,
+
+        /// Feedback file verdicts are appended to
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_FEEDBACK_FILE)]
+        store: String,
+
+        /// Extra detection rule file the finding came from; repeat to load several
+        #[arg(long = "rules", value_name = "FILE")]
+        rule_files: Vec<String>,
+
+        /// Sigma rule file or directory the finding came from; repeat to load several
+        #[arg(long = "sigma", value_name = "PATH")]
+        sigma_paths: Vec<String>,
+    },
+    /// Show precision per rule from the recorded verdicts
+    Stats {
+        /// Feedback file
+        #[arg(long, value_name = "FILE", default_value = DEFAULT_FEEDBACK_FILE)]
+        store: String,
+
+        /// Output format (table or json)
+        #[arg(short, long, default_value = "table")]
+        format: OutputFormat,
     },
 }
 
+const DEFAULT_FEEDBACK_FILE: &str = "logr-feedback.jsonl";
+
+fn parse_verdict(value: &str) -> Result<Verdict, String> {
+    Verdict::from_name(value).ok_or_else(|| format!("expected tp or fp, got '{}'", value))
+}
+
 #[derive(ValueEnum, Clone, Copy)]
 enum OutputFormat {
     /// Human-readable table output with colors
@@ -90,9 +232,73 @@ fn main() {
             severity,
             ci,
             verbose,
+            json_profile,
+            strict,
+            error_samples,
+            rule_files,
+            sigma_paths,
+            alert_files,
+            allowlist_files,
+            ioc_paths,
+            feedback_file,
         } => {
+            if !rule_files.is_empty() {
+                let installed = RuleSet::with_files(&rule_files).and_then(rules::install);
+                if let Err(e) = installed {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                }
+            }
+            if !alert_files.is_empty() {
+                let installed = AlertEngine::with_files(&alert_files)
+                    .and_then(|engine| engine.check(rules::active()).map(|_| engine))
+                    .and_then(alerts::install);
+                if let Err(e) = installed {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                }
+            }
+            if !allowlist_files.is_empty() {
+                let installed = Allowlist::with_files(&allowlist_files).and_then(allowlist::install);
+                if let Err(e) = installed {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                }
+            }
+            if let Some(path) = feedback_file {
+                let installed = FeedbackStore::open(&path).and_then(feedback::install);
+                if let Err(e) = installed {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                }
+            }
+            if !ioc_paths.is_empty() {
+                let installed = IocSet::load_paths(&ioc_paths).and_then(ioc::install);
+                if let Err(e) = installed {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                }
+            }
+            if !sigma_paths.is_empty() {
+                let installed = SigmaRuleSet::load_paths(&sigma_paths).and_then(sigma::install);
+                if let Err(e) = installed {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                }
+            }
+
+            let options = ParseOptions {
+                json_profile: json_profile.map(|spec| {
+                    JsonProfile::from_spec(&spec).unwrap_or_else(|e| {
+                        eprintln!("{} {}", "error:".red().bold(), e);
+                        process::exit(2);
+                    })
+                }),
+                strict,
+                error_sample_limit: error_samples,
+            };
             let content = read_input(&file);
-            let result = analyze_content(&content);
+            let result = analyze_content(&content, &options);
 
             match format {
                 OutputFormat::Table => output::print_table(&result, &file, severity, verbose),
@@ -113,6 +319,76 @@ fn main() {
                 }
             }
         }
+        Commands::Feedback { action } => match action {
+            FeedbackAction::Mark { rule, verdict, line, matched, analyst, note, store, rule_files, sigma_paths } => {
+                if !rule_files.is_empty() {
+                    let installed = RuleSet::with_files(&rule_files).and_then(rules::install);
+                    if let Err(e) = installed {
+                        eprintln!("{} {}", "error:".red().bold(), e);
+                        process::exit(2);
+                    }
+                }
+                if !sigma_paths.is_empty() {
+                    let installed = SigmaRuleSet::load_paths(&sigma_paths).and_then(sigma::install);
+                    if let Err(e) = installed {
+                        eprintln!("{} {}", "error:".red().bold(), e);
+                        process::exit(2);
+                    }
+                }
+                let Some(rule) = feedback::known_rule(&rule) else {
+                    eprintln!("{} Unknown rule id '{}'", "error:".red().bold(), rule.trim());
+                    process::exit(2);
+                };
+
+                let mut record = FeedbackRecord::new(&rule.id, verdict);
+                record.threat_type = rule.threat_type;
+                record.line_number = line;
+                record.matched = matched;
+                record.analyst = analyst;
+                record.note = note;
+
+                let mut feedback = FeedbackStore::open(&store).unwrap_or_else(|e| {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                });
+                if let Err(e) = feedback.record(record) {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                }
+                let precision: Vec<_> = feedback.precision().into_iter().filter(|p| p.rule_id == rule.id).collect();
+                output::print_feedback(&precision, OutputFormat::Table);
+            }
+            FeedbackAction::Stats { store, format } => {
+                let feedback = FeedbackStore::open(&store).unwrap_or_else(|e| {
+                    eprintln!("{} {}", "error:".red().bold(), e);
+                    process::exit(2);
+                });
+                output::print_feedback(&feedback.precision(), format);
+            }
+        },
+        Commands::Eval { corpus, rule_files, baseline, save_baseline, format, verbose } => {
+            let fail = |e: String| -> ! {
+                eprintln!("{} {}", "error:".red().bold(), e);
+                process::exit(2);
+            };
+            let corpora = Corpus::load_paths(&corpus).unwrap_or_else(|e| fail(e));
+            let rules = RuleSet::with_files(&rule_files).unwrap_or_else(|e| fail(e));
+            let baseline = baseline.map(|path| Baseline::from_file(Path::new(&path)).unwrap_or_else(|e| fail(e)));
+
+            let report = corpus::evaluate(&BasicAnalyzer::with_rules(rules), &corpora);
+            let regressions = baseline.as_ref().map(|b| report.recall_regressions(b)).unwrap_or_default();
+            output::print_accuracy(&report, &regressions, format, verbose);
+
+            if let Some(path) = save_baseline {
+                if let Err(e) = fs::write(&path, report.baseline().to_json() + "\n") {
+                    fail(format!("Failed to write {}: {}", path, e));
+                }
+                eprintln!("Baseline written to {}", path);
+            }
+            if !regressions.is_empty() {
+                process::exit(1);
+            }
+        }
     }
 }
 
@@ -134,52 +410,18 @@ fn read_input(file: &str) -> String {
     }
 }
 
-fn analyze_content(content: &str) -> AnalysisResult {
-    use security_common::parsers::{parse_log_line_unified, parse_apache_combined};
-
-    let mut entries = Vec::new();
-    let mut total_lines: usize = 0;
-    let mut parsed_lines: usize = 0;
-    let mut parse_errors: Vec<ParseError> = Vec::new();
-    let mut perfect_format: usize = 0;
-    let mut alternative_format: usize = 0;
-    let mut fallback_format: usize = 0;
-
-    for line in content.lines() {
-        total_lines += 1;
-
-        if line.trim().is_empty() {
-            continue;
-        }
-
-        if let Some(entry) = parse_log_line_unified(line) {
-            parsed_lines += 1;
-
-            if parse_apache_combined(line).is_ok() {
-                perfect_format += 1;
-            } else if entry.timestamp.contains('-') && !entry.level.is_empty() {
-                alternative_format += 1;
-            } else {
-                fallback_format += 1;
-            }
-
-            entries.push(entry);
-        } else if parse_errors.len() < 10 {
-            parse_errors.push(ParseError {
-                line_number: total_lines,
-                line_content: if line.len() > 100 {
-                    format!("{}...", &line[..100])
-                } else {
-                    line.to_string()
-                },
-                error_type: "Parse failed".to_string(),
-                suggestion: "Line was empty or invalid".to_string(),
-            });
-        }
-    }
+fn analyze_content(content: &str, options: &ParseOptions) -> AnalysisResult {
+    let parsed = parse_content_with(content, options);
+    let integrity = integrity::check(content, &parsed);
+    let mut entries = parsed.entries;
+    let threat_intel = ioc::active().tag(&mut entries, &parsed.line_numbers);
+    let sigma = rules::sigma::active().scan(&entries, &parsed.line_numbers);
+    let mut alert_suppressions = SuppressionReport::default();
+    let alerts = rules::alerts::active().evaluate(&entries, &mut alert_suppressions);
 
     let analyzer = BasicAnalyzer::new();
-    let analysis = analyzer.analyze(&entries);
+    let mut analysis = analyzer.analyze_lines(&entries, &parsed.line_numbers);
+    analysis.suppressed.merge(alert_suppressions);
     let cvss_scores = analyzer.generate_cvss_scores(&analysis);
 
     let mut ip_vec: Vec<_> = analysis.ip_frequency.iter().collect();
@@ -187,14 +429,16 @@ fn analyze_content(content: &str) -> AnalysisResult {
 
     let high_risk_ips: Vec<IpInfo> = ip_vec
         .iter()
-        .filter(|(_, count)| **count >= 3)
+        .filter(|(ip, _)| analysis.is_high_risk(ip))
         .map(|(ip, count)| IpInfo {
             ip: ip.to_string(),
             count: **count,
             risk_level: "high".to_string(),
+            total_bytes: analysis.ip_bytes.get(ip.as_str()).copied().unwrap_or(0),
             country: None,
             city: None,
             is_vpn: false,
+            threat_intel: ioc::active().match_ip(ip),
         })
         .collect();
 
@@ -203,51 +447,20 @@ fn analyze_content(content: &str) -> AnalysisResult {
         .map(|(ip, count)| IpInfo {
             ip: ip.to_string(),
             count: **count,
-            risk_level: if **count >= 3 { "high" } else { "low" }.to_string(),
+            risk_level: if analysis.is_high_risk(ip) { "high" } else { "low" }.to_string(),
+            total_bytes: analysis.ip_bytes.get(ip.as_str()).copied().unwrap_or(0),
             country: None,
             city: None,
             is_vpn: false,
+            threat_intel: ioc::active().match_ip(ip),
         })
         .collect();
 
-    let total_threats = analysis.failed_logins
-        + analysis.root_attempts
-        + analysis.suspicious_file_access
-        + analysis.critical_alerts
-        + analysis.sql_injection_attempts
-        + analysis.port_scanning_attempts
-        + analysis.malware_detections;
-
-    let mut threat_types_for_aggregate = Vec::new();
-    if analysis.sql_injection_attempts > 0 {
-        threat_types_for_aggregate
-            .push((cvss::ThreatType::SQLInjection, analysis.sql_injection_attempts));
-    }
-    if analysis.failed_logins > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::FailedLogin, analysis.failed_logins));
-    }
-    if analysis.root_attempts > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::RootAccess, analysis.root_attempts));
-    }
-    if analysis.suspicious_file_access > 0 {
-        threat_types_for_aggregate.push((
-            cvss::ThreatType::SuspiciousFileAccess,
-            analysis.suspicious_file_access,
-        ));
-    }
-    if analysis.port_scanning_attempts > 0 {
-        threat_types_for_aggregate
-            .push((cvss::ThreatType::PortScanning, analysis.port_scanning_attempts));
-    }
-    if analysis.malware_detections > 0 {
-        threat_types_for_aggregate.push((cvss::ThreatType::Malware, analysis.malware_detections));
-    }
-    if analysis.critical_alerts > 0 {
-        threat_types_for_aggregate
-            .push((cvss::ThreatType::CriticalAlert, analysis.critical_alerts));
-    }
+    let total_threats = analysis.total_threats();
+    // Per threat type and per CVE, so exploit signatures weigh in with their own scores
+    let scores_for_aggregate: Vec<(f32, usize)> = cvss_scores.iter().map(|s| (s.cvss_score, s.count)).collect();
 
-    let aggregate_cvss = cvss::calculate_aggregate_score(&threat_types_for_aggregate);
+    let aggregate_cvss = cvss::aggregate_scores(&scores_for_aggregate);
 
     let (level, description) = if total_threats > 20 {
         ("CRITICAL", "Immediate action required")
@@ -266,6 +479,11 @@ fn analyze_content(content: &str) -> AnalysisResult {
             suspicious_file_access: analysis.suspicious_file_access,
             critical_alerts: analysis.critical_alerts,
             sql_injection_attempts: analysis.sql_injection_attempts,
+            xss_attempts: analysis.xss_attempts,
+            path_traversal_attempts: analysis.path_traversal_attempts,
+            command_injection_attempts: analysis.command_injection_attempts,
+            scanner_detections: analysis.scanner_detections,
+            unauthorized_access_attempts: analysis.unauthorized_access_attempts,
             port_scanning_attempts: analysis.port_scanning_attempts,
             malware_detections: analysis.malware_detections,
             cvss_scores,
@@ -281,17 +499,22 @@ fn analyze_content(content: &str) -> AnalysisResult {
             cvss_aggregate_score: aggregate_cvss.base_score,
             cvss_severity: aggregate_cvss.severity.as_str().to_string(),
         },
-        parsing_info: ParsingInfo {
-            total_lines,
-            parsed_lines,
-            skipped_lines: total_lines - parsed_lines,
-            errors: parse_errors,
-            format_quality: FormatQuality {
-                perfect_format,
-                alternative_format,
-                fallback_format,
-            },
-        },
-        alerts: Vec::new(),
+        parsing_info: parsed.parsing_info,
+        alerts,
+        auth_attacks: analysis.auth_attacks,
+        port_scans: analysis.port_scans,
+        rate_anomalies: analysis.rate_anomalies,
+        exfiltration: analysis.exfiltration,
+        content_discovery: analysis.content_discovery,
+        sessions: analysis.sessions,
+        logins: analysis.logins,
+        // Needs geolocation lookups, which only the API makes
+        impossible_travel: Vec::new(),
+        sigma,
+        integrity,
+        suppressed: analysis.suppressed,
+        threat_intel,
+        rule_precision: feedback::for_findings(&analysis.findings),
+        findings: analysis.findings,
     }
 }
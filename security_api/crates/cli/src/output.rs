@@ -4,9 +4,12 @@ use comfy_table::{
     ContentArrangement, Table,
 };
 
+use security_analyzer_basic::corpus::{AccuracyReport, Regression, ThreatAccuracy};
+use security_analyzer_basic::exfil::human_bytes;
 use security_common::AnalysisResult;
+use security_common::feedback::RulePrecision;
 
-use crate::SeverityFilter;
+use crate::{OutputFormat, SeverityFilter};
 
 pub fn print_table(
     result: &AnalysisResult,
@@ -93,6 +96,40 @@ pub fn print_table(
         for line in table.to_string().lines() {
             println!("  {}", line);
         }
+
+        // Where each threat was found
+        if verbose {
+            println!();
+            println!("  {}", "FINDINGS".dimmed());
+            for finding in result
+                .findings
+                .iter()
+                .filter(|f| match &f.vulnerability {
+                    Some(vulnerability) => vulnerability.cvss.base_score,
+                    None => f.threat_type.cvss_score().base_score,
+                } >= min_score)
+            {
+                let lowered = match &finding.downgraded_from {
+                    Some(original) => format!(" (lowered from {})", original.as_str().to_uppercase()),
+                    None => String::new(),
+                };
+                let cve = match &finding.vulnerability {
+                    Some(vulnerability) => format!(" {} {}", vulnerability.cve, vulnerability.product),
+                    None => String::new(),
+                };
+                println!(
+                    "  {}  {}{}  {}{}  {}  {}  {}",
+                    format!("L{}", finding.line_number).dimmed(),
+                    color_severity(&finding.severity.as_str().to_uppercase()),
+                    lowered.dimmed(),
+                    finding.threat_type.display_name().white(),
+                    cve.red(),
+                    finding.ip_address.as_deref().unwrap_or("-").white(),
+                    format!("{}: {}", finding.field, finding.matched).dimmed(),
+                    format!("[{}]", finding.rule_id).dimmed()
+                );
+            }
+        }
     }
 
     // High risk IPs
@@ -107,11 +144,17 @@ pub fn print_table(
         };
 
         for ip in result.ip_analysis.high_risk_ips.iter().take(show_count) {
-            println!(
-                "  {}  {}",
-                ip.ip.white(),
-                format!("({} requests)", ip.count).dimmed()
-            );
+            let traffic = if ip.total_bytes > 0 {
+                format!("({} requests, {})", ip.count, human_bytes(ip.total_bytes))
+            } else {
+                format!("({} requests)", ip.count)
+            };
+            let feeds: Vec<&str> = ip.threat_intel.iter().map(|i| i.feed.as_str()).collect();
+            if feeds.is_empty() {
+                println!("  {}  {}", ip.ip.white(), traffic.dimmed());
+            } else {
+                println!("  {}  {}  {}", ip.ip.white(), traffic.dimmed(), format!("[{}]", feeds.join(", ")).red());
+            }
         }
 
         let remaining = result
@@ -124,16 +167,307 @@ pub fn print_table(
         }
     }
 
-    // Parse errors (verbose only)
-    if verbose && !result.parsing_info.errors.is_empty() {
+    // Authentication attacks, by shape
+    if !result.auth_attacks.is_empty() {
+        println!();
+        println!("  {}", "AUTH ATTACKS".dimmed());
+        for attack in &result.auth_attacks {
+            println!(
+                "  {}  {}  {}",
+                color_severity(&attack.severity.to_uppercase()),
+                attack.pattern.white(),
+                attack.description.dimmed()
+            );
+            let limit = if verbose { usize::MAX } else { 5 };
+            println!("    {} {}", "accounts:".dimmed(), summarize(&attack.accounts, limit));
+            println!("    {} {}", "sources: ".dimmed(), summarize(&attack.source_ips, limit));
+            if verbose {
+                for line in &attack.evidence {
+                    println!("    {}", line.dimmed());
+                }
+            } else if !attack.evidence.is_empty() {
+                println!("    {}", format!("{} events of evidence, use --verbose to list", attack.evidence.len()).dimmed());
+            }
+        }
+    }
+
+    // Port scans from firewall records
+    if !result.port_scans.is_empty() {
+        println!();
+        println!("  {}", "PORT SCANS".dimmed());
+        let limit = if verbose { usize::MAX } else { 5 };
+        for scan in &result.port_scans {
+            println!("  {}  {}", scan.kind.to_uppercase().yellow(), scan.description.white());
+            let ports: Vec<String> = scan.ports.iter().map(u16::to_string).collect();
+            println!("    {} {}", "ports:  ".dimmed(), summarize(&ports, limit));
+            println!("    {} {}", "targets:".dimmed(), summarize(&scan.targets, limit));
+        }
+    }
+
+    // Request rates far above the log's baseline, strongest first
+    if !result.rate_anomalies.is_empty() {
+        println!();
+        println!("  {}", "RATE ANOMALIES".dimmed());
+        let show_count = if verbose { result.rate_anomalies.len() } else { 10 };
+        for anomaly in result.rate_anomalies.iter().take(show_count) {
+            println!(
+                "  {}  {}  {}",
+                anomaly.kind.to_uppercase().yellow(),
+                anomaly.pattern.replace('_', " ").white(),
+                anomaly.description.dimmed()
+            );
+            if verbose {
+                println!("    {} {} → {}", "window:".dimmed(), anomaly.first_seen, anomaly.last_seen);
+            }
+        }
+        let remaining = result.rate_anomalies.len().saturating_sub(show_count);
+        if remaining > 0 {
+            println!("  {}", format!("  ... and {} more", remaining).dimmed());
+        }
+    }
+
+    // Content discovery runs and what they found
+    if !result.content_discovery.is_empty() {
+        println!();
+        println!("  {}", "CONTENT DISCOVERY".dimmed());
+        let limit = if verbose { usize::MAX } else { 5 };
+        for run in &result.content_discovery {
+            println!("  {}  {}", "FORCED BROWSING".yellow(), run.description.white());
+            if !run.sensitive_probes.is_empty() {
+                println!("    {} {}", "probed: ".dimmed(), summarize(&run.sensitive_probes, limit));
+            }
+            if !run.exposed.is_empty() {
+                println!("    {} {}", "exposed:".dimmed(), summarize(&run.exposed, usize::MAX).red().bold());
+            }
+            if verbose {
+                println!("    {} {}", "agents: ".dimmed(), summarize(&run.user_agents, limit));
+            }
+        }
+    }
+
+    // Downloads that look like data leaving the server
+    if !result.exfiltration.is_empty() {
+        println!();
+        println!("  {}", "EXFILTRATION".dimmed());
+        let limit = if verbose { usize::MAX } else { 5 };
+        for exfil in &result.exfiltration {
+            let kind = exfil.kind.replace('_', " ").to_uppercase();
+            let kind = if exfil.after_sql_injection { kind.red().bold() } else { kind.yellow() };
+            println!("  {}  {}", kind, exfil.description.white());
+            println!("    {} {}", "paths:".dimmed(), summarize(&exfil.paths, limit));
+        }
+    }
+
+    // Indicators from the threat-intelligence feeds seen in the log
+    if !result.threat_intel.hits.is_empty() {
+        println!();
+        println!("  {}", "THREAT INTEL".dimmed());
+        let show_count = if verbose { result.threat_intel.hits.len() } else { 10 };
+        for hit in result.threat_intel.hits.iter().take(show_count) {
+            let seen = if hit.value == hit.indicator {
+                String::new()
+            } else {
+                format!(" ({} in {})", hit.value, hit.field)
+            };
+            println!(
+                "  {}  {}{}  {}",
+                hit.feed.red(),
+                hit.indicator.white(),
+                seen.dimmed(),
+                format!("{} entries from line {}", hit.entries, hit.first_line).dimmed()
+            );
+        }
+        let remaining = result.threat_intel.hits.len().saturating_sub(show_count);
+        if remaining > 0 {
+            println!("  {}", format!("  ... and {} more", remaining).dimmed());
+        }
+    }
+
+    // Visitor sessions: totals per class, then the automated visitors
+    if !result.sessions.is_empty() {
+        let mut classes: Vec<(&str, usize)> = Vec::new();
+        for session in &result.sessions {
+            match classes.iter_mut().find(|(class, _)| *class == session.classification) {
+                Some(class) => class.1 += 1,
+                None => classes.push((&session.classification, 1)),
+            }
+        }
+        let totals: Vec<String> = classes.iter().map(|(class, count)| format!("{} {}", count, class)).collect();
+
+        println!();
+        println!("  {}  {}", "SESSIONS".dimmed(), totals.join(" · ").dimmed());
+        let automated: Vec<_> = result.sessions.iter().filter(|s| !s.is_human()).collect();
+        let show_count = if verbose { automated.len() } else { 10 };
+        for session in automated.iter().take(show_count) {
+            println!("  {}  {}", session.classification.to_uppercase().yellow(), session.summary().white());
+            if verbose {
+                println!("    {}", session.bot_signals.join("; ").dimmed());
+                println!("    {}", session.activity.join("; ").dimmed());
+            }
+        }
+        let remaining = automated.len().saturating_sub(show_count);
+        if remaining > 0 {
+            println!("  {}", format!("  ... and {} more", remaining).dimmed());
+        }
+    }
+
+    // Threshold alerts, in the order they fired
+    if !result.alerts.is_empty() {
+        println!();
+        println!("  {}", "ALERTS".dimmed());
+
+        let show_count = if verbose { result.alerts.len() } else { 10 };
+        for alert in result.alerts.iter().take(show_count) {
+            println!(
+                "  {}  {}  {}  {}",
+                color_severity(&alert.severity.to_uppercase()),
+                alert.title.white(),
+                alert.ip_address.as_deref().unwrap_or("-").white(),
+                alert.timestamp.dimmed()
+            );
+            if verbose {
+                println!("    {}", alert.description.dimmed());
+            }
+        }
+
+        let remaining = result.alerts.len().saturating_sub(show_count);
+        if remaining > 0 {
+            println!("  {}", format!("  ... and {} more", remaining).dimmed());
+        }
+    }
+
+    // Sigma findings, grouped by rule
+    let sigma = &result.sigma;
+    if !sigma.findings.is_empty() {
+        let mut by_rule: Vec<(&str, &str, &[String], usize)> = Vec::new();
+        for finding in &sigma.findings {
+            match by_rule.iter_mut().find(|(id, ..)| *id == finding.rule_id) {
+                Some(group) => group.3 += 1,
+                None => by_rule.push((&finding.rule_id, &finding.title, &finding.tags, 1)),
+            }
+        }
+
+        println!();
+        println!("  {}", "SIGMA".dimmed());
+        for (rule_id, title, tags, count) in by_rule {
+            let level = sigma
+                .findings
+                .iter()
+                .find(|f| f.rule_id == rule_id)
+                .map(|f| f.level.to_uppercase())
+                .unwrap_or_default();
+            println!(
+                "  {}  {} {}  {}",
+                color_severity(&level),
+                title.white(),
+                format!("×{}", count).dimmed(),
+                tags.join(" ").dimmed()
+            );
+        }
+    }
+    if !sigma.unsupported.is_empty() {
+        println!();
+        println!(
+            "  {} {}",
+            "!".yellow().bold(),
+            format!(
+                "{} Sigma rule{} skipped (unsupported features){}",
+                sigma.unsupported.len(),
+                if sigma.unsupported.len() == 1 { "" } else { "s" },
+                if verbose { "" } else { ", use --verbose to list" }
+            )
+            .dimmed()
+        );
+        if verbose {
+            for skipped in &sigma.unsupported {
+                println!(
+                    "    {} {}",
+                    skipped.title.as_deref().unwrap_or(&skipped.source).white(),
+                    skipped.reason.dimmed()
+                );
+            }
+        }
+    }
+
+    // Tampering signs, in file order
+    let integrity = &result.integrity;
+    if !integrity.is_clean() {
+        println!();
+        println!("  {}", "INTEGRITY".dimmed());
+        let show_count = if verbose { integrity.issues.len() } else { 10 };
+        for issue in integrity.issues.iter().take(show_count) {
+            let lines = match issue.end_line {
+                Some(end) => format!("lines {}-{}", issue.line_number, end),
+                None => format!("line {}", issue.line_number),
+            };
+            println!(
+                "  {}  {}  {}  {}",
+                color_severity(&issue.severity.as_str().to_uppercase()),
+                issue.kind.replace('_', " ").white(),
+                lines.dimmed(),
+                issue.description.white()
+            );
+        }
+        let remaining = integrity.issues.len().saturating_sub(show_count) + integrity.suppressed;
+        if remaining > 0 {
+            println!("  {}", format!("  ... and {} more", remaining).dimmed());
+        }
+    }
+
+    // Allowlist suppressions, so nothing is hidden without a trace
+    let suppressed = &result.suppressed;
+    if suppressed.total() > 0 || !suppressed.expired.is_empty() {
+        println!();
+        println!("  {}", "SUPPRESSED".dimmed());
+        if suppressed.total() > 0 {
+            println!(
+                "  {} findings, {} reports, {} alert events, {} high-risk IPs",
+                suppressed.findings.to_string().white(),
+                suppressed.reports.to_string().white(),
+                suppressed.alert_events.to_string().white(),
+                suppressed.high_risk_ips.to_string().white()
+            );
+        }
+        for (entry, count) in &suppressed.by_entry {
+            println!("  {}  {}", count.to_string().white(), entry.dimmed());
+        }
+        for entry in &suppressed.expired {
+            println!("  {}  {}", "EXPIRED".yellow(), entry.white());
+        }
+    }
+
+    // Analyst verdicts on the rules that fired
+    if !result.rule_precision.is_empty() {
+        println!();
+        println!("  {}", "FEEDBACK".dimmed());
+        for rule in &result.rule_precision {
+            print_rule_precision(rule);
+        }
+    }
+
+    // Parse errors: counts whenever lines were rejected, samples in verbose mode
+    let error_counts = &result.parsing_info.error_counts;
+    if !error_counts.is_empty() {
         println!();
         println!("  {}", "PARSE ERRORS".dimmed());
+        for (category, count) in error_counts {
+            println!("  {}  {}", count.to_string().white(), category.dimmed());
+        }
+    }
+    if verbose && !result.parsing_info.errors.is_empty() {
+        println!();
         for err in &result.parsing_info.errors {
             println!(
                 "  {} {}",
-                format!("L{}:", err.line_number).dimmed(),
+                format!("L{}:{}", err.line_number, err.column).dimmed(),
                 err.line_content.dimmed()
             );
+            println!(
+                "    {} {} {}",
+                format!("{}:", err.error_type).yellow(),
+                "expected".dimmed(),
+                err.expected.white()
+            );
         }
     }
 
@@ -182,6 +516,172 @@ pub fn print_summary(result: &AnalysisResult, file: &str) {
     );
 }
 
+/// Per-rule precision from analyst verdicts (`logr feedback stats`)
+pub fn print_feedback(precision: &[RulePrecision], format: OutputFormat) {
+    if let OutputFormat::Json = format {
+        match serde_json::to_string_pretty(precision) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("{} Failed to serialize: {}", "error:".red().bold(), e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    if precision.is_empty() {
+        println!("  {}", "No verdicts recorded".dimmed());
+        return;
+    }
+    println!();
+    println!("  {}", "FEEDBACK".dimmed());
+    for rule in precision {
+        print_rule_precision(rule);
+    }
+    println!();
+}
+
+fn print_rule_precision(rule: &RulePrecision) {
+    let precision = format!(
+        "{:.0}% precision ({} TP / {} FP)",
+        rule.precision * 100.0,
+        rule.true_positives,
+        rule.false_positives
+    );
+    let recent = match rule.recent_precision {
+        Some(recent) => format!("{:.0}% over the last {} verdicts", recent * 100.0, rule.recent_verdicts),
+        None => "no recent verdicts".to_string(),
+    };
+    let downgrade = match rule.downgrade {
+        0 => String::new(),
+        steps => format!("  severity lowered {} step{}", steps, if steps == 1 { "" } else { "s" }),
+    };
+    println!(
+        "  {}  {}  {}{}",
+        rule.rule_id.white(),
+        precision.white(),
+        recent.dimmed(),
+        downgrade.yellow()
+    );
+}
+
+pub fn print_accuracy(report: &AccuracyReport, regressions: &[Regression], format: OutputFormat, verbose: bool) {
+    if let OutputFormat::Json = format {
+        let json = serde_json::json!({ "accuracy": report, "regressions": regressions });
+        match serde_json::to_string_pretty(&json) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("{} Failed to serialize: {}", "error:".red().bold(), e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    println!();
+    println!(
+        "  {}",
+        format!(
+            "{} labeled lines in {} corpus file{}",
+            report.lines,
+            report.corpora,
+            if report.corpora == 1 { "" } else { "s" }
+        )
+        .dimmed()
+    );
+    if report.unparsed_lines > 0 {
+        println!("  {}", format!("{} lines were not parsed", report.unparsed_lines).yellow());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Threat").fg(TableColor::White),
+            Cell::new("TP").fg(TableColor::White),
+            Cell::new("FP").fg(TableColor::White),
+            Cell::new("FN").fg(TableColor::White),
+            Cell::new("Precision").fg(TableColor::White),
+            Cell::new("Recall").fg(TableColor::White),
+            Cell::new("F1").fg(TableColor::White),
+        ]);
+    let score_cell = |score: f64| {
+        let color = if score >= 0.9 {
+            TableColor::Green
+        } else if score >= 0.6 {
+            TableColor::Yellow
+        } else {
+            TableColor::Red
+        };
+        Cell::new(format!("{:.2}", score)).fg(color)
+    };
+    let row = |threat: &ThreatAccuracy| {
+        vec![
+            Cell::new(&threat.threat_type),
+            Cell::new(threat.true_positives),
+            Cell::new(threat.false_positives),
+            Cell::new(threat.false_negatives),
+            score_cell(threat.precision),
+            score_cell(threat.recall),
+            score_cell(threat.f1),
+        ]
+    };
+    for threat in &report.threats {
+        table.add_row(row(threat));
+    }
+    table.add_row(row(&report.overall));
+    println!("{}", table);
+
+    if !regressions.is_empty() {
+        println!();
+        println!("  {}", "RECALL REGRESSIONS".red().bold());
+        for regression in regressions {
+            println!(
+                "  {}  {:.2} → {:.2}",
+                regression.threat_type.white(),
+                regression.baseline_recall,
+                regression.recall
+            );
+        }
+    }
+
+    if !report.mismatches.is_empty() {
+        println!();
+        println!("  {}", "MISMATCHES".dimmed());
+        let shown = if verbose { report.mismatches.len() } else { 10 };
+        let label = |types: &[String]| if types.is_empty() { "-".to_string() } else { types.join(", ") };
+        for mismatch in report.mismatches.iter().take(shown) {
+            println!(
+                "  {}:{}  expected {}  detected {}",
+                mismatch.corpus.dimmed(),
+                mismatch.line_number,
+                label(&mismatch.expected).green(),
+                label(&mismatch.detected).yellow()
+            );
+            if verbose {
+                println!("    {}", mismatch.text.dimmed());
+            }
+        }
+        if report.mismatches.len() > shown {
+            println!(
+                "  {}",
+                format!("... and {} more (use --verbose to see all)", report.mismatches.len() - shown).dimmed()
+            );
+        }
+    }
+    println!();
+}
+
+/// Comma-separated list, truncated to `limit` items with a "+N more" suffix
+fn summarize(items: &[String], limit: usize) -> String {
+    let shown: Vec<&str> = items.iter().take(limit).map(String::as_str).collect();
+    match items.len().saturating_sub(limit) {
+        0 => shown.join(", "),
+        more => format!("{} +{} more", shown.join(", "), more),
+    }
+}
+
 fn color_severity(level: &str) -> String {
     match level {
         "CRITICAL" => level.red().bold().to_string(),
@@ -1,30 +1,35 @@
-/// CVSS 3.1 (Common Vulnerability Scoring System) Implementation
-/// 
-/// This module provides CVSS scoring for detected security threats.
-/// CVSS is the industry-standard framework for rating vulnerability severity.
-/// 
-/// Score Range: 0.0 - 10.0
-/// - None: 0.0
-/// - Low: 0.1-3.9
-/// - Medium: 4.0-6.9
-/// - High: 7.0-8.9
-/// - Critical: 9.0-10.0
+//! CVSS 3.1 (Common Vulnerability Scoring System) Implementation
+//! 
+//! This module provides CVSS scoring for detected security threats.
+//! CVSS is the industry-standard framework for rating vulnerability severity.
+//! 
+//! Score Range: 0.0 - 10.0
+//! - None: 0.0
+//! - Low: 0.1-3.9
+//! - Medium: 4.0-6.9
+//! - High: 7.0-8.9
+//! - Critical: 9.0-10.0
 
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Severity {
+    #[serde(alias = "none", alias = "NONE")]
     None,
+    #[serde(alias = "low", alias = "LOW")]
     Low,
+    #[serde(alias = "medium", alias = "MEDIUM")]
     Medium,
+    #[serde(alias = "high", alias = "HIGH")]
     High,
+    #[serde(alias = "critical", alias = "CRITICAL")]
     Critical,
 }
 
 impl Severity {
     pub fn from_score(score: f32) -> Self {
         match score {
-            s if s == 0.0 => Severity::None,
+            0.0 => Severity::None,
             s if s < 4.0 => Severity::Low,
             s if s < 7.0 => Severity::Medium,
             s if s < 9.0 => Severity::High,
@@ -53,7 +58,7 @@ impl Severity {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CVSSScore {
     /// Base score (0.0 - 10.0)
     pub base_score: f32,
@@ -77,10 +82,88 @@ impl CVSSScore {
             explanation,
         }
     }
+
+    /// Score a CVSS 3.0/3.1 base vector
+    /// ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H")
+    pub fn from_vector(vector: &str, explanation: String) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("invalid CVSS vector '{}': {}", vector, reason);
+        let mut parts = vector.split('/');
+        if !matches!(parts.next(), Some("CVSS:3.0" | "CVSS:3.1")) {
+            return Err(invalid("expected a CVSS:3.0 or CVSS:3.1 prefix"));
+        }
+        let mut metrics = std::collections::HashMap::new();
+        for part in parts {
+            let (metric, value) = part.split_once(':').ok_or_else(|| invalid(part))?;
+            metrics.insert(metric, value);
+        }
+        let metric = |name: &str| metrics.get(name).copied().ok_or_else(|| invalid(&format!("missing {}", name)));
+
+        let changed = match metric("S")? {
+            "U" => false,
+            "C" => true,
+            _ => return Err(invalid("S must be U or C")),
+        };
+        let weight = |name: &str, table: &[(&str, f64)]| -> Result<f64, String> {
+            let value = metric(name)?;
+            table
+                .iter()
+                .find(|(v, _)| *v == value)
+                .map(|(_, w)| *w)
+                .ok_or_else(|| invalid(&format!("unknown {}:{}", name, value)))
+        };
+        let av = weight("AV", &[("N", 0.85), ("A", 0.62), ("L", 0.55), ("P", 0.2)])?;
+        let ac = weight("AC", &[("L", 0.77), ("H", 0.44)])?;
+        let pr = if changed {
+            weight("PR", &[("N", 0.85), ("L", 0.68), ("H", 0.5)])?
+        } else {
+            weight("PR", &[("N", 0.85), ("L", 0.62), ("H", 0.27)])?
+        };
+        let ui = weight("UI", &[("N", 0.85), ("R", 0.62)])?;
+        let impact_weights = [("H", 0.56), ("L", 0.22), ("N", 0.0)];
+        let (c, i, a) = (weight("C", &impact_weights)?, weight("I", &impact_weights)?, weight("A", &impact_weights)?);
+
+        let iss = 1.0 - (1.0 - c) * (1.0 - i) * (1.0 - a);
+        let impact = if changed {
+            7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15)
+        } else {
+            6.42 * iss
+        };
+        let exploitability = 8.22 * av * ac * pr * ui;
+        let base = if impact <= 0.0 {
+            0.0
+        } else if changed {
+            round_up((1.08 * (impact + exploitability)).min(10.0))
+        } else {
+            round_up((impact + exploitability).min(10.0))
+        };
+        Ok(Self::new(base as f32, vector.to_string(), explanation))
+    }
+}
+
+/// CVSS 3.1 "Roundup": smallest one-decimal number not below `value`,
+/// computed on integers to avoid floating point artifacts
+fn round_up(value: f64) -> f64 {
+    let scaled = (value * 100_000.0).round() as i64;
+    if scaled % 10_000 == 0 {
+        scaled as f64 / 100_000.0
+    } else {
+        (scaled / 10_000 + 1) as f64 / 10.0
+    }
+}
+
+/// A known vulnerability an exploit signature targets
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Vulnerability {
+    /// CVE id (`CVE-2021-44228`)
+    pub cve: String,
+    /// Affected product (`Apache Log4j 2`)
+    pub product: String,
+    /// Score of the CVE's own vector
+    pub cvss: CVSSScore,
 }
 
 /// Threat types that can be detected in logs
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ThreatType {
     SQLInjection,
     XSS,
@@ -90,8 +173,20 @@ pub enum ThreatType {
     RootAccess,
     SuspiciousFileAccess,
     PortScanning,
+    Scanner,
+    UnauthorizedAccess,
     Malware,
     CriticalAlert,
+    BruteForce,
+    PasswordSpraying,
+    CredentialStuffing,
+    AccountCompromise,
+    DataExfiltration,
+    ForcedBrowsing,
+    SensitiveFileExposure,
+    Webshell,
+    PrivilegeEscalation,
+    ThreatIntelMatch,
 }
 
 impl ThreatType {
@@ -162,6 +257,21 @@ impl ThreatType {
                  Often precedes more serious attacks.".to_string(),
             ),
             
+            ThreatType::Scanner => CVSSScore::new(
+                5.3,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:L/I:N/A:N".to_string(),
+                "Requests from automated vulnerability scanners (sqlmap, nikto, \
+                 nmap scripts). Reconnaissance with low direct impact that \
+                 maps the attack surface for follow-up exploitation.".to_string(),
+            ),
+
+            ThreatType::UnauthorizedAccess => CVSSScore::new(
+                5.3,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:L/I:N/A:N".to_string(),
+                "Requests rejected with 401/403. Probing for protected \
+                 resources; low impact while access keeps being denied.".to_string(),
+            ),
+
             ThreatType::Malware => CVSSScore::new(
                 9.8,
                 "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H".to_string(),
@@ -177,9 +287,145 @@ impl ThreatType {
                  Specific impact depends on alert type but generally \
                  indicates serious security incident.".to_string(),
             ),
+
+            ThreatType::BruteForce => CVSSScore::new(
+                7.5,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:N/A:N".to_string(),
+                "Many passwords tried against one or two accounts from a single source. \
+                 A guessed password exposes the account; lockout policies \
+                 usually limit how far the attack gets.".to_string(),
+            ),
+
+            ThreatType::PasswordSpraying => CVSSScore::new(
+                8.2,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:L/A:N".to_string(),
+                "A few common passwords tried across many accounts, staying \
+                 under per-account lockout thresholds. Any account with a \
+                 weak password is likely to be compromised.".to_string(),
+            ),
+
+            ThreatType::CredentialStuffing => CVSSScore::new(
+                9.1,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:N".to_string(),
+                "Leaked username/password pairs replayed from many sources. \
+                 Reused credentials log in on the first try, giving full \
+                 access to the affected accounts.".to_string(),
+            ),
+
+            ThreatType::AccountCompromise => CVSSScore::new(
+                9.8,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H".to_string(),
+                "Successful login following repeated failures for the same \
+                 account or source. The attacker most likely holds valid \
+                 credentials and full access to the account.".to_string(),
+            ),
+
+            ThreatType::DataExfiltration => CVSSScore::new(
+                7.5,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:N/A:N".to_string(),
+                "Unusually large downloads, outsized responses or backup and \
+                 archive files served to one client. Database contents or \
+                 source code may have left the server.".to_string(),
+            ),
+
+            ThreatType::ForcedBrowsing => CVSSScore::new(
+                5.3,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:L/I:N/A:N".to_string(),
+                "Content discovery run: many guessed paths, mostly not found, \
+                 from one client. Reconnaissance for hidden pages, backups \
+                 and configuration files.".to_string(),
+            ),
+
+            ThreatType::SensitiveFileExposure => CVSSScore::new(
+                7.5,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:N/A:N".to_string(),
+                "A sensitive path probed during content discovery was served \
+                 successfully. Secrets, source code or administrative \
+                 interfaces are exposed to anyone who asks.".to_string(),
+            ),
+
+            ThreatType::Webshell => CVSSScore::new(
+                10.0,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H".to_string(),
+                "Requests to a planted script that runs attacker commands. \
+                 The server is already compromised; the shell gives remote \
+                 code execution beyond the web application.".to_string(),
+            ),
+
+            ThreatType::PrivilegeEscalation => CVSSScore::new(
+                7.8,
+                "CVSS:3.1/AV:L/AC:L/PR:L/UI:N/S:U/C:H/I:H/A:H".to_string(),
+                "A local user gained or tried to keep root privileges: a root \
+                 shell through sudo, su or pkexec, edits to sudoers or the \
+                 account files, or an account escalating for the first time.".to_string(),
+            ),
+
+            ThreatType::ThreatIntelMatch => CVSSScore::new(
+                7.3,
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:L/I:L/A:L".to_string(),
+                "Traffic involving an IP, domain, URL or user agent listed in \
+                 a threat-intelligence feed: known attack infrastructure, \
+                 command-and-control or malware tooling.".to_string(),
+            ),
         }
     }
     
+    /// Stable identifier, as accepted by `from_name` (e.g. `sql_injection`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThreatType::SQLInjection => "sql_injection",
+            ThreatType::XSS => "xss",
+            ThreatType::PathTraversal => "path_traversal",
+            ThreatType::CommandInjection => "command_injection",
+            ThreatType::FailedLogin => "failed_login",
+            ThreatType::RootAccess => "root_access",
+            ThreatType::SuspiciousFileAccess => "suspicious_file_access",
+            ThreatType::PortScanning => "port_scanning",
+            ThreatType::Scanner => "scanner",
+            ThreatType::UnauthorizedAccess => "unauthorized_access",
+            ThreatType::Malware => "malware",
+            ThreatType::CriticalAlert => "critical_alert",
+            ThreatType::BruteForce => "brute_force",
+            ThreatType::PasswordSpraying => "password_spraying",
+            ThreatType::CredentialStuffing => "credential_stuffing",
+            ThreatType::AccountCompromise => "account_compromise",
+            ThreatType::DataExfiltration => "data_exfiltration",
+            ThreatType::ForcedBrowsing => "forced_browsing",
+            ThreatType::SensitiveFileExposure => "sensitive_file_exposure",
+            ThreatType::Webshell => "webshell",
+            ThreatType::PrivilegeEscalation => "privilege_escalation",
+            ThreatType::ThreatIntelMatch => "threat_intel",
+        }
+    }
+
+    /// Human-readable name used in reports
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ThreatType::SQLInjection => "SQL Injection",
+            ThreatType::XSS => "Cross-Site Scripting",
+            ThreatType::PathTraversal => "Path Traversal",
+            ThreatType::CommandInjection => "Command Injection",
+            ThreatType::FailedLogin => "Failed Login",
+            ThreatType::RootAccess => "Root Access Attempt",
+            ThreatType::SuspiciousFileAccess => "Suspicious File Access",
+            ThreatType::PortScanning => "Port Scanning",
+            ThreatType::Scanner => "Security Scanner",
+            ThreatType::UnauthorizedAccess => "Unauthorized Access Attempt",
+            ThreatType::Malware => "Malware",
+            ThreatType::CriticalAlert => "Critical Alert",
+            ThreatType::BruteForce => "Brute Force",
+            ThreatType::PasswordSpraying => "Password Spraying",
+            ThreatType::CredentialStuffing => "Credential Stuffing",
+            ThreatType::AccountCompromise => "Probable Account Compromise",
+            ThreatType::DataExfiltration => "Data Exfiltration",
+            ThreatType::ForcedBrowsing => "Forced Browsing",
+            ThreatType::SensitiveFileExposure => "Sensitive File Exposure",
+            ThreatType::Webshell => "Webshell",
+            ThreatType::PrivilegeEscalation => "Privilege Escalation",
+            ThreatType::ThreatIntelMatch => "Threat Intelligence Match",
+        }
+    }
+
     /// Get threat type from string name
     pub fn from_name(name: &str) -> Option<Self> {
         match name.to_lowercase().as_str() {
@@ -191,15 +437,50 @@ impl ThreatType {
             "root_access" | "root access" => Some(ThreatType::RootAccess),
             "suspicious_file_access" | "suspicious file access" => Some(ThreatType::SuspiciousFileAccess),
             "port_scanning" | "port scanning" => Some(ThreatType::PortScanning),
+            "scanner" | "security scanner" => Some(ThreatType::Scanner),
+            "unauthorized_access" | "unauthorized access" | "unauthorized access attempt" => {
+                Some(ThreatType::UnauthorizedAccess)
+            }
             "malware" => Some(ThreatType::Malware),
             "critical_alert" | "critical alert" => Some(ThreatType::CriticalAlert),
+            "brute_force" | "brute force" => Some(ThreatType::BruteForce),
+            "password_spraying" | "password spraying" => Some(ThreatType::PasswordSpraying),
+            "credential_stuffing" | "credential stuffing" => Some(ThreatType::CredentialStuffing),
+            "account_compromise" | "account compromise" | "probable account compromise" => {
+                Some(ThreatType::AccountCompromise)
+            }
+            "data_exfiltration" | "data exfiltration" | "exfiltration" => Some(ThreatType::DataExfiltration),
+            "forced_browsing" | "forced browsing" | "content_discovery" | "content discovery" => {
+                Some(ThreatType::ForcedBrowsing)
+            }
+            "sensitive_file_exposure" | "sensitive file exposure" => Some(ThreatType::SensitiveFileExposure),
+            "webshell" | "web shell" => Some(ThreatType::Webshell),
+            "privilege_escalation" | "privilege escalation" => Some(ThreatType::PrivilegeEscalation),
+            "threat_intel" | "threat intelligence match" | "ioc" => Some(ThreatType::ThreatIntelMatch),
             _ => None,
         }
     }
 }
 
+/// Serialized as its stable identifier (`as_str`)
+impl Serialize for ThreatType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 /// Calculate aggregate CVSS score for multiple threats
 pub fn calculate_aggregate_score(threats: &[(ThreatType, usize)]) -> CVSSScore {
+    let scores: Vec<(f32, usize)> = threats
+        .iter()
+        .map(|(threat_type, count)| (threat_type.cvss_score().base_score, *count))
+        .collect();
+    aggregate_scores(&scores)
+}
+
+/// Aggregate CVSS score from (base score, instances) pairs, one per threat
+/// type or CVE
+pub fn aggregate_scores(threats: &[(f32, usize)]) -> CVSSScore {
     if threats.is_empty() {
         return CVSSScore::new(
             0.0,
@@ -211,7 +492,7 @@ pub fn calculate_aggregate_score(threats: &[(ThreatType, usize)]) -> CVSSScore {
     // Find highest severity threat
     let max_score = threats
         .iter()
-        .map(|(threat_type, _)| threat_type.cvss_score().base_score)
+        .map(|(score, _)| *score)
         .fold(0.0_f32, f32::max);
     
     // Count total threat instances
@@ -240,7 +521,7 @@ pub fn calculate_aggregate_score(threats: &[(ThreatType, usize)]) -> CVSSScore {
     
     CVSSScore::new(
         aggregate_score,
-        format!("CVSS:3.1/AGGREGATE"),
+        "CVSS:3.1/AGGREGATE".to_string(),
         explanation,
     )
 }
@@ -276,6 +557,19 @@ mod tests {
         assert!(score.base_score <= 10.0); // But capped at 10.0
     }
 
+    #[test]
+    fn test_score_from_vector() {
+        let score = |v: &str| CVSSScore::from_vector(v, String::new()).unwrap().base_score;
+        assert_eq!(score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H"), 10.0);
+        assert_eq!(score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), 9.8);
+        assert_eq!(score("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N"), 6.1);
+        assert_eq!(score("CVSS:3.0/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:N/A:N"), 7.5);
+        assert_eq!(score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N"), 0.0);
+        assert!(CVSSScore::from_vector("AV:N/AC:L", String::new()).is_err());
+        assert!(CVSSScore::from_vector("CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", String::new()).is_err());
+        assert!(CVSSScore::from_vector("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H", String::new()).is_err());
+    }
+
     #[test]
     fn test_threat_type_from_name() {
         assert_eq!(
@@ -83,6 +83,42 @@ pub struct NewAnalysisResult {
     pub major_issues_count: i32,
 }
 
+impl NewAnalysisResult {
+    /// Row for an analysis of `upload_id`. `threat_score` is the aggregate
+    /// CVSS score ×10; threats without a dedicated column count as
+    /// suspicious patterns.
+    pub fn from_analysis(upload_id: i32, result: &crate::AnalysisResult) -> Self {
+        let stats = &result.threat_statistics;
+        let quality = &result.parsing_info.format_quality;
+        let dedicated = stats.sql_injection_attempts
+            + stats.xss_attempts
+            + stats.path_traversal_attempts
+            + stats.command_injection_attempts;
+        let total = result.risk_assessment.total_threats;
+        let parsed = result.parsing_info.parsed_lines;
+
+        Self {
+            upload_id,
+            risk_level: result.risk_assessment.level.to_lowercase(),
+            total_threats: total as i32,
+            threat_score: (result.risk_assessment.cvss_aggregate_score * 10.0).round() as i32,
+            sql_injection_count: stats.sql_injection_attempts as i32,
+            xss_count: stats.xss_attempts as i32,
+            path_traversal_count: stats.path_traversal_attempts as i32,
+            command_injection_count: stats.command_injection_attempts as i32,
+            suspicious_patterns_count: total.saturating_sub(dedicated) as i32,
+            format_quality_percentage: if parsed == 0 {
+                0.0
+            } else {
+                quality.perfect_format as f32 * 100.0 / parsed as f32
+            },
+            perfect_format_count: quality.perfect_format as i32,
+            minor_issues_count: quality.alternative_format as i32,
+            major_issues_count: quality.fallback_format as i32,
+        }
+    }
+}
+
 /// New AI analysis to insert
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewAIAnalysis {
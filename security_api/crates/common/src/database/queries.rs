@@ -138,6 +138,35 @@ pub async fn save_detected_threat(
     Ok(())
 }
 
+/// Record analyst feedback against a threat pattern: its false-positive
+/// rate (percent) and the severity its findings are now reported at
+pub async fn save_pattern_feedback(
+    pool: &DbPool,
+    pattern_type: &str,
+    pattern_signature: &str,
+    severity: &str,
+    false_positive_rate: f64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO threat_patterns
+        (pattern_type, pattern_signature, severity, false_positive_rate)
+        VALUES (?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE
+            severity = VALUES(severity),
+            false_positive_rate = VALUES(false_positive_rate)
+        "#,
+    )
+    .bind(pattern_type)
+    .bind(pattern_signature)
+    .bind(severity)
+    .bind(false_positive_rate)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// Get recent log uploads
 pub async fn get_recent_uploads(
     pool: &DbPool,
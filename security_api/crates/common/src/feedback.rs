@@ -0,0 +1,475 @@
+// Analyst feedback
+// Analysts mark findings as true or false positives. Each verdict is stored
+// against the finding's rule id (the pattern signature) in an append-only
+// JSON Lines file, so the CLI and the API share verdicts without needing a
+// database. Precision is computed per rule over every verdict and over the
+// recent ones; a rule whose recent verdicts are mostly false positives has
+// its findings lowered one severity step, or two when nearly all are.
+// Findings are never dropped this way (that is what allowlists are for),
+// only ranked lower.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use crate::Finding;
+use crate::cvss::{Severity, ThreatType};
+use crate::rules;
+
+/// Verdicts older than this no longer count towards downgrades
+pub const RECENT_DAYS: i64 = 90;
+/// Recent verdicts a rule needs before it can be downgraded
+pub const MIN_VERDICTS: usize = 5;
+/// Recent false-positive rate that lowers a rule's findings one step
+const DOWNGRADE_FP_RATE: f64 = 0.5;
+/// Recent false-positive rate that lowers them two steps
+const DOUBLE_DOWNGRADE_FP_RATE: f64 = 0.8;
+
+/// Finding ids of the basic analyzer's behavioral detectors, which are not
+/// rules in the rule set
+pub const BEHAVIORAL_RULES: &[(&str, ThreatType)] = &[
+    ("auth.brute_force", ThreatType::BruteForce),
+    ("auth.password_spraying", ThreatType::PasswordSpraying),
+    ("auth.credential_stuffing", ThreatType::CredentialStuffing),
+    ("auth.account_compromise", ThreatType::AccountCompromise),
+    ("scan.vertical", ThreatType::PortScanning),
+    ("scan.horizontal", ThreatType::PortScanning),
+    ("discovery.forced_browsing", ThreatType::ForcedBrowsing),
+    ("discovery.exposure", ThreatType::SensitiveFileExposure),
+    ("webshell.rare_script", ThreatType::Webshell),
+    ("privilege.first_escalation", ThreatType::PrivilegeEscalation),
+    ("exfil.volume", ThreatType::DataExfiltration),
+    ("exfil.outsized_responses", ThreatType::DataExfiltration),
+    ("exfil.archive_download", ThreatType::DataExfiltration),
+];
+
+/// A finding id verdicts can be recorded against, with what it reports
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownRule {
+    pub id: String,
+    pub threat_type: Option<String>,
+    pub severity: Severity,
+}
+
+/// Look `rule_id` up among the active rules, the behavioral detectors and
+/// the loaded Sigma rules
+pub fn known_rule(rule_id: &str) -> Option<KnownRule> {
+    let rule_id = rule_id.trim();
+    if let Some(rule) = rules::active().get(rule_id).filter(|r| r.enabled) {
+        return Some(KnownRule {
+            id: rule.id.clone(),
+            threat_type: rule.threat_type.clone(),
+            severity: rule.severity.clone(),
+        });
+    }
+    if let Some((id, threat)) = BEHAVIORAL_RULES.iter().find(|(id, _)| *id == rule_id) {
+        return Some(KnownRule {
+            id: id.to_string(),
+            threat_type: Some(threat.as_str().to_string()),
+            severity: threat.cvss_score().severity,
+        });
+    }
+    rules::sigma::active().rules.iter().find(|r| r.id == rule_id).map(|rule| KnownRule {
+        id: rule.id.clone(),
+        threat_type: None,
+        severity: match rule.level.as_str() {
+            "critical" => Severity::Critical,
+            "high" => Severity::High,
+            "medium" => Severity::Medium,
+            _ => Severity::Low,
+        },
+    })
+}
+
+/// An analyst's verdict on a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    #[serde(alias = "tp")]
+    TruePositive,
+    #[serde(alias = "fp")]
+    FalsePositive,
+}
+
+impl Verdict {
+    /// `tp`/`fp`, or the names spelled out
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "tp" | "true_positive" => Some(Verdict::TruePositive),
+            "fp" | "false_positive" => Some(Verdict::FalsePositive),
+            _ => None,
+        }
+    }
+}
+
+/// One verdict, with enough of the finding to find it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackRecord {
+    pub rule_id: String,
+    pub verdict: Verdict,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threat_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_number: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analyst: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl FeedbackRecord {
+    /// A verdict on a rule, made now
+    pub fn new(rule_id: &str, verdict: Verdict) -> Self {
+        FeedbackRecord {
+            rule_id: rule_id.to_string(),
+            verdict,
+            timestamp: Utc::now(),
+            threat_type: None,
+            line_number: None,
+            matched: None,
+            analyst: None,
+            note: None,
+        }
+    }
+}
+
+/// Verdict counts and precision for one rule
+#[derive(Debug, Clone, Serialize)]
+pub struct RulePrecision {
+    pub rule_id: String,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    /// True positives over all verdicts
+    pub precision: f64,
+    /// Verdicts from the last `RECENT_DAYS` days
+    pub recent_verdicts: usize,
+    pub recent_precision: Option<f64>,
+    /// Severity steps this rule's findings are lowered by
+    pub downgrade: u8,
+    pub last_verdict: DateTime<Utc>,
+}
+
+impl RulePrecision {
+    pub fn false_positive_rate(&self) -> f64 {
+        1.0 - self.precision
+    }
+}
+
+/// Severity downgrades per rule, derived from feedback
+#[derive(Debug, Clone, Default)]
+pub struct Adjustments {
+    downgrades: BTreeMap<String, u8>,
+}
+
+impl Adjustments {
+    pub fn is_empty(&self) -> bool {
+        self.downgrades.is_empty()
+    }
+
+    /// Steps findings of `rule_id` are lowered by
+    pub fn downgrade(&self, rule_id: &str) -> u8 {
+        self.downgrades.get(rule_id).copied().unwrap_or(0)
+    }
+
+    /// Lower the finding's severity if its rule keeps producing false
+    /// positives, keeping the original in `downgraded_from`
+    pub fn apply(&self, finding: &mut Finding) -> bool {
+        let steps = self.downgrade(&finding.rule_id);
+        let lowered = lower(&finding.severity, steps);
+        if lowered == finding.severity {
+            return false;
+        }
+        finding.downgraded_from = Some(std::mem::replace(&mut finding.severity, lowered));
+        true
+    }
+}
+
+/// `severity` lowered by `steps`, never below Low
+pub fn lower(severity: &Severity, steps: u8) -> Severity {
+    let mut severity = severity.clone();
+    for _ in 0..steps {
+        severity = match severity {
+            Severity::Critical => Severity::High,
+            Severity::High => Severity::Medium,
+            _ => Severity::Low,
+        };
+    }
+    severity
+}
+
+/// Verdicts, optionally backed by a JSON Lines file
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackStore {
+    path: Option<PathBuf>,
+    records: Vec<FeedbackRecord>,
+    /// Time recency is judged against; now when unset
+    now: Option<DateTime<Utc>>,
+}
+
+impl FeedbackStore {
+    /// Load the verdicts in `path`; a missing file is an empty store that
+    /// new verdicts are appended to
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut store = FeedbackStore { path: Some(path.to_path_buf()), ..Default::default() };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(format!("Failed to read feedback file {}: {}", path.display(), e)),
+        };
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(line)
+                .map_err(|e| format!("Feedback file {} line {}: {}", path.display(), index + 1, e))?;
+            store.records.push(record);
+        }
+        Ok(store)
+    }
+
+    /// Judge recency as of `now` instead of the current time
+    pub fn as_of(mut self, now: DateTime<Utc>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    pub fn records(&self) -> &[FeedbackRecord] {
+        &self.records
+    }
+
+    /// Add a verdict, appending it to the file when the store has one
+    pub fn record(&mut self, record: FeedbackRecord) -> Result<(), String> {
+        if record.rule_id.trim().is_empty() {
+            return Err("feedback needs a rule id".to_string());
+        }
+        if let Some(path) = &self.path {
+            let line = serde_json::to_string(&record).map_err(|e| e.to_string())?;
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|e| format!("Failed to write feedback file {}: {}", path.display(), e))?;
+        }
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Precision of every rule with verdicts, by rule id
+    pub fn precision(&self) -> Vec<RulePrecision> {
+        let recent_since = self.now.unwrap_or_else(Utc::now) - Duration::days(RECENT_DAYS);
+        let mut by_rule: BTreeMap<&str, Vec<&FeedbackRecord>> = BTreeMap::new();
+        for record in &self.records {
+            by_rule.entry(&record.rule_id).or_default().push(record);
+        }
+        by_rule
+            .into_iter()
+            .map(|(rule_id, records)| {
+                let true_positives = records.iter().filter(|r| r.verdict == Verdict::TruePositive).count();
+                let recent: Vec<Verdict> =
+                    records.iter().filter(|r| r.timestamp >= recent_since).map(|r| r.verdict).collect();
+                let recent_tp = recent.iter().filter(|v| **v == Verdict::TruePositive).count();
+                let recent_precision = (!recent.is_empty()).then(|| recent_tp as f64 / recent.len() as f64);
+                let downgrade = match recent_precision {
+                    Some(precision) if recent.len() >= MIN_VERDICTS => {
+                        let fp_rate = 1.0 - precision;
+                        if fp_rate >= DOUBLE_DOWNGRADE_FP_RATE {
+                            2
+                        } else if fp_rate >= DOWNGRADE_FP_RATE {
+                            1
+                        } else {
+                            0
+                        }
+                    }
+                    _ => 0,
+                };
+                RulePrecision {
+                    rule_id: rule_id.to_string(),
+                    true_positives,
+                    false_positives: records.len() - true_positives,
+                    precision: true_positives as f64 / records.len() as f64,
+                    recent_verdicts: recent.len(),
+                    recent_precision,
+                    downgrade,
+                    last_verdict: records.iter().map(|r| r.timestamp).max().unwrap_or(recent_since),
+                }
+            })
+            .collect()
+    }
+
+    /// Downgrades for rules that keep producing false positives
+    pub fn adjustments(&self) -> Adjustments {
+        Adjustments {
+            downgrades: self
+                .precision()
+                .into_iter()
+                .filter(|p| p.downgrade > 0)
+                .map(|p| (p.rule_id, p.downgrade))
+                .collect(),
+        }
+    }
+}
+
+static ACTIVE_FEEDBACK: OnceLock<RwLock<FeedbackStore>> = OnceLock::new();
+
+fn active() -> &'static RwLock<FeedbackStore> {
+    ACTIVE_FEEDBACK.get_or_init(|| RwLock::new(FeedbackStore::default()))
+}
+
+/// Install the process-wide feedback store. Unlike rules, verdicts keep
+/// arriving while logs are analyzed: `record` adds to this store.
+pub fn install(store: FeedbackStore) -> Result<(), String> {
+    ACTIVE_FEEDBACK
+        .set(RwLock::new(store))
+        .map_err(|_| "feedback store is already initialized".to_string())
+}
+
+/// Record a verdict in the process-wide store; returns the rule's precision
+/// including it
+pub fn record(record: FeedbackRecord) -> Result<RulePrecision, String> {
+    let rule_id = record.rule_id.clone();
+    let mut store = active().write().map_err(|_| "feedback store is poisoned".to_string())?;
+    store.record(record)?;
+    store
+        .precision()
+        .into_iter()
+        .find(|p| p.rule_id == rule_id)
+        .ok_or_else(|| format!("no verdicts for {}", rule_id))
+}
+
+/// Precision of every rule in the process-wide store
+pub fn precision() -> Vec<RulePrecision> {
+    active().read().map(|store| store.precision()).unwrap_or_default()
+}
+
+/// Precision of the rules behind `findings`, from the process-wide store
+pub fn for_findings(findings: &[Finding]) -> Vec<RulePrecision> {
+    precision()
+        .into_iter()
+        .filter(|p| findings.iter().any(|f| f.rule_id == p.rule_id))
+        .collect()
+}
+
+/// Current downgrades from the process-wide store (none unless verdicts
+/// were installed or recorded)
+pub fn adjustments() -> Adjustments {
+    active().read().map(|store| store.adjustments()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(days_ago: i64, rule_id: &str, verdict: Verdict) -> FeedbackRecord {
+        let mut record = FeedbackRecord::new(rule_id, verdict);
+        record.timestamp = now() - Duration::days(days_ago);
+        record
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-06-01T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    fn store(records: Vec<FeedbackRecord>) -> FeedbackStore {
+        let mut store = FeedbackStore::default().as_of(now());
+        for record in records {
+            store.record(record).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn precision_and_downgrades_follow_recent_verdicts() {
+        use Verdict::*;
+        let mut records = Vec::new();
+        // Noisy lately: 4 of 5 recent verdicts are false positives
+        records.extend([(1, FalsePositive), (2, FalsePositive), (3, FalsePositive), (4, FalsePositive), (5, TruePositive)]
+            .map(|(days, v)| at(days, "http.scanner", v)));
+        // Mostly wrong, but long ago
+        records.extend((0..6).map(|_| at(200, "log.failed_login", FalsePositive)));
+        records.push(at(1, "log.failed_login", TruePositive));
+        // Too few verdicts to judge
+        records.extend((0..3).map(|_| at(1, "log.malware", FalsePositive)));
+        // Half wrong
+        records.extend((0..6).map(|i| at(i, "auth.brute_force", if i % 2 == 0 { FalsePositive } else { TruePositive })));
+
+        let precision = store(records).precision();
+        let by_rule = |id: &str| precision.iter().find(|p| p.rule_id == id).unwrap();
+        let scanner = by_rule("http.scanner");
+        assert_eq!((scanner.true_positives, scanner.false_positives, scanner.downgrade), (1, 4, 2));
+        assert!((scanner.precision - 0.2).abs() < 1e-9);
+        assert!((scanner.false_positive_rate() - 0.8).abs() < 1e-9);
+        assert_eq!(by_rule("log.failed_login").recent_verdicts, 1);
+        assert_eq!(by_rule("log.failed_login").downgrade, 0);
+        assert_eq!(by_rule("log.malware").downgrade, 0);
+        assert_eq!(by_rule("auth.brute_force").downgrade, 1);
+    }
+
+    #[test]
+    fn adjustments_lower_findings_but_not_below_low() {
+        let records: Vec<FeedbackRecord> = (0..5).map(|d| at(d, "http.sql_injection", Verdict::FalsePositive)).collect();
+        let adjustments = store(records).adjustments();
+        let mut finding = Finding {
+            rule_id: "http.sql_injection".to_string(),
+            threat_type: crate::cvss::ThreatType::SQLInjection,
+            severity: Severity::Critical,
+            field: "http.path".to_string(),
+            matched: "union select".to_string(),
+            entry_index: 0,
+            line_number: 1,
+            ip_address: None,
+            username: None,
+            message: String::new(),
+            downgraded_from: None,
+            vulnerability: None,
+        };
+        assert!(adjustments.apply(&mut finding));
+        assert_eq!((finding.severity.clone(), finding.downgraded_from.clone()), (Severity::Medium, Some(Severity::Critical)));
+        assert_eq!(lower(&Severity::Medium, 2), Severity::Low);
+        finding.rule_id = "http.xss".to_string();
+        assert!(!adjustments.apply(&mut finding));
+    }
+
+    #[test]
+    fn verdicts_persist_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("logr-feedback-{}.jsonl", uuid::Uuid::new_v4()));
+        let mut store = FeedbackStore::open(&path).unwrap();
+        assert!(store.records().is_empty());
+        let mut record = FeedbackRecord::new("log.privileged_shell", Verdict::FalsePositive);
+        record.note = Some("Ansible run".to_string());
+        store.record(record).unwrap();
+        store.record(FeedbackRecord::new("log.privileged_shell", Verdict::TruePositive)).unwrap();
+        assert!(store.record(FeedbackRecord::new(" ", Verdict::TruePositive)).is_err());
+
+        let reopened = FeedbackStore::open(&path).unwrap();
+        assert_eq!(reopened.records().len(), 2);
+        assert_eq!(reopened.records()[0].note.as_deref(), Some("Ansible run"));
+        assert!(std::fs::read_to_string(&path).unwrap().contains(r#""verdict":"false_positive""#));
+
+        std::fs::write(&path, "{\"rule_id\": \"x\"}\n").unwrap();
+        assert!(FeedbackStore::open(&path).unwrap_err().contains("line 1"));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Verdict::from_name("FP"), Some(Verdict::FalsePositive));
+    }
+
+    #[test]
+    fn known_rules_cover_rules_and_behavioral_detectors() {
+        let rule = known_rule(" http.sql_injection ").unwrap();
+        assert_eq!((rule.id.as_str(), rule.threat_type.as_deref()), ("http.sql_injection", Some("sql_injection")));
+        assert_eq!(rule.severity, Severity::Critical);
+
+        let behavioral = known_rule("privilege.first_escalation").unwrap();
+        assert_eq!(behavioral.threat_type.as_deref(), Some("privilege_escalation"));
+        assert_eq!(behavioral.severity, ThreatType::PrivilegeEscalation.cvss_score().severity);
+
+        assert!(known_rule("http.scaner").is_none());
+        assert!(known_rule("").is_none());
+    }
+}
@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use std::net::IpAddr;
 
 /// Result of a geolocation lookup for a single IP
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GeoResult {
     pub country: Option<String>,
     pub country_code: Option<String>,
@@ -22,24 +22,6 @@ pub struct GeoResult {
     pub timezone: Option<String>,
 }
 
-impl Default for GeoResult {
-    fn default() -> Self {
-        Self {
-            country: None,
-            country_code: None,
-            region: None,
-            city: None,
-            lat: None,
-            lon: None,
-            isp: None,
-            org: None,
-            is_proxy: false,
-            is_hosting: false,
-            timezone: None,
-        }
-    }
-}
-
 /// Response from ip-api.com batch endpoint
 #[derive(Debug, Deserialize)]
 struct IpApiResponse {
@@ -143,25 +125,25 @@ pub async fn lookup_batch(ips: &[String]) -> HashMap<String, GeoResult> {
             Ok(response) => {
                 if let Ok(api_results) = response.json::<Vec<IpApiResponse>>().await {
                     for api_result in api_results {
-                        if api_result.status == "success" {
-                            if let Some(query_ip) = &api_result.query {
-                                results.insert(
-                                    query_ip.clone(),
-                                    GeoResult {
-                                        country: api_result.country,
-                                        country_code: api_result.country_code,
-                                        region: api_result.region_name,
-                                        city: api_result.city,
-                                        lat: api_result.lat,
-                                        lon: api_result.lon,
-                                        isp: api_result.isp,
-                                        org: api_result.org,
-                                        is_proxy: api_result.proxy.unwrap_or(false),
-                                        is_hosting: api_result.hosting.unwrap_or(false),
-                                        timezone: api_result.timezone,
-                                    },
-                                );
-                            }
+                        if api_result.status == "success"
+                            && let Some(query_ip) = &api_result.query
+                        {
+                            results.insert(
+                                query_ip.clone(),
+                                GeoResult {
+                                    country: api_result.country,
+                                    country_code: api_result.country_code,
+                                    region: api_result.region_name,
+                                    city: api_result.city,
+                                    lat: api_result.lat,
+                                    lon: api_result.lon,
+                                    isp: api_result.isp,
+                                    org: api_result.org,
+                                    is_proxy: api_result.proxy.unwrap_or(false),
+                                    is_hosting: api_result.hosting.unwrap_or(false),
+                                    timezone: api_result.timezone,
+                                },
+                            );
                         }
                     }
                 }
@@ -0,0 +1,432 @@
+// Log integrity and tampering checks
+// An attacker who gets onto a host tends to cover their tracks: deleting a
+// stretch of the log, truncating it, splicing in old lines or clearing it
+// outright. Those edits leave traces in the file itself:
+// - a silent stretch far longer than the usual spacing between events
+// - timestamps that go backwards
+// - a final line cut off mid-write
+// - a sudden, sustained switch to another log format
+// - a block of lines repeated verbatim from earlier in the file
+// - the clearing itself, when it was logged (Windows event 1102, a shell or
+//   sudo command emptying a file under /var/log)
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::cvss::Severity;
+use crate::parsers::{parse_timestamp, LineFormat, ParsedContent};
+
+/// Timestamped events needed before the spacing between them means anything
+const MIN_TIMED_EVENTS: usize = 20;
+/// A gap is suspicious when this many events would have been expected in it
+const MIN_MISSING_EVENTS: f64 = 20.0;
+/// Gaps shorter than this are never reported
+const MIN_GAP_SECONDS: i64 = 300;
+/// Servers log requests as they finish, so small reorderings are normal
+const CLOCK_TOLERANCE_SECONDS: i64 = 60;
+/// Entries of one format on both sides of a format change
+const MIN_FORMAT_RUN: usize = 10;
+/// Consecutive lines that make a replayed block
+const MIN_REPLAYED_LINES: usize = 5;
+/// Issues reported per kind; the rest are only counted
+const MAX_ISSUES_PER_KIND: usize = 20;
+
+/// Something in the file that suggests it was edited, truncated or cleared
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityIssue {
+    /// `time_gap`, `time_reversal`, `truncated_line`, `format_change`,
+    /// `replayed_block` or `log_cleared`
+    pub kind: String,
+    pub severity: Severity,
+    /// 1-based line the issue starts on
+    pub line_number: usize,
+    /// Last line of a replayed block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+    pub description: String,
+}
+
+/// Integrity section of an analysis result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    pub issues: Vec<IntegrityIssue>,
+    /// Usual seconds between consecutive timestamped events, when there were
+    /// enough of them to tell
+    pub baseline_interval_seconds: Option<f64>,
+    /// Issues found beyond the per-kind reporting cap
+    pub suppressed: usize,
+}
+
+impl IntegrityReport {
+    /// True when nothing suggests the log was tampered with
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn push(&mut self, issue: IntegrityIssue) {
+        if self.issues.iter().filter(|i| i.kind == issue.kind).count() < MAX_ISSUES_PER_KIND {
+            self.issues.push(issue);
+        } else {
+            self.suppressed += 1;
+        }
+    }
+}
+
+/// Check a log file for signs of tampering. `parsed` is the result of
+/// parsing `content`.
+pub fn check(content: &str, parsed: &ParsedContent) -> IntegrityReport {
+    let mut report = IntegrityReport::default();
+    let lines: Vec<&str> = content.lines().collect();
+
+    check_clearing(&lines, &mut report);
+    check_timeline(parsed, &mut report);
+    check_formats(parsed, &mut report);
+    check_replays(&lines, &mut report);
+    check_truncation(content, &lines, parsed, &mut report);
+
+    report.issues.sort_by_key(|issue| issue.line_number);
+    report
+}
+
+fn issue(kind: &str, severity: Severity, line_number: usize, description: String) -> IntegrityIssue {
+    IntegrityIssue {
+        kind: kind.to_string(),
+        severity,
+        line_number,
+        end_line: None,
+        description,
+    }
+}
+
+/// Log-clearing events and commands, with what each one means
+fn clearing_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"(?i)\bevent\s*_?id\W{0,3}1102\b|audit log was cleared", "Windows security audit log cleared (event 1102)"),
+            (r"(?i)\bevent\s*_?id\W{0,3}104\b.*\blog\b.*\bcleared\b", "Windows event log cleared (event 104)"),
+            (r"(?i)\bwevtutil(\.exe)?\s+(cl|clear-log)\b|\bclear-eventlog\b", "Windows event log cleared from the command line"),
+            // Only a redirect with nothing to write (`> f`, `: > f`, `cat /dev/null > f`)
+            // at the start of a command; `backup.sh > /var/log/backup.log` writes
+            (
+                r#"(^|;|&&|\|\||[(`"']|COMMAND=|:\s|\s-c\s)\s*(:\s*|cat\s+/dev/null\s*)?>\s*/var/log/\S+"#,
+                "file under /var/log emptied by redirection",
+            ),
+            (r"\btruncate\s+(-\S+\s+)*-s\s*0\s+\S*/var/log/", "file under /var/log truncated"),
+            (r"\b(rm|shred|unlink)\s+(-\S+\s+)*\S*/var/log/", "file under /var/log deleted"),
+            (r"\bjournalctl\s+.*--(vacuum-\w+|rotate)\b", "systemd journal vacuumed"),
+            (r"\bhistory\s+-c\b|\bunset\s+HISTFILE\b|HISTFILE=/dev/null", "shell history cleared or disabled"),
+        ]
+        .into_iter()
+        .map(|(pattern, meaning)| (Regex::new(pattern).expect("log clearing pattern"), meaning))
+        .collect()
+    })
+}
+
+fn check_clearing(lines: &[&str], report: &mut IntegrityReport) {
+    for (index, line) in lines.iter().enumerate() {
+        if let Some((_, meaning)) = clearing_patterns().iter().find(|(re, _)| re.is_match(line)) {
+            let excerpt: String = line.trim().chars().take(160).collect();
+            report.push(issue("log_cleared", Severity::Critical, index + 1, format!("{}: {}", meaning, excerpt)));
+        }
+    }
+}
+
+/// Gaps and reversals between consecutive timestamped entries. Entries are
+/// only compared with entries of the same format: a syslog timestamp has no
+/// year, so it cannot be put on the same timeline as an Apache one.
+fn check_timeline(parsed: &ParsedContent, report: &mut IntegrityReport) {
+    let timed: Vec<(usize, LineFormat, DateTime<Utc>)> = parsed
+        .entries
+        .iter()
+        .zip(&parsed.line_numbers)
+        .zip(&parsed.formats)
+        .filter_map(|((entry, &line), &format)| parse_timestamp(&entry.timestamp).map(|t| (line, format, t)))
+        .collect();
+    if timed.len() < MIN_TIMED_EVENTS {
+        return;
+    }
+
+    let mut intervals: Vec<i64> = timed
+        .windows(2)
+        .filter(|pair| pair[0].1 == pair[1].1)
+        .map(|pair| (pair[1].2 - pair[0].2).num_seconds())
+        .filter(|&seconds| seconds >= 0)
+        .collect();
+    if intervals.is_empty() {
+        return;
+    }
+    intervals.sort_unstable();
+    // Busy logs share seconds between many events; the mean then says more
+    // than a median of zero
+    let median = intervals[intervals.len() / 2] as f64;
+    let span = (intervals.iter().sum::<i64>() as f64).max(1.0);
+    let baseline = if median > 0.0 { median } else { span / intervals.len() as f64 };
+    report.baseline_interval_seconds = Some(baseline);
+
+    // Gaps are measured from the latest time seen so far, so a single line
+    // from the past is one reversal and not also a gap on the way back
+    let mut latest = timed[0];
+    for pair in timed.windows(2) {
+        let ((before_line, format, before), (line, next_format, at)) = (pair[0], pair[1]);
+        if format != next_format {
+            latest = pair[1];
+            continue;
+        }
+        let seconds = (at - before).num_seconds();
+        let silence = (at - latest.2).num_seconds();
+        if seconds < -CLOCK_TOLERANCE_SECONDS {
+            report.push(issue(
+                "time_reversal",
+                Severity::High,
+                line,
+                format!("timestamp goes back {} from line {} ({} -> {})", duration(-seconds), before_line, before, at),
+            ));
+        } else if silence >= MIN_GAP_SECONDS && silence as f64 / baseline >= MIN_MISSING_EVENTS {
+            report.push(issue(
+                "time_gap",
+                Severity::Medium,
+                line,
+                format!(
+                    "no events for {} after line {} (about {:.0} expected at the usual {:.1}s spacing)",
+                    duration(silence),
+                    latest.0,
+                    silence as f64 / baseline,
+                    baseline
+                ),
+            ));
+        }
+        if at > latest.2 {
+            latest = pair[1];
+        }
+    }
+}
+
+/// A sustained run of one format followed by a sustained run of another
+fn check_formats(parsed: &ParsedContent, report: &mut IntegrityReport) {
+    // (format, line of the first entry, entries)
+    let mut runs: Vec<(LineFormat, usize, usize)> = Vec::new();
+    for (&format, &line) in parsed.formats.iter().zip(&parsed.line_numbers) {
+        match runs.last_mut() {
+            Some((current, _, count)) if *current == format => *count += 1,
+            _ => runs.push((format, line, 1)),
+        }
+    }
+    let sustained: Vec<_> = runs.into_iter().filter(|(_, _, count)| *count >= MIN_FORMAT_RUN).collect();
+    for pair in sustained.windows(2) {
+        let ((from, _, before), (to, line, after)) = (pair[0], pair[1]);
+        if from != to {
+            report.push(issue(
+                "format_change",
+                Severity::Medium,
+                line,
+                format!(
+                    "format switches from {} ({} entries) to {} ({} entries)",
+                    from.as_str(),
+                    before,
+                    to.as_str(),
+                    after
+                ),
+            ));
+        }
+    }
+}
+
+/// Blocks of consecutive lines that repeat an earlier block verbatim
+fn check_replays(lines: &[&str], report: &mut IntegrityReport) {
+    if lines.len() < MIN_REPLAYED_LINES * 2 {
+        return;
+    }
+    let mut first_seen: HashMap<&[&str], usize> = HashMap::new();
+    let mut start = 0;
+    while start + MIN_REPLAYED_LINES <= lines.len() {
+        let window = &lines[start..start + MIN_REPLAYED_LINES];
+        if !is_distinctive(window) {
+            start += 1;
+            continue;
+        }
+        match first_seen.get(window) {
+            Some(&original) if original + MIN_REPLAYED_LINES <= start => {
+                let mut length = MIN_REPLAYED_LINES;
+                while start + length < lines.len()
+                    && original + length < start
+                    && lines[original + length] == lines[start + length]
+                {
+                    length += 1;
+                }
+                let mut replay = issue(
+                    "replayed_block",
+                    Severity::High,
+                    start + 1,
+                    format!("{} lines repeat lines {}-{} verbatim", length, original + 1, original + length),
+                );
+                replay.end_line = Some(start + length);
+                report.push(replay);
+                start += length;
+            }
+            Some(_) => start += 1,
+            None => {
+                first_seen.insert(window, start);
+                start += 1;
+            }
+        }
+    }
+}
+
+/// Identical or blank lines repeat on their own (keep-alives, "last message
+/// repeated"); a replayed block has to be made of varied lines
+fn is_distinctive(window: &[&str]) -> bool {
+    let mut distinct: Vec<&str> = window.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    distinct.len() >= 3
+}
+
+/// A file written by a logger ends with a newline. A last line without one
+/// that the parser rejected, or that parsed differently from the line before
+/// it, was most likely cut off.
+fn check_truncation(content: &str, lines: &[&str], parsed: &ParsedContent, report: &mut IntegrityReport) {
+    let Some(last) = lines.last().filter(|line| !line.trim().is_empty()) else {
+        return;
+    };
+    if content.ends_with('\n') || lines.len() < 2 {
+        return;
+    }
+    let last_number = lines.len();
+    let formats: Vec<(usize, LineFormat)> =
+        parsed.line_numbers.iter().copied().zip(parsed.formats.iter().copied()).collect();
+    let truncated = match formats.as_slice() {
+        [.., (before, previous), (line, format)] if *line == last_number => {
+            *before == last_number - 1 && previous.is_recognized() && previous != format
+        }
+        [.., (line, _)] => *line < last_number,
+        [] => false,
+    };
+    if truncated {
+        let excerpt: String = last.chars().take(80).collect();
+        report.push(issue(
+            "truncated_line",
+            Severity::Low,
+            last_number,
+            format!("last line ends without a newline and does not match the lines before it: {}", excerpt),
+        ));
+    }
+}
+
+fn duration(seconds: i64) -> String {
+    match seconds {
+        s if s >= 86_400 => format!("{}d {}h", s / 86_400, s % 86_400 / 3600),
+        s if s >= 3600 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m {}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_content;
+
+    fn syslog(minute: u32, second: u32, message: &str) -> String {
+        format!("Jan 15 10:{:02}:{:02} web01 sshd[1234]: {}\n", minute, second, message)
+    }
+
+    fn steady(minutes: std::ops::Range<u32>) -> String {
+        minutes
+            .flat_map(|m| (0..60).step_by(15).map(move |s| (m, s)))
+            .map(|(m, s)| syslog(m, s, &format!("Accepted publickey for deploy port {}", 40000 + m * 60 + s)))
+            .collect()
+    }
+
+    fn kinds(report: &IntegrityReport) -> Vec<&str> {
+        report.issues.iter().map(|i| i.kind.as_str()).collect()
+    }
+
+    #[test]
+    fn clean_log_has_no_issues() {
+        let content = steady(0..10);
+        let report = check(&content, &parse_content(&content));
+        assert!(report.is_clean(), "{:?}", report.issues);
+        assert_eq!(report.baseline_interval_seconds, Some(15.0));
+    }
+
+    #[test]
+    fn detects_gaps_and_reversals() {
+        // 15s spacing, then 40 silent minutes, then a line from the past
+        let mut content = steady(0..10);
+        content.push_str(&steady(50..55));
+        content.push_str(&syslog(20, 0, "Accepted publickey for deploy port 1"));
+        content.push_str(&steady(55..57));
+        let report = check(&content, &parse_content(&content));
+        assert_eq!(kinds(&report), vec!["time_gap", "time_reversal"]);
+        assert_eq!(report.issues[0].line_number, 41);
+        assert!(report.issues[0].description.contains("40m 15s"));
+        assert_eq!(report.issues[1].line_number, 61);
+    }
+
+    #[test]
+    fn detects_replayed_blocks() {
+        let block = steady(0..2);
+        let mut content = steady(2..6);
+        content.push_str(&block);
+        content.push_str(&block);
+        // Repeated keep-alive lines are not a replay
+        content.push_str(&"Jan 15 10:09:00 web01 systemd[1]: tick\n".repeat(12));
+        let report = check(&content, &parse_content(&content));
+        let replays: Vec<_> = report.issues.iter().filter(|i| i.kind == "replayed_block").collect();
+        assert_eq!(replays.len(), 1);
+        assert_eq!(replays[0].line_number, 25);
+        assert_eq!(replays[0].end_line, Some(32));
+        assert!(replays[0].description.contains("lines 17-24"));
+    }
+
+    #[test]
+    fn detects_clearing_commands_and_events() {
+        let lines = [
+            "Jan 15 10:00:00 web01 sudo: alice : TTY=pts/0 ; PWD=/root ; USER=root ; COMMAND=/bin/sh -c > /var/log/auth.log",
+            "Jan 15 10:00:01 web01 bash[99]: root: cat /dev/null > /var/log/nginx/access.log",
+            "2024-01-15T10:00:02Z DC01 EventID=1102 The audit log was cleared. Subject: CORP\\mallory",
+            "Jan 15 10:00:03 web01 bash[99]: root: shred -u /var/log/secure",
+            "Jan 15 10:00:04 web01 bash[99]: root: history -c",
+            // Reading logs is fine, and so is writing to them
+            "Jan 15 10:00:05 web01 bash[99]: root: tail -f /var/log/auth.log",
+            "Jan 15 10:00:06 web01 app[7]: wrote 1102 records to /var/log/app/export.log",
+            "Jan 15 10:00:07 web01 CRON[1]: (root) CMD (/usr/local/bin/backup.sh > /var/log/backup.log 2>&1)",
+            "Jan 15 10:00:08 web01 sudo: alice : TTY=pts/0 ; PWD=/root ; USER=root ; COMMAND=/opt/app/run > /var/log/app.log",
+            "Jan 15 10:00:09 web01 bash[99]: root: ./deploy.sh &> /var/log/deploy.log",
+            "Jan 15 10:00:10 web01 bash[99]: root: : > /var/log/wtmp",
+        ];
+        let content = lines.join("\n") + "\n";
+        let report = check(&content, &parse_content(&content));
+        let cleared: Vec<usize> =
+            report.issues.iter().filter(|i| i.kind == "log_cleared").map(|i| i.line_number).collect();
+        assert_eq!(cleared, vec![1, 2, 3, 4, 5, 11]);
+        assert!(report.issues[2].description.starts_with("Windows security audit log cleared"));
+    }
+
+    #[test]
+    fn detects_format_change_and_truncation() {
+        let mut content = steady(0..3);
+        for i in 0..12 {
+            content.push_str(&format!(
+                "203.0.113.5 - - [15/Jan/2024:10:{:02}:00 +0000] \"GET /item/{} HTTP/1.1\" 200 512 \"-\" \"Mozilla/5.0\"\n",
+                3 + i / 4,
+                i
+            ));
+        }
+        content.push_str("203.0.113.5 - - [15/Jan/2024:10:06:00 +0000] \"GET /item/12 HT");
+        let report = check(&content, &parse_content(&content));
+        assert_eq!(kinds(&report), vec!["format_change", "truncated_line"]);
+        assert!(report.issues[0].description.contains("from syslog (12 entries) to apache (12 entries)"));
+        assert_eq!(report.issues[0].line_number, 13);
+        assert_eq!(report.issues[1].line_number, 25);
+
+        // The same last line with its newline is just a malformed line
+        content.push('\n');
+        let report = check(&content, &parse_content(&content));
+        assert_eq!(kinds(&report), vec!["format_change"]);
+    }
+}
@@ -0,0 +1,689 @@
+// Offline threat-intelligence (IOC) matching
+// Indicators of compromise are loaded from local feeds, one feed per file:
+//   plain lists:  one IP, CIDR, domain or URL per line (`#` comments, text
+//                 after the first whitespace ignored, as in DROP lists)
+//   CSV:          the first column, or the `indicator`/`value`/`ip`/...
+//                 column when there is a header; an optional `type` column
+//   STIX 2.1:     `indicator` objects with `[ipv4-addr:value = '...']`-style
+//                 patterns (IPs, CIDRs, domains, URLs and HTTP user agents)
+//                 and bare `ipv4-addr`/`domain-name`/`url` objects
+// The feed name is the file name without its extension. Defanged values
+// (`evil[.]com`, `hxxp://`) are refanged.
+//
+// Networks are indexed per prefix length, so looking up an IP costs one hash
+// probe per prefix length in use rather than one comparison per CIDR.
+// Domains match their subdomains as well.
+//
+// Matching entries are tagged with `ioc.*` fields before they are analyzed,
+// so the `log.threat_intel` rule, alert rules and Sigma rules can all use
+// them.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::LogEntry;
+use crate::rules::allowlist::Cidr;
+
+/// Entry fields holding an IP address other than the source
+const IP_FIELDS: &[&str] = &["firewall.dst_ip", "destination.ip"];
+/// Entry fields holding a host name
+const DOMAIN_FIELDS: &[&str] = &["http.host", "url.domain", "destination.domain", "dns.query"];
+/// Entry fields that may hold an absolute URL
+const URL_FIELDS: &[&str] = &["http.path", "http.referer"];
+
+/// What an indicator describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorKind {
+    Ip,
+    Cidr,
+    Domain,
+    Url,
+    UserAgent,
+}
+
+impl IndicatorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndicatorKind::Ip => "ip",
+            IndicatorKind::Cidr => "cidr",
+            IndicatorKind::Domain => "domain",
+            IndicatorKind::Url => "url",
+            IndicatorKind::UserAgent => "user_agent",
+        }
+    }
+
+    /// Kind named by a CSV `type` column
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "ip" | "ipv4" | "ipv6" | "ip_address" | "ipv4_addr" | "ipv6_addr" => Some(IndicatorKind::Ip),
+            "cidr" | "network" | "netblock" | "subnet" => Some(IndicatorKind::Cidr),
+            "domain" | "domain_name" | "hostname" | "fqdn" => Some(IndicatorKind::Domain),
+            "url" | "uri" => Some(IndicatorKind::Url),
+            "user_agent" | "useragent" | "ua" => Some(IndicatorKind::UserAgent),
+            _ => None,
+        }
+    }
+}
+
+/// One indicator from one feed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Indicator {
+    pub feed: String,
+    pub kind: IndicatorKind,
+    /// Normalized value (lowercase host names, refanged)
+    pub indicator: String,
+}
+
+/// A loaded feed
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedInfo {
+    pub name: String,
+    pub path: String,
+    pub indicators: usize,
+    /// Lines or objects that held no usable indicator (and STIX indicators
+    /// that are revoked or past `valid_until`)
+    pub skipped: usize,
+}
+
+/// An indicator seen in the log, with every entry it was seen in counted
+#[derive(Debug, Clone, Serialize)]
+pub struct IocHit {
+    pub feed: String,
+    pub kind: IndicatorKind,
+    pub indicator: String,
+    /// Entry field the value was found in (`ip` for the source address)
+    pub field: String,
+    /// The value seen, e.g. the address inside a listed network
+    pub value: String,
+    pub entries: usize,
+    pub first_line: usize,
+}
+
+/// Feeds in use and the indicators they matched
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IocReport {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub feeds: Vec<FeedInfo>,
+    pub hits: Vec<IocHit>,
+}
+
+/// Networks by prefix length, keyed by the masked network address
+#[derive(Debug, Clone, Default)]
+struct NetworkIndex {
+    v4: BTreeMap<u8, HashMap<u32, Vec<usize>>>,
+    v6: BTreeMap<u8, HashMap<u128, Vec<usize>>>,
+}
+
+impl NetworkIndex {
+    fn insert(&mut self, cidr: Cidr, indicator: usize) {
+        match cidr.network {
+            IpAddr::V4(network) => {
+                let key = u32::from(network) & mask_v4(cidr.prefix);
+                self.v4.entry(cidr.prefix).or_default().entry(key).or_default().push(indicator);
+            }
+            IpAddr::V6(network) => {
+                let key = u128::from(network) & mask_v6(cidr.prefix);
+                self.v6.entry(cidr.prefix).or_default().entry(key).or_default().push(indicator);
+            }
+        }
+    }
+
+    /// Indicators covering `ip`, most specific network first
+    fn lookup(&self, ip: IpAddr) -> Vec<usize> {
+        match ip {
+            IpAddr::V4(ip) => {
+                let ip = u32::from(ip);
+                self.v4.iter().rev().filter_map(|(prefix, nets)| nets.get(&(ip & mask_v4(*prefix)))).flatten().copied().collect()
+            }
+            IpAddr::V6(ip) => {
+                let ip = u128::from(ip);
+                self.v6.iter().rev().filter_map(|(prefix, nets)| nets.get(&(ip & mask_v6(*prefix)))).flatten().copied().collect()
+            }
+        }
+    }
+}
+
+fn mask_v4(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0)
+}
+
+fn mask_v6(prefix: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0)
+}
+
+/// Indicators from every loaded feed, indexed for lookup
+#[derive(Debug, Clone, Default)]
+pub struct IocSet {
+    indicators: Vec<Indicator>,
+    feeds: Vec<FeedInfo>,
+    networks: NetworkIndex,
+    domains: HashMap<String, Vec<usize>>,
+    urls: HashMap<String, Vec<usize>>,
+    user_agents: HashMap<String, Vec<usize>>,
+}
+
+impl IocSet {
+    /// Load feed files, and every feed file in directories
+    pub fn load_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Self, String> {
+        let mut set = Self::default();
+        for path in paths {
+            set.load_path(path.as_ref())?;
+        }
+        Ok(set)
+    }
+
+    fn load_path(&mut self, path: &Path) -> Result<(), String> {
+        if path.is_dir() {
+            let mut children: Vec<_> = std::fs::read_dir(path)
+                .map_err(|e| format!("Failed to read IOC feed directory {}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|child| child.is_file() && !child.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')))
+                .collect();
+            children.sort();
+            for child in children {
+                self.load_path(&child)?;
+            }
+            return Ok(());
+        }
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read IOC feed {}: {}", path.display(), e))?;
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        let source = path.display().to_string();
+        match extension.as_str() {
+            "json" | "stix" => self.add_stix(&name, &source, &text),
+            "csv" => self.add_csv(&name, &source, &text),
+            _ => {
+                self.add_list(&name, &source, &text);
+                Ok(())
+            }
+        }
+    }
+
+    /// Add a plain list: one indicator per line
+    pub fn add_list(&mut self, feed: &str, source: &str, text: &str) {
+        let mut info = self.feed_info(feed, source);
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') || line.starts_with("//") {
+                continue;
+            }
+            let token = line.split_whitespace().next().unwrap_or_default();
+            self.add(&mut info, None, token);
+        }
+        self.feeds.push(info);
+    }
+
+    /// Add a CSV feed: the indicator column (with an optional `type` column)
+    pub fn add_csv(&mut self, feed: &str, source: &str, text: &str) -> Result<(), String> {
+        const VALUE_COLUMNS: &[&str] =
+            &["indicator", "value", "ioc", "ip", "ip_address", "dst_ip", "src_ip", "cidr", "network", "domain", "url", "user_agent"];
+        const TYPE_COLUMNS: &[&str] = &["type", "indicator_type", "ioc_type", "kind"];
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .trim(csv::Trim::All)
+            .from_reader(text.as_bytes());
+        let mut info = self.feed_info(feed, source);
+        let (mut value_column, mut type_column) = (0, None);
+        for (position, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| format!("IOC feed {}: {}", source, e))?;
+            // A header row names the columns; without one the first column holds the indicator
+            if position == 0 {
+                let column = |names: &[&str]| {
+                    record.iter().position(|cell| names.contains(&cell.to_lowercase().replace([' ', '-'], "_").as_str()))
+                };
+                if let Some(value) = column(VALUE_COLUMNS)
+                    && classify(record.get(value).unwrap_or_default()).is_none()
+                {
+                    value_column = value;
+                    type_column = column(TYPE_COLUMNS);
+                    continue;
+                }
+            }
+            let kind = type_column.and_then(|c| record.get(c)).and_then(IndicatorKind::from_name);
+            self.add(&mut info, kind, record.get(value_column).unwrap_or_default());
+        }
+        self.feeds.push(info);
+        Ok(())
+    }
+
+    /// Add a STIX 2.1 bundle (or a single object, or an array of objects)
+    pub fn add_stix(&mut self, feed: &str, source: &str, text: &str) -> Result<(), String> {
+        let document: Value = serde_json::from_str(text).map_err(|e| format!("IOC feed {}: {}", source, e))?;
+        let objects = match &document {
+            Value::Array(objects) => objects.as_slice(),
+            Value::Object(bundle) => match bundle.get("objects").and_then(Value::as_array) {
+                Some(objects) => objects.as_slice(),
+                None => std::slice::from_ref(&document),
+            },
+            _ => return Err(format!("IOC feed {}: not a STIX bundle", source)),
+        };
+
+        let now = Utc::now();
+        let mut info = self.feed_info(feed, source);
+        for object in objects {
+            let text_field = |name: &str| object.get(name).and_then(Value::as_str);
+            match text_field("type") {
+                Some("indicator") => {
+                    let expired = text_field("valid_until")
+                        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                        .is_some_and(|until| until < now);
+                    let revoked = object.get("revoked").and_then(Value::as_bool).unwrap_or(false);
+                    let is_stix = text_field("pattern_type").is_none_or(|t| t == "stix");
+                    let comparisons = match text_field("pattern") {
+                        Some(pattern) if is_stix && !expired && !revoked => stix_comparisons(pattern),
+                        _ => Vec::new(),
+                    };
+                    if comparisons.is_empty() {
+                        info.skipped += 1;
+                    }
+                    for (kind, value) in comparisons {
+                        self.add(&mut info, kind, &value);
+                    }
+                }
+                Some(kind @ ("ipv4-addr" | "ipv6-addr" | "domain-name" | "url")) => {
+                    let kind = IndicatorKind::from_name(kind).filter(|k| *k != IndicatorKind::Ip);
+                    self.add(&mut info, kind, text_field("value").unwrap_or_default());
+                }
+                _ => {}
+            }
+        }
+        self.feeds.push(info);
+        Ok(())
+    }
+
+    fn feed_info(&self, feed: &str, source: &str) -> FeedInfo {
+        FeedInfo { name: feed.to_string(), path: source.to_string(), indicators: 0, skipped: 0 }
+    }
+
+    /// Index one value; `kind` when the feed says what it is, inferred
+    /// otherwise (user agents are never inferred)
+    fn add(&mut self, info: &mut FeedInfo, kind: Option<IndicatorKind>, value: &str) {
+        let value = refang(value.trim());
+        let classified = match kind {
+            Some(IndicatorKind::UserAgent) if !value.is_empty() => Some((IndicatorKind::UserAgent, value.to_lowercase())),
+            Some(IndicatorKind::Domain) => normalize_domain(&value).map(|d| (IndicatorKind::Domain, d)),
+            _ => classify(&value),
+        };
+        let Some((kind, normalized)) = classified else {
+            info.skipped += 1;
+            return;
+        };
+        let indicator = Indicator { feed: info.name.clone(), kind, indicator: normalized.clone() };
+        let index = self.indicators.len();
+        let slot = match kind {
+            IndicatorKind::Ip | IndicatorKind::Cidr => match Cidr::parse(&normalized) {
+                Some(cidr) => {
+                    if self.networks.lookup(cidr.network).iter().any(|i| self.indicators[*i] == indicator) {
+                        return;
+                    }
+                    self.networks.insert(cidr, index);
+                    None
+                }
+                None => {
+                    info.skipped += 1;
+                    return;
+                }
+            },
+            IndicatorKind::Domain => Some(self.domains.entry(normalized).or_default()),
+            IndicatorKind::Url => Some(self.urls.entry(normalized).or_default()),
+            IndicatorKind::UserAgent => Some(self.user_agents.entry(normalized).or_default()),
+        };
+        if let Some(slot) = slot {
+            if slot.iter().any(|i| self.indicators[*i].feed == info.name) {
+                return;
+            }
+            slot.push(index);
+        }
+        self.indicators.push(indicator);
+        info.indicators += 1;
+    }
+
+    pub fn feeds(&self) -> &[FeedInfo] {
+        &self.feeds
+    }
+
+    pub fn len(&self) -> usize {
+        self.indicators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indicators.is_empty()
+    }
+
+    /// Indicators listing `ip`, directly or through a network
+    pub fn match_ip(&self, ip: &str) -> Vec<Indicator> {
+        match ip.trim().parse::<IpAddr>() {
+            Ok(ip) => self.networks.lookup(ip).into_iter().map(|i| self.indicators[i].clone()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Indicators listing the host name or one of its parent domains
+    pub fn match_domain(&self, host: &str) -> Vec<Indicator> {
+        let Some(host) = normalize_domain(host) else {
+            return Vec::new();
+        };
+        let mut found = Vec::new();
+        let mut suffix = host.as_str();
+        loop {
+            if let Some(indices) = self.domains.get(suffix) {
+                found.extend(indices.iter().map(|i| self.indicators[*i].clone()));
+            }
+            match suffix.split_once('.') {
+                Some((_, parent)) => suffix = parent,
+                None => return found,
+            }
+        }
+    }
+
+    /// Every indicator an entry matches, with the field and value matched
+    pub fn match_entry(&self, entry: &LogEntry) -> Vec<(Indicator, String, String)> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let mut found = Vec::new();
+        let mut push = |indicators: Vec<Indicator>, field: &str, value: &str| {
+            found.extend(indicators.into_iter().map(|i| (i, field.to_string(), value.to_string())));
+        };
+
+        if let Some(ip) = &entry.ip_address {
+            push(self.match_ip(ip), "ip", ip);
+        }
+        for field in IP_FIELDS {
+            if let Some(ip) = entry.fields.get(*field) {
+                push(self.match_ip(ip), field, ip);
+            }
+        }
+        for field in URL_FIELDS {
+            let Some(url) = entry.fields.get(*field).filter(|v| has_scheme(v)) else {
+                continue;
+            };
+            if let Some(normalized) = normalize_url(url) {
+                let without_query = normalized.split(['?', '#']).next().unwrap_or_default();
+                let mut keys = vec![normalized.as_str()];
+                if without_query != normalized {
+                    keys.push(without_query);
+                }
+                for key in keys {
+                    if let Some(indices) = self.urls.get(key) {
+                        push(indices.iter().map(|i| self.indicators[*i].clone()).collect(), field, url);
+                    }
+                }
+            }
+            if let Some(host) = url_host(url) {
+                push(self.match_domain(host), field, url);
+            }
+        }
+        for field in DOMAIN_FIELDS {
+            if let Some(host) = entry.fields.get(*field) {
+                push(self.match_domain(host), field, host);
+            }
+        }
+        if let Some(agent) = entry.fields.get("http.user_agent")
+            && let Some(indices) = self.user_agents.get(&agent.trim().to_lowercase())
+        {
+            push(indices.iter().map(|i| self.indicators[*i].clone()).collect(), "http.user_agent", agent);
+        }
+        found
+    }
+
+    /// Tag every matching entry with `ioc.feed`, `ioc.type`, `ioc.indicator`
+    /// and `ioc.field` (its first match) and summarize the matches per
+    /// indicator and value. `line_numbers` is parallel to `entries`.
+    pub fn tag(&self, entries: &mut [LogEntry], line_numbers: &[usize]) -> IocReport {
+        let mut report = IocReport { feeds: self.feeds.clone(), hits: Vec::new() };
+        let mut hits: BTreeMap<(String, IndicatorKind, String, String), IocHit> = BTreeMap::new();
+        for (index, entry) in entries.iter_mut().enumerate() {
+            let matches = self.match_entry(entry);
+            let line = line_numbers.get(index).copied().unwrap_or(index + 1);
+            for (indicator, field, value) in &matches {
+                let key = (indicator.feed.clone(), indicator.kind, indicator.indicator.clone(), value.clone());
+                hits.entry(key)
+                    .or_insert_with(|| IocHit {
+                        feed: indicator.feed.clone(),
+                        kind: indicator.kind,
+                        indicator: indicator.indicator.clone(),
+                        field: field.clone(),
+                        value: value.clone(),
+                        entries: 0,
+                        first_line: line,
+                    })
+                    .entries += 1;
+            }
+            if let Some((indicator, field, _)) = matches.into_iter().next() {
+                entry.fields.insert("ioc.feed".to_string(), indicator.feed);
+                entry.fields.insert("ioc.type".to_string(), indicator.kind.as_str().to_string());
+                entry.fields.insert("ioc.indicator".to_string(), indicator.indicator);
+                entry.fields.insert("ioc.field".to_string(), field);
+            }
+        }
+        report.hits = hits.into_values().collect();
+        report.hits.sort_by_key(|hit| hit.first_line);
+        report
+    }
+}
+
+/// Infer what a value is: IP, network, URL or domain
+fn classify(value: &str) -> Option<(IndicatorKind, String)> {
+    let value = refang(value.trim());
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Some((IndicatorKind::Ip, ip.to_string()));
+    }
+    if value.contains('/')
+        && let Some(cidr) = Cidr::parse(&value)
+    {
+        let bits = if cidr.network.is_ipv4() { 32 } else { 128 };
+        let kind = if cidr.prefix == bits { IndicatorKind::Ip } else { IndicatorKind::Cidr };
+        return Some((kind, value));
+    }
+    if has_scheme(&value) {
+        return normalize_url(&value).map(|url| (IndicatorKind::Url, url));
+    }
+    normalize_domain(&value).map(|domain| (IndicatorKind::Domain, domain))
+}
+
+/// Undo common defanging: `hxxp://`, `evil[.]com`, `1.2.3[.]4`
+fn refang(value: &str) -> String {
+    let value = value.replace("[.]", ".").replace("(.)", ".").replace("[:]", ":").replace("[://]", "://");
+    match value.get(..4) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("hxxp") => format!("http{}", &value[4..]),
+        _ => value,
+    }
+}
+
+fn has_scheme(value: &str) -> bool {
+    let lower = value.get(..8).unwrap_or(value).to_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Lowercase scheme and host, no trailing slash
+fn normalize_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let (host, path) = match rest.find(['/', '?', '#']) {
+        Some(split) => rest.split_at(split),
+        None => (rest, ""),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let normalized = format!("{}://{}{}", scheme.to_lowercase(), host.to_lowercase(), path);
+    Some(normalized.trim_end_matches('/').to_string())
+}
+
+/// Host of an absolute URL, without credentials or port
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.trim().split_once("://")?.1;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    Some(host.rsplit_once(':').map_or(host, |(host, _)| host))
+}
+
+/// Lowercase host name without a trailing dot, if it is one
+fn normalize_domain(value: &str) -> Option<String> {
+    static DOMAIN: OnceLock<Regex> = OnceLock::new();
+    let domain = DOMAIN.get_or_init(|| {
+        Regex::new(r"^(?:[a-z0-9_](?:[a-z0-9_-]{0,61}[a-z0-9])?\.)+[a-z][a-z0-9-]{0,62}$").expect("valid regex")
+    });
+    let value = value.trim().trim_end_matches('.').to_lowercase();
+    domain.is_match(&value).then_some(value)
+}
+
+/// The comparisons of a STIX pattern this matcher understands, joined by
+/// OR or AND alike: any observed value is worth flagging
+fn stix_comparisons(pattern: &str) -> Vec<(Option<IndicatorKind>, String)> {
+    static COMPARISON: OnceLock<Regex> = OnceLock::new();
+    let comparison = COMPARISON.get_or_init(|| {
+        Regex::new(
+            r"(?i)(ipv4-addr|ipv6-addr|domain-name|url|network-traffic):(value|extensions\.'http-request-ext'\.request_header\.'user-agent')\s*(?:=|ISSUBSET)\s*'((?:[^'\\]|\\.)*)'",
+        )
+        .expect("valid regex")
+    });
+    comparison
+        .captures_iter(pattern)
+        .filter_map(|c| {
+            let value = c[3].replace("\\'", "'").replace("\\\\", "\\");
+            let kind = match (c[1].to_lowercase().as_str(), c[2].to_lowercase().as_str()) {
+                ("network-traffic", property) if property != "value" => Some(IndicatorKind::UserAgent),
+                ("network-traffic", _) => return None,
+                ("domain-name", _) => Some(IndicatorKind::Domain),
+                ("url", _) => Some(IndicatorKind::Url),
+                _ => None,
+            };
+            Some((kind, value))
+        })
+        .collect()
+}
+
+static ACTIVE_IOCS: OnceLock<IocSet> = OnceLock::new();
+
+/// Install the process-wide IOC feeds. Must be called before the first log
+/// is analyzed; fails if the feeds are already in use.
+pub fn install(set: IocSet) -> Result<(), String> {
+    ACTIVE_IOCS
+        .set(set)
+        .map_err(|_| "IOC feeds are already initialized".to_string())
+}
+
+/// The process-wide IOC feeds (none unless `install` was called)
+pub fn active() -> &'static IocSet {
+    ACTIVE_IOCS.get_or_init(IocSet::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_log_line_unified;
+
+    fn web_entry(ip: &str, path: &str, agent: &str) -> LogEntry {
+        let line = format!(
+            "{} - - [01/Mar/2024:10:00:00 +0000] \"GET {} HTTP/1.1\" 200 512 \"-\" \"{}\"",
+            ip, path, agent
+        );
+        parse_log_line_unified(&line).unwrap()
+    }
+
+    #[test]
+    fn lists_and_csv_feeds_index_networks() {
+        let mut set = IocSet::default();
+        set.add_list(
+            "drop",
+            "drop.txt",
+            "; Spamhaus DROP\n198.51.100.0/24 ; SBL1\n203.0.113.7\n2001:db8::/32\nevil[.]example\nnot an indicator\n",
+        );
+        set.add_csv(
+            "feodo",
+            "feodo.csv",
+            "# C2 servers\nfirst_seen,dst_ip,type\n2024-01-01,192.0.2.10,ip\n2024-01-02,Mozilla/4.0 (compatible; EvilBot),user_agent\n",
+        )
+        .unwrap();
+        assert_eq!(set.len(), 6);
+        assert_eq!((set.feeds()[0].indicators, set.feeds()[0].skipped), (4, 1));
+
+        let hits = set.match_ip("198.51.100.77");
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].feed.as_str(), hits[0].kind, hits[0].indicator.as_str()), ("drop", IndicatorKind::Cidr, "198.51.100.0/24"));
+        assert_eq!(set.match_ip("203.0.113.7")[0].kind, IndicatorKind::Ip);
+        assert_eq!(set.match_ip("2001:db8::1").len(), 1);
+        assert!(set.match_ip("203.0.113.8").is_empty());
+        assert_eq!(set.match_domain("cdn.EVIL.example.")[0].indicator, "evil.example");
+        assert!(set.match_domain("notevil.example").is_empty());
+
+        // The CSV header names the value column; its `type` column is used
+        let agent = web_entry("10.0.0.1", "/", "mozilla/4.0 (compatible; evilbot)");
+        let matched = set.match_entry(&agent);
+        assert_eq!(matched.len(), 1);
+        assert_eq!((matched[0].0.kind, matched[0].1.as_str()), (IndicatorKind::UserAgent, "http.user_agent"));
+    }
+
+    #[test]
+    fn stix_bundles_yield_indicators() {
+        let bundle = r#"{
+            "type": "bundle",
+            "id": "bundle--1",
+            "objects": [
+                {"type": "indicator", "pattern_type": "stix",
+                 "pattern": "[ipv4-addr:value = '203.0.113.0/25'] OR [domain-name:value = 'c2.example.net']"},
+                {"type": "indicator", "pattern_type": "stix",
+                 "pattern": "[url:value = 'http://files.example.org/payload.exe']"},
+                {"type": "indicator", "pattern_type": "stix",
+                 "pattern": "[network-traffic:extensions.'http-request-ext'.request_header.'User-Agent' = 'Havoc\\'s agent']"},
+                {"type": "indicator", "pattern_type": "stix", "valid_until": "2001-01-01T00:00:00Z",
+                 "pattern": "[ipv4-addr:value = '192.0.2.1']"},
+                {"type": "indicator", "pattern_type": "sigma", "pattern": "title: x"},
+                {"type": "ipv6-addr", "id": "ipv6-addr--1", "value": "2001:db8::66"},
+                {"type": "malware", "name": "ignored"}
+            ]
+        }"#;
+        let mut set = IocSet::default();
+        set.add_stix("apt-feed", "apt-feed.json", bundle).unwrap();
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.feeds()[0].skipped, 2);
+        assert!(set.match_ip("192.0.2.1").is_empty());
+        assert_eq!(set.match_ip("2001:db8::66")[0].kind, IndicatorKind::Ip);
+
+        let mut entry = web_entry("203.0.113.5", "/index.html", "Havoc's agent");
+        entry.fields.insert("http.referer".to_string(), "HTTP://Files.Example.org/payload.exe?x=1".to_string());
+        let kinds: Vec<IndicatorKind> = set.match_entry(&entry).into_iter().map(|(i, _, _)| i.kind).collect();
+        assert_eq!(kinds, vec![IndicatorKind::Cidr, IndicatorKind::Url, IndicatorKind::UserAgent]);
+        assert!(set.add_stix("bad", "bad.json", "[1, 2").is_err());
+    }
+
+    #[test]
+    fn tagging_marks_entries_and_summarizes_hits() {
+        let mut set = IocSet::default();
+        set.add_list("blocklist", "blocklist.txt", "203.0.113.0/24\nhxxp://c2.example.net/gate.php\n");
+        let mut entries = vec![
+            web_entry("203.0.113.5", "/login", "curl/8.0"),
+            web_entry("198.51.100.1", "/", "Mozilla/5.0"),
+            web_entry("203.0.113.5", "/admin", "curl/8.0"),
+            web_entry("198.51.100.2", "http://c2.example.net/gate.php", "Mozilla/5.0"),
+        ];
+        let report = set.tag(&mut entries, &[10, 11, 12, 13]);
+
+        assert_eq!(entries[0].fields["ioc.feed"], "blocklist");
+        assert_eq!(entries[0].fields["ioc.type"], "cidr");
+        assert_eq!(entries[0].fields["ioc.field"], "ip");
+        assert!(!entries[1].fields.contains_key("ioc.feed"));
+        assert_eq!(entries[3].fields["ioc.indicator"], "http://c2.example.net/gate.php");
+
+        assert_eq!(report.hits.len(), 2);
+        assert_eq!((report.hits[0].value.as_str(), report.hits[0].entries, report.hits[0].first_line), ("203.0.113.5", 2, 10));
+        assert_eq!((report.hits[1].kind, report.hits[1].first_line), (IndicatorKind::Url, 13));
+        assert_eq!(report.feeds[0].indicators, 2);
+    }
+}
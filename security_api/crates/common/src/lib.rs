@@ -4,9 +4,16 @@
 pub mod cvss;
 pub mod geolocation;
 pub mod parsers;
+pub mod rules;
+pub mod sessions;
+pub mod travel;
 pub mod database;
+pub mod feedback;
+pub mod integrity;
+pub mod ioc;
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Log entry parsed from security logs
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,6 +23,10 @@ pub struct LogEntry {
     pub ip_address: Option<String>,
     pub username: Option<String>,
     pub message: String,
+    /// Additional structured fields captured by format-specific parsers
+    /// (e.g. `container.stream`), keyed by dotted field name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
 }
 
 /// Complete analysis result returned to API
@@ -26,6 +37,40 @@ pub struct AnalysisResult {
     pub risk_assessment: RiskAssessment,
     pub parsing_info: ParsingInfo,
     pub alerts: Vec<Alert>,
+    /// Every detected threat with the line it was found on
+    pub findings: Vec<Finding>,
+    /// Failed-login campaigns classified by shape (brute force, spraying,
+    /// stuffing) and successful logins that followed such failures
+    pub auth_attacks: Vec<AuthAttack>,
+    /// Port scans seen in firewall logs
+    pub port_scans: Vec<PortScan>,
+    /// Request rates well above the baseline: per-IP bursts and floods,
+    /// per-endpoint 5xx spikes
+    pub rate_anomalies: Vec<RateAnomaly>,
+    /// Large or repeated outsized downloads and archive/backup file
+    /// downloads, per source IP
+    pub exfiltration: Vec<Exfiltration>,
+    /// Content discovery runs (dirbuster, ffuf, gobuster and the like)
+    pub content_discovery: Vec<ContentDiscovery>,
+    /// Web requests grouped into visitor sessions, with bot classification
+    pub sessions: Vec<sessions::WebSession>,
+    /// Successful logins with the account and source IP, in log order
+    pub logins: Vec<Login>,
+    /// Logins to one account from places too far apart for the time between
+    /// them; needs geolocation, so only filled where lookups are made
+    pub impossible_travel: Vec<ImpossibleTravel>,
+    /// Sigma rule findings and rules that could not be loaded
+    pub sigma: rules::sigma::SigmaReport,
+    /// Signs that the log was edited, truncated or cleared
+    pub integrity: integrity::IntegrityReport,
+    /// Findings, reports, alert events and high-risk IPs the allowlist
+    /// suppressed
+    pub suppressed: rules::allowlist::SuppressionReport,
+    /// IOC feeds in use and the indicators seen in the log
+    pub threat_intel: ioc::IocReport,
+    /// Analyst verdicts on the rules behind the findings
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rule_precision: Vec<feedback::RulePrecision>,
 }
 
 /// Information about log parsing quality
@@ -34,7 +79,12 @@ pub struct ParsingInfo {
     pub total_lines: usize,
     pub parsed_lines: usize,
     pub skipped_lines: usize,
+    /// True when only recognized formats were accepted (no free-text fallback)
+    pub strict: bool,
+    /// Sample of rejected lines, capped per category
     pub errors: Vec<ParseError>,
+    /// Number of rejected lines per category (`malformed_apache`, `unrecognized_format`, ...)
+    pub error_counts: BTreeMap<String, usize>,
     pub format_quality: FormatQuality,
 }
 
@@ -53,6 +103,14 @@ pub struct ParseError {
     pub line_content: String,
     pub error_type: String,
     pub suggestion: String,
+    /// Aggregation key for `ParsingInfo::error_counts`
+    pub category: String,
+    /// Parser that matched the most of the line before failing
+    pub parser: String,
+    /// 1-based character column where that parser stopped matching
+    pub column: usize,
+    /// What that parser expected at `column`
+    pub expected: String,
 }
 
 /// Threat statistics with CVSS scores
@@ -63,6 +121,11 @@ pub struct ThreatStats {
     pub suspicious_file_access: usize,
     pub critical_alerts: usize,
     pub sql_injection_attempts: usize,
+    pub xss_attempts: usize,
+    pub path_traversal_attempts: usize,
+    pub command_injection_attempts: usize,
+    pub scanner_detections: usize,
+    pub unauthorized_access_attempts: usize,
     pub port_scanning_attempts: usize,
     pub malware_detections: usize,
     pub cvss_scores: Vec<ThreatCVSS>,
@@ -80,6 +143,35 @@ pub struct ThreatCVSS {
     pub explanation: String,
 }
 
+/// One detected threat on one log entry
+#[derive(Debug, Serialize, Clone)]
+pub struct Finding {
+    /// Detection rule id (`log.sql_injection`), or the behavioral detector
+    /// for threats found across entries (`auth.brute_force`, `scan.vertical`)
+    pub rule_id: String,
+    pub threat_type: cvss::ThreatType,
+    pub severity: cvss::Severity,
+    /// Field that matched (`message`, `http.path`, ...)
+    pub field: String,
+    /// The matched text within that field
+    pub matched: String,
+    /// Index into the parsed entries
+    pub entry_index: usize,
+    /// 1-based source line (the entry position when line numbers are unknown)
+    pub line_number: usize,
+    pub ip_address: Option<String>,
+    pub username: Option<String>,
+    /// The entry's message (first 200 characters)
+    pub message: String,
+    /// Severity before analyst feedback lowered it (the rule keeps producing
+    /// false positives)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downgraded_from: Option<cvss::Severity>,
+    /// CVE, product and CVSS score when an exploit signature matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vulnerability: Option<cvss::Vulnerability>,
+}
+
 /// IP address analysis results
 #[derive(Serialize)]
 pub struct IpAnalysis {
@@ -93,9 +185,14 @@ pub struct IpInfo {
     pub ip: String,
     pub count: usize,
     pub risk_level: String,
+    /// Response bytes served to this IP (web requests with a size)
+    pub total_bytes: u64,
     pub country: Option<String>,
     pub city: Option<String>,
     pub is_vpn: bool,
+    /// Threat-intelligence feed entries listing this IP
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub threat_intel: Vec<ioc::Indicator>,
 }
 
 /// Geolocation data
@@ -119,10 +216,159 @@ pub struct Alert {
     pub triggered_by: String,
 }
 
-/// Alert rule configuration
-#[derive(Serialize)]
+/// A burst of failed logins classified by its shape, or a successful login
+/// that followed one
+#[derive(Debug, Serialize, Clone)]
+pub struct AuthAttack {
+    /// "Brute Force", "Password Spraying", "Credential Stuffing" or
+    /// "Probable Account Compromise"
+    pub pattern: String,
+    pub severity: String,
+    pub failed_attempts: usize,
+    /// Targeted accounts, most attempted first
+    pub accounts: Vec<String>,
+    /// Source IPs, most active first
+    pub source_ips: Vec<String>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub description: String,
+    /// The events behind the finding in time order (`timestamp message`);
+    /// for a probable compromise, every failure and then the success
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<String>,
+}
+
+/// A port scan reconstructed from firewall records
+#[derive(Debug, Serialize, Clone)]
+pub struct PortScan {
+    pub source_ip: String,
+    /// `vertical` (many ports on one host) or `horizontal` (one port across
+    /// many hosts)
+    pub kind: String,
+    /// Scanned hosts (one for a vertical scan)
+    pub targets: Vec<String>,
+    /// Scanned destination ports, ascending (one for a horizontal scan)
+    pub ports: Vec<u16>,
+    /// Firewall records belonging to the scan
+    pub events: usize,
+    /// Records the firewall blocked
+    pub blocked: usize,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub description: String,
+}
+
+/// A stretch of web traffic whose per-minute rate stands out from the
+/// baseline (see `analyzer_basic::rate`)
+#[derive(Debug, Serialize, Clone)]
+pub struct RateAnomaly {
+    /// `burst` (a few minutes), `flood` (sustained) or `error_spike` (5xx
+    /// responses on one endpoint)
+    pub kind: String,
+    /// What the traffic looks like: `dos`, `scraping`, `exploitation_loop`,
+    /// or `server_errors` for an error spike
+    pub pattern: String,
+    /// `ip` or `endpoint`
+    pub scope: String,
+    /// The IP address or request path
+    pub subject: String,
+    /// Requests (5xx responses for an error spike) during the anomaly
+    pub requests: usize,
+    /// Anomalous one-minute buckets
+    pub minutes: usize,
+    pub peak_per_minute: usize,
+    /// Median per-minute rate the peak was compared with
+    pub baseline_per_minute: f64,
+    /// Robust z-score of the peak minute against the baseline
+    pub z_score: f64,
+    /// Distinct paths requested (for an IP) or distinct clients (for an endpoint)
+    pub distinct: usize,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub description: String,
+}
+
+/// Responses to one client that suggest data is leaving the server
+#[derive(Debug, Serialize, Clone)]
+pub struct Exfiltration {
+    pub source_ip: String,
+    /// `volume` (far more bytes than other clients), `outsized_responses`
+    /// (responses far above their endpoint's usual size) or
+    /// `archive_download` (backups, dumps and archives)
+    pub kind: String,
+    /// Responses behind the finding
+    pub requests: usize,
+    /// Bytes served in those responses
+    pub bytes: u64,
+    /// Requested paths, in request order without repeats
+    pub paths: Vec<String>,
+    /// The client had a SQL injection request answered successfully first
+    pub after_sql_injection: bool,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub description: String,
+}
+
+/// A successful login by a known account from a known address
+#[derive(Debug, Serialize, Clone)]
+pub struct Login {
+    pub account: String,
+    pub ip: String,
+    pub timestamp: String,
+    pub line_number: usize,
+}
+
+/// Two successful logins to one account from places too far apart for the
+/// time between them
+#[derive(Debug, Serialize, Clone)]
+pub struct ImpossibleTravel {
+    pub account: String,
+    pub from_ip: String,
+    pub from_location: String,
+    pub from_time: String,
+    pub to_ip: String,
+    pub to_location: String,
+    pub to_time: String,
+    pub distance_km: f64,
+    pub hours: f64,
+    pub speed_kmh: f64,
+    /// Logins from VPN, proxy or hosting ranges in between, which were left
+    /// out of the comparison
+    pub anonymized_logins_skipped: usize,
+    /// Line of the second login
+    pub line_number: usize,
+    pub description: String,
+}
+
+/// One client guessing paths: many distinct requests, mostly not found
+#[derive(Debug, Serialize, Clone)]
+pub struct ContentDiscovery {
+    pub source_ip: String,
+    pub requests: usize,
+    pub distinct_paths: usize,
+    /// Share of 404/410 responses
+    pub not_found_ratio: f64,
+    /// Distinct paths per minute over the client's active time
+    pub paths_per_minute: f64,
+    /// Requested paths on the sensitive-path wordlist, in request order
+    pub sensitive_probes: Vec<String>,
+    /// Sensitive probes that were served with a 2xx status
+    pub exposed: Vec<String>,
+    /// User agents the client used
+    pub user_agents: Vec<String>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub description: String,
+}
+
+/// Alert rule configuration: raise an alert when `threshold` entries from one
+/// IP match `condition` within `timeframe_minutes` (see `rules::alerts`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct AlertRule {
     pub name: String,
+    /// `rule:<detection rule id>`, `<field> == <value>`, `<field> != <value>`
+    /// or `<field> contains <value>`
     pub condition: String,
     pub threshold: usize,
     pub timeframe_minutes: u32,
@@ -8,6 +8,9 @@ use nom::{
     IResult,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::rules::{RuleScope, RuleSet};
 
 /// Apache Combined Log Format Entry
 /// Format: IP - - [timestamp] "METHOD /path HTTP/1.1" status size "referer" "user-agent"
@@ -30,121 +33,54 @@ pub struct ApacheLog {
 }
 
 impl ApacheLog {
-    /// Analyze log entry for security threats
+    /// Analyze log entry for security threats using the active rule set
     pub fn analyze(&mut self) {
-        // SQL Injection patterns
-        if self.is_sql_injection() {
-            self.is_suspicious = true;
-            self.threat_type = Some("SQL Injection".to_string());
-            self.severity = Some("Critical".to_string());
-            return;
-        }
-        
-        // Path Traversal
-        if self.is_path_traversal() {
-            self.is_suspicious = true;
-            self.threat_type = Some("Path Traversal".to_string());
-            self.severity = Some("High".to_string());
-            return;
-        }
-        
-        // XSS attempts
-        if self.is_xss() {
-            self.is_suspicious = true;
-            self.threat_type = Some("Cross-Site Scripting".to_string());
-            self.severity = Some("High".to_string());
-            return;
-        }
-        
-        // Command Injection
-        if self.is_command_injection() {
-            self.is_suspicious = true;
-            self.threat_type = Some("Command Injection".to_string());
-            self.severity = Some("Critical".to_string());
-            return;
-        }
-        
-        // Suspicious status codes
-        if self.status == 401 || self.status == 403 {
-            self.is_suspicious = true;
-            self.threat_type = Some("Unauthorized Access Attempt".to_string());
-            self.severity = Some("Medium".to_string());
-            return;
-        }
-        
-        // Scanner detection
-        if self.is_scanner() {
-            self.is_suspicious = true;
-            self.threat_type = Some("Security Scanner".to_string());
-            self.severity = Some("Medium".to_string());
-            return;
-        }
-    }
-    
-    fn is_sql_injection(&self) -> bool {
-        let path_lower = self.path.to_lowercase();
-        path_lower.contains("union") && path_lower.contains("select")
-            || path_lower.contains("or 1=1")
-            || path_lower.contains("or '1'='1")
-            || path_lower.contains("'; drop table")
-            || path_lower.contains("' or '1'='1")
-    }
-    
-    fn is_path_traversal(&self) -> bool {
-        self.path.contains("../")
-            || self.path.contains("..\\")
-            || self.path.contains("%2e%2e%2f")
-            || self.path.contains("%2e%2e/")
+        self.analyze_with(crate::rules::active());
     }
-    
-    fn is_xss(&self) -> bool {
-        let path_lower = self.path.to_lowercase();
-        path_lower.contains("<script")
-            || path_lower.contains("javascript:")
-            || path_lower.contains("onerror=")
-            || path_lower.contains("onload=")
+
+    /// Request attributes as `LogEntry` structured fields (`http.method`, ...)
+    pub fn http_fields(&self) -> BTreeMap<String, String> {
+        [
+            ("http.method", self.method.clone()),
+            ("http.path", self.path.clone()),
+            ("http.protocol", self.protocol.clone()),
+            ("http.status", self.status.to_string()),
+            ("http.size", self.size.to_string()),
+            ("http.referer", self.referer.clone()),
+            ("http.user_agent", self.user_agent.clone()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
     }
-    
-    fn is_command_injection(&self) -> bool {
-        let path_lower = self.path.to_lowercase();
 
-        // A shell metacharacter alone is not enough: legitimate URLs often
-        // contain ';' or '|' in query strings (e.g. matrix params, filters).
-        // Require a chaining/substitution metacharacter *and* an actual shell
-        // command token to flag a genuine injection payload.
-        let has_metachar = path_lower.contains(';')
-            || path_lower.contains('|')
-            || path_lower.contains("&&")
-            || path_lower.contains('`')
-            || path_lower.contains("$(")
-            || path_lower.contains("%3b") // ; encoded
-            || path_lower.contains("%7c") // | encoded
-            || path_lower.contains("%60"); // ` encoded
+    /// Rebuild a request from a parsed entry's `http.*` fields. Entries
+    /// without a request path or a usable timestamp give `None`.
+    pub fn from_entry(entry: &crate::LogEntry) -> Option<Self> {
+        let field = |name: &str| entry.fields.get(name).cloned();
+        Some(Self {
+            ip: entry.ip_address.clone().unwrap_or_else(|| "unknown".to_string()),
+            timestamp: super::parse_timestamp(&entry.timestamp)?,
+            method: field("http.method").unwrap_or_else(|| "GET".to_string()),
+            path: field("http.path")?,
+            protocol: field("http.protocol").unwrap_or_else(|| "HTTP/1.1".to_string()),
+            status: field("http.status").and_then(|s| s.parse().ok()).unwrap_or(0),
+            size: field("http.size").and_then(|s| s.parse().ok()).unwrap_or(0),
+            referer: field("http.referer").unwrap_or_else(|| "-".to_string()),
+            user_agent: field("http.user_agent").unwrap_or_else(|| "-".to_string()),
+            is_suspicious: false,
+            threat_type: None,
+            severity: None,
+        })
+    }
 
-        if !has_metachar {
-            return false;
+    /// Label the request with the first matching `http` rule
+    pub fn analyze_with(&mut self, rules: &RuleSet) {
+        if let Some(detection) = rules.first_match(RuleScope::Http, self) {
+            self.is_suspicious = true;
+            self.threat_type = Some(detection.name);
+            self.severity = Some(detection.severity.as_str().to_string());
         }
-
-        const COMMAND_TOKENS: [&str; 16] = [
-            "whoami", "netcat", "wget", "curl", "bash", "/bin/", "/etc/",
-            "cat ", "cat%20", "ls ", "ls%20", "rm ", "rm%20", "chmod",
-            "cmd.exe", "powershell",
-        ];
-
-        COMMAND_TOKENS
-            .iter()
-            .any(|token| path_lower.contains(token))
-    }
-    
-    fn is_scanner(&self) -> bool {
-        let ua_lower = self.user_agent.to_lowercase();
-        ua_lower.contains("nmap")
-            || ua_lower.contains("nikto")
-            || ua_lower.contains("sqlmap")
-            || ua_lower.contains("masscan")
-            || ua_lower.contains("nessus")
-            || ua_lower.contains("burp")
-            || ua_lower.contains("acunetix")
     }
 }
 
@@ -0,0 +1,269 @@
+// Container runtime log unwrapping
+// Container runtimes wrap every application line before writing it to disk:
+//   Docker json-file: {"log":"<inner line>\n","stream":"stderr","time":"2025-02-20T10:30:45.1Z"}
+//   CRI (containerd/CRI-O): 2025-02-20T10:30:45.1Z stdout F <inner line>
+// The wrapper is stripped and the inner line is parsed as if it had been read
+// directly, so an Apache line logged by a container is still parsed as Apache.
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::LogEntry;
+use super::{LineFormat, ParseOptions, classify_log_line};
+
+/// Runtime that produced a wrapped container log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerRuntime {
+    Docker,
+    Cri,
+}
+
+impl ContainerRuntime {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Cri => "cri",
+        }
+    }
+}
+
+/// A single record written by a container runtime, with the wrapper removed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerLine {
+    pub runtime: ContainerRuntime,
+    /// Output stream the application wrote to (`stdout` or `stderr`)
+    pub stream: String,
+    /// Time the runtime recorded the line, verbatim
+    pub time: String,
+    /// True when the runtime split a long line and more chunks follow
+    pub partial: bool,
+    /// The application's own log line
+    pub log: String,
+}
+
+/// Detect a Docker json-file or CRI wrapper and unwrap the inner line.
+///
+/// Returns `None` for lines that are not container runtime records.
+pub fn unwrap_container_line(line: &str) -> Option<ContainerLine> {
+    parse_docker_json(line).or_else(|| parse_cri(line))
+}
+
+/// Parse a Docker json-file record: `{"log":"...\n","stream":"stdout","time":"..."}`.
+///
+/// Docker marks a split line by omitting the trailing newline from every
+/// chunk except the last one.
+fn parse_docker_json(line: &str) -> Option<ContainerLine> {
+    let trimmed = line.trim();
+    if !trimmed.starts_with('{') || !trimmed.ends_with('}') {
+        return None;
+    }
+
+    let value: Value = serde_json::from_str(trimmed).ok()?;
+    let obj = value.as_object()?;
+    let log = obj.get("log")?.as_str()?;
+    let stream = obj.get("stream")?.as_str()?;
+    let time = obj.get("time").and_then(|v| v.as_str()).unwrap_or_default();
+
+    let partial = !log.ends_with('\n');
+    let log = log.trim_end_matches('\n').trim_end_matches('\r');
+
+    Some(ContainerLine {
+        runtime: ContainerRuntime::Docker,
+        stream: stream.to_string(),
+        time: time.to_string(),
+        partial,
+        log: log.to_string(),
+    })
+}
+
+/// Parse a CRI record: `<RFC 3339 time> <stdout|stderr> <P|F> <line>`.
+fn parse_cri(line: &str) -> Option<ContainerLine> {
+    static CRI: OnceLock<Regex> = OnceLock::new();
+    let re = CRI.get_or_init(|| {
+        Regex::new(
+            r"^(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:\d{2})) (stdout|stderr) ([PF]) ?(.*)$",
+        )
+        .expect("CRI record pattern")
+    });
+
+    let caps = re.captures(line)?;
+
+    Some(ContainerLine {
+        runtime: ContainerRuntime::Cri,
+        stream: caps.get(2)?.as_str().to_string(),
+        time: caps.get(1)?.as_str().to_string(),
+        partial: caps.get(3)?.as_str() == "P",
+        log: caps.get(4).map(|m| m.as_str()).unwrap_or_default().to_string(),
+    })
+}
+
+/// Parse the inner line of a (complete) container record.
+///
+/// The inner line is re-dispatched through the unified parser. The
+/// container stream, runtime and time are kept as structured fields, and the
+/// runtime time is used when the inner line carries no timestamp of its own.
+pub fn parse_container_line(container: &ContainerLine) -> Option<LogEntry> {
+    parse_container_line_with(container, &ParseOptions::default())
+}
+
+/// `parse_container_line` with per-source options applied to the inner line
+pub fn parse_container_line_with(container: &ContainerLine, options: &ParseOptions) -> Option<LogEntry> {
+    classify_container_line(container, options)
+        .filter(|(_, format)| options.accepts(*format))
+        .map(|(entry, _)| entry)
+}
+
+/// Parse the inner line and report which format recognized it
+pub(crate) fn classify_container_line(
+    container: &ContainerLine,
+    options: &ParseOptions,
+) -> Option<(LogEntry, LineFormat)> {
+    let (mut entry, format) = classify_log_line(&container.log, options)?;
+
+    if entry.timestamp.is_empty() {
+        entry.timestamp = container.time.clone();
+    }
+
+    entry.fields.insert("container.runtime".to_string(), container.runtime.as_str().to_string());
+    entry.fields.insert("container.stream".to_string(), container.stream.clone());
+    entry.fields.insert("container.time".to_string(), container.time.clone());
+
+    Some((entry, format))
+}
+
+/// Reassembles application lines that the runtime split into partial records.
+///
+/// Chunks are buffered per runtime and stream (stdout and stderr are written
+/// independently and may interleave) until the final chunk arrives.
+#[derive(Debug, Default)]
+pub struct ContainerReassembler {
+    pending: HashMap<(ContainerRuntime, String), ContainerLine>,
+}
+
+impl ContainerReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one record. Returns the complete line once its final chunk has
+    /// been seen, or `None` while more chunks are expected.
+    pub fn push(&mut self, chunk: ContainerLine) -> Option<ContainerLine> {
+        let key = (chunk.runtime, chunk.stream.clone());
+
+        let line = match self.pending.remove(&key) {
+            Some(mut buffered) => {
+                buffered.log.push_str(&chunk.log);
+                buffered.partial = chunk.partial;
+                buffered
+            }
+            None => chunk,
+        };
+
+        if line.partial {
+            self.pending.insert(key, line);
+            None
+        } else {
+            Some(line)
+        }
+    }
+
+    /// Flush lines whose final chunk never arrived (e.g. a truncated file).
+    pub fn finish(&mut self) -> Vec<ContainerLine> {
+        self.pending.drain().map(|(_, line)| line).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unwraps_docker_json_file_record() {
+        let line = r#"{"log":"Failed password for root from 203.0.113.5 port 22 ssh2\n","stream":"stderr","time":"2025-02-20T10:30:45.123Z"}"#;
+        let container = unwrap_container_line(line).expect("should unwrap");
+        assert_eq!(container.runtime, ContainerRuntime::Docker);
+        assert_eq!(container.stream, "stderr");
+        assert_eq!(container.time, "2025-02-20T10:30:45.123Z");
+        assert!(!container.partial);
+        assert_eq!(container.log, "Failed password for root from 203.0.113.5 port 22 ssh2");
+    }
+
+    #[test]
+    fn unwraps_cri_record() {
+        let line = "2025-02-20T10:30:45.1Z stdout F GET /health returned 200";
+        let container = unwrap_container_line(line).expect("should unwrap");
+        assert_eq!(container.runtime, ContainerRuntime::Cri);
+        assert_eq!(container.stream, "stdout");
+        assert!(!container.partial);
+        assert_eq!(container.log, "GET /health returned 200");
+    }
+
+    #[test]
+    fn ignores_plain_lines_and_ordinary_json() {
+        assert!(unwrap_container_line("2025-02-20 10:30:45 [ERROR] something").is_none());
+        assert!(unwrap_container_line(r#"{"level":"error","message":"boom"}"#).is_none());
+    }
+
+    #[test]
+    fn apache_line_inside_docker_is_parsed_as_apache() {
+        let line = r#"{"log":"203.0.113.10 - - [15/Dec/2025:17:19:00 +0000] \"GET /../../etc/passwd HTTP/1.1\" 403 0 \"-\" \"curl/8.0\"\n","stream":"stdout","time":"2025-12-15T17:19:00.5Z"}"#;
+        let container = unwrap_container_line(line).expect("should unwrap");
+        let entry = parse_container_line(&container).expect("should parse");
+        assert_eq!(entry.ip_address, Some("203.0.113.10".to_string()));
+        assert_eq!(entry.timestamp, "2025-12-15T17:19:00+00:00");
+        assert!(entry.message.contains("Path Traversal"));
+        assert_eq!(entry.fields.get("container.stream"), Some(&"stdout".to_string()));
+        assert_eq!(entry.fields.get("container.runtime"), Some(&"docker".to_string()));
+    }
+
+    #[test]
+    fn container_time_used_when_inner_line_has_none() {
+        let line = "2025-02-20T10:30:45.1Z stderr F Suspicious activity detected from IP 172.16.0.50";
+        let container = unwrap_container_line(line).expect("should unwrap");
+        let entry = parse_container_line(&container).expect("should parse");
+        assert_eq!(entry.timestamp, "2025-02-20T10:30:45.1Z");
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.fields.get("container.time"), Some(&"2025-02-20T10:30:45.1Z".to_string()));
+    }
+
+    #[test]
+    fn reassembles_partial_cri_chunks() {
+        let mut reassembler = ContainerReassembler::new();
+        let chunks = [
+            "2025-02-20T10:30:45.1Z stdout P Failed password for ",
+            "2025-02-20T10:30:45.2Z stderr F unrelated stderr line",
+            "2025-02-20T10:30:45.3Z stdout P admin from ",
+            "2025-02-20T10:30:45.4Z stdout F 198.51.100.7 port 22 ssh2",
+        ];
+
+        let complete: Vec<ContainerLine> = chunks
+            .iter()
+            .filter_map(|c| unwrap_container_line(c))
+            .filter_map(|c| reassembler.push(c))
+            .collect();
+
+        assert_eq!(complete.len(), 2);
+        assert_eq!(complete[0].log, "unrelated stderr line");
+        assert_eq!(complete[1].log, "Failed password for admin from 198.51.100.7 port 22 ssh2");
+        // The reassembled line keeps the time of its first chunk.
+        assert_eq!(complete[1].time, "2025-02-20T10:30:45.1Z");
+        assert!(reassembler.finish().is_empty());
+    }
+
+    #[test]
+    fn reassembles_split_docker_lines_and_flushes_leftovers() {
+        let mut reassembler = ContainerReassembler::new();
+        let first = unwrap_container_line(r#"{"log":"part one, ","stream":"stdout","time":"t1"}"#).unwrap();
+        let second = unwrap_container_line(r#"{"log":"part two","stream":"stdout","time":"t2"}"#).unwrap();
+        assert!(first.partial && second.partial);
+
+        assert!(reassembler.push(first).is_none());
+        assert!(reassembler.push(second).is_none());
+
+        let leftovers = reassembler.finish();
+        assert_eq!(leftovers.len(), 1);
+        assert_eq!(leftovers[0].log, "part one, part two");
+    }
+}
@@ -0,0 +1,227 @@
+// Parse diagnostics
+// When a line is rejected, every structured parser is replayed as a sequence
+// of small anchored steps. The parser that consumed the most input before a
+// step failed is reported as the closest match, together with the column and
+// what it expected there.
+
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// Why a line was not recognized
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    /// Parser that got furthest (`apache`, `json`, `syslog`, `generic`), or
+    /// `none` when no parser matched the start of the line
+    pub parser: &'static str,
+    /// 1-based character column where that parser stopped matching
+    pub column: usize,
+    /// What the parser expected at that column
+    pub expected: String,
+}
+
+impl Rejection {
+    /// Aggregation key: `unrecognized_format` when no parser matched anything,
+    /// otherwise `malformed_<parser>`
+    pub fn category(&self) -> String {
+        if self.column <= 1 {
+            "unrecognized_format".to_string()
+        } else {
+            format!("malformed_{}", self.parser)
+        }
+    }
+
+    pub fn error_type(&self) -> String {
+        if self.column <= 1 {
+            return "Unrecognized format".to_string();
+        }
+        match self.parser {
+            "apache" => "Malformed Apache/nginx line",
+            "json" => "Malformed JSON",
+            "syslog" => "Malformed syslog line",
+            _ => "Malformed timestamped line",
+        }
+        .to_string()
+    }
+
+    pub fn suggestion(&self) -> String {
+        if self.column <= 1 {
+            return "Use a supported format (Apache/nginx combined, JSON lines, syslog, \
+                    or timestamped text) or disable strict mode"
+                .to_string();
+        }
+        match self.parser {
+            "apache" => "Expected: IP - - [dd/Mon/yyyy:HH:MM:SS +zzzz] \"METHOD /path HTTP/1.1\" status size \"referer\" \"user-agent\"",
+            "json" => "Fix the JSON syntax; each line must be one complete JSON object",
+            "syslog" => "Expected: Mon dd HH:MM:SS host process[pid]: message",
+            _ => "Expected: YYYY-MM-DD HH:MM:SS [LEVEL] message",
+        }
+        .to_string()
+    }
+}
+
+/// One anchored step of a format: what it looks like and how to describe it
+struct Step {
+    expected: &'static str,
+    pattern: &'static str,
+}
+
+const APACHE_STEPS: [Step; 17] = [
+    Step { expected: "client IP address", pattern: r"(?:\d{1,3}(?:\.\d{1,3}){3}|[0-9A-Fa-f]*:[0-9A-Fa-f:.]+)" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "identity '-'", pattern: r"-" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "user '-'", pattern: r"-" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "bracketed timestamp [dd/Mon/yyyy:HH:MM:SS +zzzz]", pattern: r"\[[^\]]*\]" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "quoted request \"METHOD /path HTTP/x.x\"", pattern: r#""[A-Za-z]+\s+.*?\sHTTP[^"]*""# },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "numeric status code", pattern: r"\d+" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "response size or '-'", pattern: r"(?:\d+|-)" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "quoted referer", pattern: r#""[^"]*""# },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "quoted user agent", pattern: r#""[^"]*""# },
+];
+
+const SYSLOG_STEPS: [Step; 6] = [
+    Step { expected: "syslog timestamp (e.g. 'Feb 20 10:30:45')", pattern: r"[A-Z][a-z]{2}\s+\d{1,2}\s+\d{2}:\d{2}:\d{2}" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "hostname", pattern: r"\S+" },
+    Step { expected: "space", pattern: r"\s+" },
+    Step { expected: "process tag (e.g. 'sshd[1234]:')", pattern: r"[A-Za-z0-9_./-]+(?:\[\d+\])?:" },
+    Step { expected: "space and message", pattern: r"\s+.+" },
+];
+
+const GENERIC_STEPS: [Step; 3] = [
+    Step { expected: "timestamp (YYYY-MM-DD HH:MM:SS)", pattern: r"[\[\s]*\d{4}[-/]\d{2}[-/]\d{2}[\sT]+\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?" },
+    Step { expected: "space or ']' after the timestamp", pattern: r"[\]\s]+" },
+    Step { expected: "message", pattern: r".+" },
+];
+
+/// Explain why no structured parser accepted `line`.
+pub fn diagnose_line(line: &str) -> Rejection {
+    static APACHE: OnceLock<Vec<Regex>> = OnceLock::new();
+    static SYSLOG: OnceLock<Vec<Regex>> = OnceLock::new();
+    static GENERIC: OnceLock<Vec<Regex>> = OnceLock::new();
+
+    let candidates = [
+        walk("apache", line, &APACHE_STEPS, APACHE.get_or_init(|| compile(&APACHE_STEPS))),
+        diagnose_json(line),
+        walk("syslog", line, &SYSLOG_STEPS, SYSLOG.get_or_init(|| compile(&SYSLOG_STEPS))),
+        walk("generic", line, &GENERIC_STEPS, GENERIC.get_or_init(|| compile(&GENERIC_STEPS))),
+    ];
+
+    // Ties keep the earlier (more specific) parser; a line no parser could
+    // start on is not attributed to any of them
+    candidates
+        .into_iter()
+        .flatten()
+        .filter(|candidate| candidate.column > 1)
+        .fold(None, |best: Option<Rejection>, candidate| match best {
+            Some(b) if b.column >= candidate.column => Some(b),
+            _ => Some(candidate),
+        })
+        .unwrap_or(Rejection {
+            parser: "none",
+            column: 1,
+            expected: "a recognized log format (Apache/nginx, JSON, syslog or timestamped text)".to_string(),
+        })
+}
+
+fn compile(steps: &[Step]) -> Vec<Regex> {
+    steps
+        .iter()
+        .map(|s| Regex::new(&format!("^(?:{})", s.pattern)).expect("diagnostic step pattern"))
+        .collect()
+}
+
+/// Match the steps in order; report the first one that fails.
+/// Returns `None` when every step matches.
+fn walk(parser: &'static str, line: &str, steps: &[Step], regexes: &[Regex]) -> Option<Rejection> {
+    let mut pos = 0;
+    for (step, re) in steps.iter().zip(regexes) {
+        match re.find(&line[pos..]) {
+            Some(m) => pos += m.end(),
+            None => {
+                return Some(Rejection {
+                    parser,
+                    column: line[..pos].chars().count() + 1,
+                    expected: step.expected.to_string(),
+                });
+            }
+        }
+    }
+    None
+}
+
+fn diagnose_json(line: &str) -> Option<Rejection> {
+    let offset = line.len() - line.trim_start().len();
+    let trimmed = line.trim();
+    if !trimmed.starts_with('{') {
+        return Some(Rejection {
+            parser: "json",
+            column: 1,
+            expected: "'{' starting a JSON object".to_string(),
+        });
+    }
+
+    match serde_json::from_str::<Value>(trimmed) {
+        Ok(_) => None,
+        Err(e) => {
+            // serde_json reports a byte column; convert to characters
+            let byte_col = e.column().saturating_sub(1).min(trimmed.len());
+            let byte_col = (0..=byte_col).rev().find(|i| trimmed.is_char_boundary(*i)).unwrap_or(0);
+            let message = e.to_string();
+            let reason = message.split(" at line ").next().unwrap_or(&message);
+            let expected = format!("valid JSON ({})", reason);
+            Some(Rejection {
+                parser: "json",
+                column: line[..offset].chars().count() + trimmed[..byte_col].chars().count() + 1,
+                expected,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_apache_line_with_bad_status() {
+        let line = r#"203.0.113.10 - - [15/Dec/2025:17:19:00 +0000] "GET / HTTP/1.1" OK 15 "-" "curl""#;
+        let rejection = diagnose_line(line);
+        assert_eq!(rejection.parser, "apache");
+        assert_eq!(rejection.column, 64);
+        assert_eq!(rejection.expected, "numeric status code");
+        assert_eq!(rejection.category(), "malformed_apache");
+    }
+
+    #[test]
+    fn reports_truncated_json() {
+        let rejection = diagnose_line(r#"{"level":"error","message":"boom""#);
+        assert_eq!(rejection.parser, "json");
+        assert!(rejection.column > 1);
+        assert!(rejection.expected.contains("EOF"));
+    }
+
+    #[test]
+    fn reports_syslog_line_without_process_tag() {
+        let rejection = diagnose_line("Feb 20 10:30:45 web01 ");
+        assert_eq!(rejection.parser, "syslog");
+        assert_eq!(rejection.column, 23);
+        assert!(rejection.expected.starts_with("process tag"));
+    }
+
+    #[test]
+    fn free_text_is_unrecognized() {
+        let rejection = diagnose_line("hello world");
+        assert_eq!(rejection.column, 1);
+        assert_eq!(rejection.parser, "none");
+        assert_eq!(rejection.category(), "unrecognized_format");
+        assert_eq!(rejection.error_type(), "Unrecognized format");
+    }
+}
@@ -0,0 +1,225 @@
+// Firewall log parser
+// Handles packet-filter records, whether they arrive inside a syslog line or
+// on their own:
+//   netfilter/iptables: kernel: IPTABLES-DROP: IN=eth0 OUT= SRC=203.0.113.5 DST=10.0.0.2 PROTO=TCP SPT=51234 DPT=22
+//   UFW:                kernel: [ 1234.5678] [UFW BLOCK] IN=eth0 OUT= ... SRC=... DST=... PROTO=TCP SPT=... DPT=...
+//   pfSense filterlog:  filterlog[1234]: 5,,,1000000103,em0,match,block,in,4,0x0,,64,0,0,DF,6,tcp,60,203.0.113.5,10.0.0.2,51234,22,0,S,...
+
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::LogEntry;
+
+/// One packet-filter decision
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirewallEvent {
+    /// `iptables`, `ufw` or `pfsense`
+    pub format: &'static str,
+    /// `block`, `allow` or `log` (logged without a recognizable verdict)
+    pub action: String,
+    /// `in`, `out` or `fwd` when known
+    pub direction: Option<String>,
+    pub interface: Option<String>,
+    /// Lowercase protocol name (`tcp`, `udp`, `icmp`, ...)
+    pub protocol: Option<String>,
+    pub src_ip: String,
+    pub dst_ip: Option<String>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+}
+
+impl FirewallEvent {
+    /// Structured fields for `LogEntry::fields` (`firewall.*`)
+    pub fn fields(&self) -> BTreeMap<String, String> {
+        let mut fields = BTreeMap::new();
+        let mut set = |name: &str, value: Option<String>| {
+            if let Some(value) = value {
+                fields.insert(format!("firewall.{}", name), value);
+            }
+        };
+        set("format", Some(self.format.to_string()));
+        set("action", Some(self.action.clone()));
+        set("direction", self.direction.clone());
+        set("interface", self.interface.clone());
+        set("protocol", self.protocol.clone());
+        set("src_ip", Some(self.src_ip.clone()));
+        set("dst_ip", self.dst_ip.clone());
+        set("src_port", self.src_port.map(|p| p.to_string()));
+        set("dst_port", self.dst_port.map(|p| p.to_string()));
+        fields
+    }
+}
+
+/// Find a firewall record anywhere in `text` (a whole line or a message)
+pub fn parse_firewall_message(text: &str) -> Option<FirewallEvent> {
+    parse_filterlog(text).or_else(|| parse_netfilter(text))
+}
+
+/// Add `firewall.*` fields to an entry whose message is a firewall record,
+/// and make the packet source its IP address
+pub fn enrich(entry: &mut LogEntry) {
+    if let Some(event) = parse_firewall_message(&entry.message) {
+        entry.ip_address = Some(event.src_ip.clone());
+        entry.fields.extend(event.fields());
+    }
+}
+
+/// A bare firewall record with no syslog header (e.g. `dmesg` output).
+/// These carry no wall-clock time, so the timestamp is left empty.
+pub fn parse_firewall_line(line: &str) -> Option<LogEntry> {
+    let event = parse_firewall_message(line)?;
+    Some(LogEntry {
+        timestamp: String::new(),
+        level: if event.action == "block" { "WARN" } else { "INFO" }.to_string(),
+        ip_address: Some(event.src_ip.clone()),
+        username: None,
+        message: line.trim().to_string(),
+        fields: event.fields(),
+    })
+}
+
+/// netfilter `KEY=value` records as logged by iptables `-j LOG` and UFW
+fn parse_netfilter(text: &str) -> Option<FirewallEvent> {
+    static PAIR: OnceLock<Regex> = OnceLock::new();
+    let pair = PAIR.get_or_init(|| Regex::new(r"\b([A-Z]+)=(\S*)").expect("netfilter pair pattern"));
+
+    let start = text.find("IN=")?;
+    let mut values: BTreeMap<&str, &str> = BTreeMap::new();
+    for caps in pair.captures_iter(&text[start..]) {
+        if let (Some(key), Some(value)) = (caps.get(1), caps.get(2)) {
+            values.entry(key.as_str()).or_insert(value.as_str());
+        }
+    }
+    let src_ip = values.get("SRC").filter(|v| !v.is_empty())?.to_string();
+    values.get("PROTO")?;
+
+    let prefix = &text[..start];
+    let (format, action) = match ufw_action(prefix) {
+        Some(action) => ("ufw", action),
+        None => ("iptables", verdict(prefix)),
+    };
+    let non_empty = |key: &str| values.get(key).filter(|v| !v.is_empty()).map(|v| v.to_string());
+    let direction = match (non_empty("IN"), non_empty("OUT")) {
+        (Some(_), Some(_)) => Some("fwd".to_string()),
+        (Some(_), None) => Some("in".to_string()),
+        (None, Some(_)) => Some("out".to_string()),
+        (None, None) => None,
+    };
+
+    Some(FirewallEvent {
+        format,
+        action,
+        interface: non_empty("IN").or_else(|| non_empty("OUT")),
+        direction,
+        protocol: non_empty("PROTO").map(|p| p.to_lowercase()),
+        src_ip,
+        dst_ip: non_empty("DST"),
+        src_port: values.get("SPT").and_then(|p| p.parse().ok()),
+        dst_port: values.get("DPT").and_then(|p| p.parse().ok()),
+    })
+}
+
+/// `[UFW BLOCK]`, `[UFW ALLOW]`, `[UFW AUDIT]`, `[UFW LIMIT BLOCK]`
+fn ufw_action(prefix: &str) -> Option<String> {
+    let start = prefix.find("[UFW ")? + "[UFW ".len();
+    let end = start + prefix[start..].find(']')?;
+    Some(verdict(&prefix[start..end]))
+}
+
+/// Normalize the verdict in a log prefix such as `IPTABLES-DROP:` or `BLOCK`
+fn verdict(prefix: &str) -> String {
+    let prefix = prefix.to_lowercase();
+    if ["drop", "reject", "block", "deny"].iter().any(|w| prefix.contains(w)) {
+        "block"
+    } else if ["accept", "allow", "pass"].iter().any(|w| prefix.contains(w)) {
+        "allow"
+    } else {
+        "log"
+    }
+    .to_string()
+}
+
+/// pfSense/OPNsense `filterlog` CSV
+fn parse_filterlog(text: &str) -> Option<FirewallEvent> {
+    let start = text.find("filterlog")?;
+    let csv = text[start..].split_once(": ")?.1.trim();
+    let cols: Vec<&str> = csv.split(',').collect();
+    let col = |i: usize| cols.get(i).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    let action = match col(6)? {
+        "block" | "reject" => "block",
+        "pass" => "allow",
+        _ => "log",
+    };
+    // Column layout after the IP version depends on it
+    let (protocol, src, dst, ports) = match col(8)? {
+        "4" => (col(16), col(18)?, col(19), 20),
+        "6" => (col(12), col(15)?, col(16), 17),
+        _ => return None,
+    };
+    let protocol = protocol.map(str::to_lowercase);
+    let has_ports = matches!(protocol.as_deref(), Some("tcp" | "udp"));
+    let port = |i: usize| if has_ports { col(i).and_then(|p| p.parse().ok()) } else { None };
+
+    Some(FirewallEvent {
+        format: "pfsense",
+        action: action.to_string(),
+        direction: col(7).map(str::to_string),
+        interface: col(4).map(str::to_string),
+        protocol,
+        src_ip: src.to_string(),
+        dst_ip: dst.map(str::to_string),
+        src_port: port(ports),
+        dst_port: port(ports + 1),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_iptables_and_ufw_records() {
+        let iptables = "kernel: IPTABLES-DROP: IN=eth0 OUT= MAC=00:11 SRC=203.0.113.5 DST=10.0.0.2 LEN=60 TTL=52 PROTO=TCP SPT=51234 DPT=22 WINDOW=1024 SYN";
+        let event = parse_firewall_message(iptables).unwrap();
+        assert_eq!(event.format, "iptables");
+        assert_eq!(event.action, "block");
+        assert_eq!(event.direction.as_deref(), Some("in"));
+        assert_eq!(event.protocol.as_deref(), Some("tcp"));
+        assert_eq!(event.src_ip, "203.0.113.5");
+        assert_eq!(event.dst_ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(event.dst_port, Some(22));
+
+        let ufw = "[ 1234.5678] [UFW BLOCK] IN=eth0 OUT= SRC=198.51.100.7 DST=10.0.0.2 PROTO=UDP SPT=53 DPT=161 LEN=40";
+        let entry = parse_firewall_line(ufw).unwrap();
+        assert_eq!(entry.fields["firewall.format"], "ufw");
+        assert_eq!(entry.fields["firewall.action"], "block");
+        assert_eq!(entry.fields["firewall.dst_port"], "161");
+        assert_eq!(entry.ip_address.as_deref(), Some("198.51.100.7"));
+        assert_eq!(entry.level, "WARN");
+    }
+
+    #[test]
+    fn parses_pfsense_filterlog() {
+        let v4 = "pfsense filterlog[4321]: 5,,,1000000103,em0,match,block,in,4,0x0,,64,0,0,DF,6,tcp,60,203.0.113.5,10.0.0.2,51234,3389,0,S,1,,64240,,mss";
+        let event = parse_firewall_message(v4).unwrap();
+        assert_eq!(event.format, "pfsense");
+        assert_eq!(event.action, "block");
+        assert_eq!(event.interface.as_deref(), Some("em0"));
+        assert_eq!(event.src_ip, "203.0.113.5");
+        assert_eq!(event.dst_port, Some(3389));
+
+        let v6 = "filterlog: 7,,,1000000105,em0,match,pass,out,6,0x00,0x00000,64,udp,17,80,2001:db8::1,2001:db8::2,5353,53,80";
+        let event = parse_firewall_message(v6).unwrap();
+        assert_eq!(event.action, "allow");
+        assert_eq!(event.dst_ip.as_deref(), Some("2001:db8::2"));
+        assert_eq!(event.dst_port, Some(53));
+    }
+
+    #[test]
+    fn ignores_non_firewall_text() {
+        assert!(parse_firewall_message("GET /index.html?IN=1 HTTP/1.1").is_none());
+        assert!(parse_firewall_message("sshd: Failed password for root").is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use regex::Regex;
+use std::collections::BTreeMap;
 use crate::LogEntry;
 
 /// Parse any log format with multiple fallback strategies
@@ -12,18 +13,25 @@ pub fn parse_generic_log(line: &str) -> Option<LogEntry> {
         return None;
     }
 
-    // Strategy 1: Try structured format (timestamp + level + message)
-    if let Some(entry) = try_structured_format(line) {
+    // Strategies 1 & 2: timestamped formats
+    if let Some(entry) = parse_generic_structured(line) {
         return Some(entry);
     }
 
-    // Strategy 2: Try timestamp + message format
-    if let Some(entry) = try_timestamp_message_format(line) {
+    // Strategy 3: Minimal parsing - extract what we can from any text
+    Some(try_minimal_parsing(line))
+}
+
+/// Only the timestamped strategies of `parse_generic_log` (no minimal fallback).
+/// Used by strict mode, where free text is not a recognized format.
+pub(crate) fn parse_generic_structured(line: &str) -> Option<LogEntry> {
+    // Strategy 1: Try structured format (timestamp + level + message)
+    if let Some(entry) = try_structured_format(line) {
         return Some(entry);
     }
 
-    // Strategy 3: Minimal parsing - extract what we can from any text
-    Some(try_minimal_parsing(line))
+    // Strategy 2: Try timestamp + message format
+    try_timestamp_message_format(line)
 }
 
 /// Try to parse structured log format: YYYY-MM-DD HH:MM:SS [LEVEL] message
@@ -60,6 +68,7 @@ fn try_structured_format(line: &str) -> Option<LogEntry> {
             ip_address,
             username,
             message,
+            fields: BTreeMap::new(),
         });
     }
 
@@ -90,6 +99,7 @@ fn try_timestamp_message_format(line: &str) -> Option<LogEntry> {
             ip_address,
             username,
             message,
+            fields: BTreeMap::new(),
         });
     }
 
@@ -112,6 +122,7 @@ fn try_minimal_parsing(line: &str) -> LogEntry {
         ip_address,
         username,
         message,
+        fields: BTreeMap::new(),
     }
 }
 
@@ -126,7 +137,9 @@ pub(crate) fn extract_ip_address(text: &str) -> Option<String> {
 /// Extract username from message text
 pub(crate) fn extract_username(text: &str) -> Option<String> {
     // Common patterns: "user: username", "user=username", "username@", etc.
+    // "Failed login for admin" comes first so "login" does not capture "for".
     let patterns = [
+        r"(?i)(?:login|password|authentication)\s+(?:failed\s+)?for\s+(?:invalid user\s+)?([a-zA-Z0-9_.-]+)",
         r"user[:\s=]+([a-zA-Z0-9_-]+)",
         r"username[:\s=]+([a-zA-Z0-9_-]+)",
         r"([a-zA-Z0-9_-]+)@",
@@ -134,15 +147,14 @@ pub(crate) fn extract_username(text: &str) -> Option<String> {
     ];
 
     for pattern in &patterns {
-        if let Ok(re) = Regex::new(pattern) {
-            if let Some(caps) = re.captures(text) {
-                if let Some(m) = caps.get(1) {
-                    let username = m.as_str().to_string();
-                    // Filter out common false positives
-                    if username != "root" && username.len() > 1 {
-                        return Some(username);
-                    }
-                }
+        if let Ok(re) = Regex::new(pattern)
+            && let Some(caps) = re.captures(text)
+            && let Some(m) = caps.get(1)
+        {
+            let username = m.as_str().to_string();
+            // Filter out common false positives
+            if username != "root" && username.len() > 1 {
+                return Some(username);
             }
         }
     }
@@ -0,0 +1,186 @@
+// journald JSON export parser
+// Handles records produced by `journalctl -o json`, e.g.
+//   {"__REALTIME_TIMESTAMP":"1708425045123456","PRIORITY":"3","_HOSTNAME":"web01",
+//    "SYSLOG_IDENTIFIER":"sshd","_PID":"1234","MESSAGE":"Failed password for root from ..."}
+// Every value is a string; MESSAGE is an array of bytes when it is not valid UTF-8.
+
+use chrono::DateTime;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::LogEntry;
+use super::generic::{extract_ip_address, extract_username, infer_log_level};
+use super::syslog::extract_syslog_username;
+
+/// Keys that only appear in journald exports (trusted fields and addresses)
+const JOURNALD_MARKER_KEYS: [&str; 4] = [
+    "__REALTIME_TIMESTAMP",
+    "__CURSOR",
+    "SYSLOG_IDENTIFIER",
+    "_SYSTEMD_UNIT",
+];
+
+/// Identifiers whose messages follow sshd/auth.log conventions
+const AUTH_IDENTIFIERS: [&str; 2] = ["sshd", "sudo"];
+
+/// Check whether a JSON object looks like a `journalctl -o json` record.
+pub fn is_journald_record(obj: &Map<String, Value>) -> bool {
+    obj.contains_key("MESSAGE") && JOURNALD_MARKER_KEYS.iter().any(|k| obj.contains_key(*k))
+}
+
+/// Convert a journald JSON record into a `LogEntry`.
+///
+/// The message is rendered like the syslog parser does (`host ident: text`)
+/// so detections behave the same on journald exports and on auth.log. For
+/// sshd and sudo records the auth.log username extraction is applied.
+pub fn parse_journald_record(obj: &Map<String, Value>) -> Option<LogEntry> {
+    if !is_journald_record(obj) {
+        return None;
+    }
+
+    let body = obj.get("MESSAGE").and_then(journal_value_to_string).unwrap_or_default();
+    let hostname = string_field(obj, "_HOSTNAME");
+    let identifier = string_field(obj, "SYSLOG_IDENTIFIER").or_else(|| string_field(obj, "_COMM"));
+    let pid = string_field(obj, "_PID").or_else(|| string_field(obj, "SYSLOG_PID"));
+    let priority = string_field(obj, "PRIORITY");
+
+    let timestamp = string_field(obj, "__REALTIME_TIMESTAMP")
+        .or_else(|| string_field(obj, "_SOURCE_REALTIME_TIMESTAMP"))
+        .and_then(|micros| micros_to_rfc3339(&micros))
+        .unwrap_or_default();
+
+    let level = priority
+        .as_deref()
+        .and_then(priority_to_level)
+        .map(|l| l.to_string())
+        .unwrap_or_else(|| infer_log_level(&body));
+
+    let username = match identifier.as_deref() {
+        Some(ident) if AUTH_IDENTIFIERS.contains(&ident) => extract_syslog_username(&body),
+        _ => extract_username(&body),
+    };
+
+    let message = match (&hostname, &identifier) {
+        (Some(host), Some(ident)) => format!("{} {}: {}", host, ident, body),
+        (None, Some(ident)) => format!("{}: {}", ident, body),
+        _ => body.clone(),
+    };
+
+    let mut fields = BTreeMap::new();
+    let optional_fields = [
+        ("journald.hostname", hostname),
+        ("journald.identifier", identifier),
+        ("journald.pid", pid),
+        ("journald.priority", priority),
+        ("journald.unit", string_field(obj, "_SYSTEMD_UNIT")),
+    ];
+    for (name, value) in optional_fields {
+        if let Some(value) = value {
+            fields.insert(name.to_string(), value);
+        }
+    }
+
+    Some(LogEntry {
+        timestamp,
+        level,
+        ip_address: extract_ip_address(&body),
+        username,
+        message,
+        fields,
+    })
+}
+
+/// Map a syslog PRIORITY (0 = emerg ... 7 = debug) onto the common level set.
+fn priority_to_level(priority: &str) -> Option<&'static str> {
+    match priority.trim() {
+        "0" | "1" | "2" => Some("CRITICAL"),
+        "3" => Some("ERROR"),
+        "4" => Some("WARN"),
+        "5" | "6" => Some("INFO"),
+        "7" => Some("DEBUG"),
+        _ => None,
+    }
+}
+
+/// Convert a journald realtime timestamp (microseconds since the epoch) to RFC 3339 UTC.
+fn micros_to_rfc3339(micros: &str) -> Option<String> {
+    let micros: i64 = micros.trim().parse().ok()?;
+    DateTime::from_timestamp_micros(micros).map(|dt| dt.to_rfc3339())
+}
+
+fn string_field(obj: &Map<String, Value>, key: &str) -> Option<String> {
+    obj.get(key).and_then(journal_value_to_string).filter(|s| !s.is_empty())
+}
+
+/// Render a journald field value. Binary (non-UTF-8) fields are exported as
+/// an array of byte values and are decoded lossily.
+fn journal_value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(items) => {
+            let bytes: Vec<u8> = items
+                .iter()
+                .filter_map(|b| b.as_u64())
+                .filter_map(|b| u8::try_from(b).ok())
+                .collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(json: &str) -> Map<String, Value> {
+        serde_json::from_str::<Value>(json).unwrap().as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn parses_sshd_failure_with_priority_and_realtime() {
+        let obj = record(r#"{"__REALTIME_TIMESTAMP":"1740047445123456","PRIORITY":"6","_HOSTNAME":"web01","SYSLOG_IDENTIFIER":"sshd","_PID":"1234","_SYSTEMD_UNIT":"ssh.service","MESSAGE":"Failed password for invalid user admin from 203.0.113.5 port 22 ssh2"}"#);
+        let entry = parse_journald_record(&obj).expect("should parse");
+        assert_eq!(entry.timestamp, "2025-02-20T10:30:45.123456+00:00");
+        assert_eq!(entry.level, "INFO");
+        assert_eq!(entry.username, Some("admin".to_string()));
+        assert_eq!(entry.ip_address, Some("203.0.113.5".to_string()));
+        assert_eq!(entry.message, "web01 sshd: Failed password for invalid user admin from 203.0.113.5 port 22 ssh2");
+        assert_eq!(entry.fields.get("journald.pid"), Some(&"1234".to_string()));
+        assert_eq!(entry.fields.get("journald.unit"), Some(&"ssh.service".to_string()));
+    }
+
+    #[test]
+    fn maps_priorities_to_levels() {
+        assert_eq!(priority_to_level("0"), Some("CRITICAL"));
+        assert_eq!(priority_to_level("2"), Some("CRITICAL"));
+        assert_eq!(priority_to_level("3"), Some("ERROR"));
+        assert_eq!(priority_to_level("4"), Some("WARN"));
+        assert_eq!(priority_to_level("6"), Some("INFO"));
+        assert_eq!(priority_to_level("7"), Some("DEBUG"));
+        assert_eq!(priority_to_level("x"), None);
+    }
+
+    #[test]
+    fn sudo_records_use_auth_log_username_extraction() {
+        let obj = record(r#"{"__REALTIME_TIMESTAMP":"1740047445000000","PRIORITY":"5","SYSLOG_IDENTIFIER":"sudo","MESSAGE":"alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/bin/ls"}"#);
+        let entry = parse_journald_record(&obj).expect("should parse");
+        assert_eq!(entry.username, Some("alice".to_string()));
+        assert_eq!(entry.message, "sudo: alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/bin/ls");
+    }
+
+    #[test]
+    fn decodes_binary_messages() {
+        let obj = record(r#"{"__CURSOR":"s=abc","SYSLOG_IDENTIFIER":"app","PRIORITY":"3","MESSAGE":[101,114,114,111,114]}"#);
+        let entry = parse_journald_record(&obj).expect("should parse");
+        assert_eq!(entry.message, "app: error");
+        assert_eq!(entry.level, "ERROR");
+    }
+
+    #[test]
+    fn rejects_non_journald_objects() {
+        let obj = record(r#"{"level":"error","message":"boom","MESSAGE":"not journald"}"#);
+        assert!(parse_journald_record(&obj).is_none());
+    }
+}
@@ -0,0 +1,455 @@
+// JSON field mapping profiles
+// Structured loggers disagree on where they put things: ECS nests fields
+// (`source.ip`, `log.level`), bunyan/pino use numeric levels, Serilog's compact
+// format prefixes reserved keys with '@'. A profile maps dotted or JSON-pointer
+// paths onto the `LogEntry` fields, plus any number of extra structured fields.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Names of the built-in profiles accepted by `JsonProfile::builtin`
+pub const BUILTIN_PROFILES: [&str; 5] = ["ecs", "bunyan", "pino", "zap", "serilog"];
+
+/// Field mapping for one JSON log source.
+///
+/// Every mapping is a list of paths tried in order; the first one present in
+/// the record wins. A path is either dotted (`ctx.client.addr`, array indices
+/// allowed as `items.0.id`) or an RFC 6901 JSON pointer (`/ctx/client/addr`).
+/// Dotted paths also match flattened keys such as a literal `"log.level"`.
+///
+/// Profiles can be loaded from TOML or JSON:
+///
+/// ```toml
+/// name = "checkout-service"
+/// message = "msg"
+/// ip = ["ctx.client.addr", "/ctx/forwarded_for/0"]
+/// user = "ctx.user.login"
+///
+/// [fields]
+/// "http.method" = "ctx.request.method"
+/// "http.path" = "ctx.request.path"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JsonProfile {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub timestamp: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub level: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub message: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub ip: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub user: Vec<String>,
+    /// Level to use when the record has none (Serilog omits "Information")
+    #[serde(default)]
+    pub default_level: Option<String>,
+    /// Additional structured fields: target field name -> paths
+    #[serde(default, deserialize_with = "field_paths")]
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl JsonProfile {
+    /// Look up a built-in profile by name (`ecs`, `bunyan`, `pino`, `zap`, `serilog`).
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ecs" | "elastic" => Some(ecs()),
+            "bunyan" => Some(bunyan()),
+            "pino" => Some(pino()),
+            "zap" => Some(zap()),
+            "serilog" | "clef" => Some(serilog()),
+            _ => None,
+        }
+    }
+
+    /// Resolve a profile specification: a built-in name, a path to a TOML or
+    /// JSON mapping file, or an inline TOML/JSON mapping. Reads local files,
+    /// so only for specs from the operator (CLI); see `from_inline`.
+    pub fn from_spec(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        if let Some(profile) = Self::builtin(spec) {
+            return Ok(profile);
+        }
+
+        let path = Path::new(spec);
+        if path.is_file() {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read field mapping {}: {}", spec, e))?;
+            let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+            let mut profile = if is_json { Self::from_json_str(&text)? } else { Self::from_toml_str(&text)? };
+            if profile.name.is_empty() {
+                profile.name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            }
+            return Ok(profile);
+        }
+
+        Self::inline_mapping(spec).unwrap_or_else(|| {
+            Err(format!(
+                "Unknown JSON profile '{}'. Use one of {} or a path to a TOML/JSON mapping file",
+                spec,
+                BUILTIN_PROFILES.join(", ")
+            ))
+        })
+    }
+
+    /// Resolve a profile from untrusted input (API clients): a built-in name
+    /// or an inline TOML/JSON mapping. Never touches the filesystem.
+    pub fn from_inline(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        if let Some(profile) = Self::builtin(spec) {
+            return Ok(profile);
+        }
+        Self::inline_mapping(spec).unwrap_or_else(|| {
+            Err(format!(
+                "Unknown JSON profile '{}'. Use one of {} or an inline TOML/JSON mapping",
+                spec,
+                BUILTIN_PROFILES.join(", ")
+            ))
+        })
+    }
+
+    /// Parse `spec` as an inline mapping when it looks like one
+    fn inline_mapping(spec: &str) -> Option<Result<Self, String>> {
+        if spec.starts_with('{') {
+            Some(Self::from_json_str(spec))
+        } else if spec.contains('=') {
+            Some(Self::from_toml_str(spec))
+        } else {
+            None
+        }
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| format!("Invalid TOML field mapping: {}", e))
+    }
+
+    pub fn from_json_str(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("Invalid JSON field mapping: {}", e))
+    }
+
+    /// Guess the built-in profile for a record from its shape.
+    pub fn detect(record: &Value) -> Option<Self> {
+        let obj = record.as_object()?;
+        let has = |path: &str| lookup_path(record, path).is_some();
+        let numeric_level = obj.get("level").is_some_and(|v| v.is_number());
+
+        if has("ecs.version") || (obj.contains_key("@timestamp") && has("log.level")) {
+            Some(ecs())
+        } else if obj.contains_key("v") && obj.contains_key("hostname") && numeric_level && obj.contains_key("msg") {
+            Some(bunyan())
+        } else if numeric_level && obj.get("time").is_some_and(|v| v.is_number()) && obj.contains_key("msg") {
+            Some(pino())
+        } else if obj.contains_key("ts") && obj.contains_key("msg") && (obj.contains_key("caller") || obj.contains_key("logger")) {
+            Some(zap())
+        } else if obj.contains_key("@t") && (obj.contains_key("@m") || obj.contains_key("@mt")) {
+            Some(serilog())
+        } else {
+            None
+        }
+    }
+
+    /// First value present in the record for any of the given paths.
+    pub fn resolve<'a>(record: &'a Value, paths: &[String]) -> Option<&'a Value> {
+        paths
+            .iter()
+            .filter_map(|path| lookup_path(record, path))
+            .find(|v| !v.is_null())
+    }
+
+    /// Extract every configured extra field from the record, rendered as strings.
+    pub fn extract_fields(&self, record: &Value) -> BTreeMap<String, String> {
+        self.fields
+            .iter()
+            .filter_map(|(name, paths)| {
+                Self::resolve(record, paths).map(|v| (name.clone(), render_value(v)))
+            })
+            .collect()
+    }
+}
+
+/// Resolve a dotted path or JSON pointer against a JSON value.
+pub fn lookup_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.starts_with('/') {
+        return value.pointer(path);
+    }
+    lookup_dotted(value, path)
+}
+
+/// Dotted lookup that tolerates keys which themselves contain dots: at each
+/// level the longest literal key is tried first, then every split point.
+fn lookup_dotted<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(obj) => {
+            if let Some(found) = obj.get(path) {
+                return Some(found);
+            }
+        }
+        Value::Array(items) => {
+            if let Ok(index) = path.parse::<usize>() {
+                return items.get(index);
+            }
+        }
+        _ => return None,
+    }
+
+    path.match_indices('.').find_map(|(idx, _)| {
+        let (head, rest) = (&path[..idx], &path[idx + 1..]);
+        let child = match value {
+            Value::Object(obj) => obj.get(head),
+            Value::Array(items) => head.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }?;
+        lookup_dotted(child, rest)
+    })
+}
+
+/// Render a JSON value as a flat string (objects and arrays as compact JSON).
+pub fn render_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn paths(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+fn extra(list: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+    list.iter().map(|(name, p)| (name.to_string(), paths(p))).collect()
+}
+
+/// Elastic Common Schema (Filebeat, ecs-logging libraries)
+fn ecs() -> JsonProfile {
+    JsonProfile {
+        name: "ecs".to_string(),
+        timestamp: paths(&["@timestamp"]),
+        level: paths(&["log.level"]),
+        message: paths(&["message", "event.original"]),
+        ip: paths(&["source.ip", "client.ip"]),
+        user: paths(&["user.name", "user.id"]),
+        default_level: None,
+        fields: extra(&[
+            ("http.method", &["http.request.method"]),
+            ("http.path", &["url.path", "url.original"]),
+            ("http.status", &["http.response.status_code"]),
+            ("http.user_agent", &["user_agent.original"]),
+            ("http.referer", &["http.request.referrer"]),
+            ("http.size", &["http.response.body.bytes"]),
+            ("source.port", &["source.port", "client.port"]),
+            ("destination.ip", &["destination.ip", "server.ip"]),
+            ("destination.port", &["destination.port", "server.port"]),
+            ("event.action", &["event.action"]),
+            ("event.outcome", &["event.outcome"]),
+            ("event.category", &["event.category"]),
+            ("host.name", &["host.name", "host.hostname"]),
+            ("service.name", &["service.name"]),
+            ("error.message", &["error.message"]),
+        ]),
+    }
+}
+
+/// node-bunyan (numeric levels, `v` schema version)
+fn bunyan() -> JsonProfile {
+    JsonProfile {
+        name: "bunyan".to_string(),
+        timestamp: paths(&["time"]),
+        level: paths(&["level"]),
+        message: paths(&["msg"]),
+        ip: paths(&["req.remoteAddress", "remoteAddress", "ip"]),
+        user: paths(&["user", "username", "req.user"]),
+        default_level: None,
+        fields: extra(&[
+            ("logger", &["name"]),
+            ("host.name", &["hostname"]),
+            ("process.pid", &["pid"]),
+            ("http.method", &["req.method"]),
+            ("http.path", &["req.url"]),
+            ("http.status", &["res.statusCode"]),
+            ("http.user_agent", &["req.headers.user-agent"]),
+            ("error.message", &["err.message"]),
+        ]),
+    }
+}
+
+/// pino (numeric levels, epoch-millisecond `time`)
+fn pino() -> JsonProfile {
+    JsonProfile {
+        name: "pino".to_string(),
+        timestamp: paths(&["time"]),
+        level: paths(&["level"]),
+        message: paths(&["msg"]),
+        ip: paths(&["req.remoteAddress", "req.ip", "ip"]),
+        user: paths(&["user", "username", "req.user"]),
+        default_level: None,
+        fields: extra(&[
+            ("host.name", &["hostname"]),
+            ("process.pid", &["pid"]),
+            ("http.method", &["req.method"]),
+            ("http.path", &["req.url"]),
+            ("http.status", &["res.statusCode"]),
+            ("http.user_agent", &["req.headers.user-agent"]),
+            ("http.response_time_ms", &["responseTime"]),
+            ("error.message", &["err.message"]),
+        ]),
+    }
+}
+
+/// uber-go/zap production encoder
+fn zap() -> JsonProfile {
+    JsonProfile {
+        name: "zap".to_string(),
+        timestamp: paths(&["ts"]),
+        level: paths(&["level"]),
+        message: paths(&["msg"]),
+        ip: paths(&["ip", "remote_addr", "client_ip", "remoteAddr"]),
+        user: paths(&["user", "username"]),
+        default_level: None,
+        fields: extra(&[
+            ("logger", &["logger"]),
+            ("caller", &["caller"]),
+            ("error.message", &["error"]),
+            ("error.stack", &["stacktrace"]),
+            ("http.method", &["method"]),
+            ("http.path", &["path", "url"]),
+            ("http.status", &["status"]),
+        ]),
+    }
+}
+
+/// Serilog compact JSON (CLEF) and the classic JsonFormatter layout
+fn serilog() -> JsonProfile {
+    JsonProfile {
+        name: "serilog".to_string(),
+        timestamp: paths(&["@t", "Timestamp"]),
+        level: paths(&["@l", "Level"]),
+        message: paths(&["@m", "RenderedMessage", "@mt", "MessageTemplate"]),
+        ip: paths(&["ClientIp", "RemoteIpAddress", "Properties.ClientIp", "Properties.RemoteIpAddress"]),
+        user: paths(&["UserName", "Properties.UserName"]),
+        default_level: Some("INFO".to_string()),
+        fields: extra(&[
+            ("logger", &["SourceContext", "Properties.SourceContext"]),
+            ("event.id", &["@i", "EventId.Id", "Properties.EventId.Id"]),
+            ("error.message", &["@x", "Exception"]),
+            ("http.method", &["RequestMethod", "Properties.RequestMethod"]),
+            ("http.path", &["RequestPath", "Properties.RequestPath"]),
+            ("http.status", &["StatusCode", "Properties.StatusCode"]),
+        ]),
+    }
+}
+
+/// Accept either a single path or a list of paths
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for Vec<String> {
+    fn from(value: OneOrMany) -> Self {
+        match value {
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    OneOrMany::deserialize(deserializer).map(Into::into)
+}
+
+fn field_paths<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Vec<String>>, D::Error> {
+    let raw = BTreeMap::<String, OneOrMany>::deserialize(deserializer)?;
+    Ok(raw.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_nested_flattened_and_pointer_paths() {
+        let record: Value = serde_json::from_str(
+            r#"{"log.level":"warn","source":{"ip":"198.51.100.4"},"ctx":{"client":{"addr":"10.1.2.3"},"hops":["a","b"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(lookup_path(&record, "log.level"), Some(&Value::from("warn")));
+        assert_eq!(lookup_path(&record, "source.ip"), Some(&Value::from("198.51.100.4")));
+        assert_eq!(lookup_path(&record, "/ctx/client/addr"), Some(&Value::from("10.1.2.3")));
+        assert_eq!(lookup_path(&record, "ctx.hops.1"), Some(&Value::from("b")));
+        assert_eq!(lookup_path(&record, "ctx.missing"), None);
+    }
+
+    #[test]
+    fn builtin_profiles_resolve_by_name() {
+        for name in BUILTIN_PROFILES {
+            let profile = JsonProfile::builtin(name).expect("built-in profile");
+            assert_eq!(profile.name, name);
+        }
+        assert!(JsonProfile::builtin("log4j").is_none());
+    }
+
+    #[test]
+    fn loads_toml_mapping_with_single_or_multiple_paths() {
+        let profile = JsonProfile::from_spec(
+            r#"
+            name = "checkout"
+            message = "msg"
+            ip = ["ctx.client.addr", "/ctx/forwarded_for/0"]
+
+            [fields]
+            "http.method" = "ctx.request.method"
+            "#,
+        )
+        .expect("valid mapping");
+        assert_eq!(profile.name, "checkout");
+        assert_eq!(profile.message, vec!["msg".to_string()]);
+        assert_eq!(profile.ip.len(), 2);
+        assert_eq!(profile.fields["http.method"], vec!["ctx.request.method".to_string()]);
+    }
+
+    #[test]
+    fn loads_json_mapping() {
+        let profile = JsonProfile::from_spec(r#"{"name":"app","user":["actor.login"]}"#).expect("valid mapping");
+        assert_eq!(profile.user, vec!["actor.login".to_string()]);
+    }
+
+    #[test]
+    fn inline_profiles_never_read_files() {
+        let mapping = std::env::temp_dir().join(format!("logr-profile-{}.toml", std::process::id()));
+        std::fs::write(&mapping, "message = \"secret\"\n").unwrap();
+        let spec = mapping.display().to_string();
+        assert_eq!(JsonProfile::from_spec(&spec).unwrap().message, vec!["secret".to_string()]);
+        let err = JsonProfile::from_inline(&spec).unwrap_err();
+        assert!(err.contains("inline TOML/JSON mapping") && !err.contains("secret"), "{}", err);
+        std::fs::remove_file(&mapping).ok();
+
+        assert_eq!(JsonProfile::from_inline("ecs").unwrap().name, "ecs");
+        assert_eq!(JsonProfile::from_inline(r#"{"user":["actor.login"]}"#).unwrap().user, vec!["actor.login".to_string()]);
+    }
+
+    #[test]
+    fn rejects_unknown_profile_names() {
+        let err = JsonProfile::from_spec("log4j").unwrap_err();
+        assert!(err.contains("ecs, bunyan, pino, zap, serilog"));
+    }
+
+    #[test]
+    fn detects_profiles_from_record_shape() {
+        let detect = |json: &str| JsonProfile::detect(&serde_json::from_str(json).unwrap()).map(|p| p.name);
+        assert_eq!(detect(r#"{"@timestamp":"2025-02-20T10:30:45Z","log":{"level":"info"},"message":"m"}"#), Some("ecs".to_string()));
+        assert_eq!(detect(r#"{"v":0,"hostname":"h","pid":1,"level":30,"msg":"m","time":"2025-02-20T10:30:45Z"}"#), Some("bunyan".to_string()));
+        assert_eq!(detect(r#"{"level":50,"time":1740047445000,"pid":1,"msg":"m"}"#), Some("pino".to_string()));
+        assert_eq!(detect(r#"{"level":"error","ts":1740047445.1,"caller":"main.go:10","msg":"m"}"#), Some("zap".to_string()));
+        assert_eq!(detect(r#"{"@t":"2025-02-20T10:30:45Z","@mt":"User {UserName} logged in","UserName":"bob"}"#), Some("serilog".to_string()));
+        assert_eq!(detect(r#"{"timestamp":"2025-02-20T10:30:45Z","level":"error","message":"m"}"#), None);
+    }
+}
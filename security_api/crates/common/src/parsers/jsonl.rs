@@ -3,9 +3,12 @@
 // (e.g. bunyan, pino, zap, logrus, CloudWatch/Datadog exports).
 
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 use crate::LogEntry;
 use super::generic::{extract_ip_address, extract_username, infer_log_level};
+use super::journald::parse_journald_record;
+use super::json_profile::{JsonProfile, render_value};
 
 const TIMESTAMP_KEYS: [&str; 6] = ["timestamp", "@timestamp", "time", "ts", "datetime", "date"];
 const LEVEL_KEYS: [&str; 5] = ["level", "severity", "loglevel", "log_level", "lvl"];
@@ -17,9 +20,21 @@ const USER_KEYS: [&str; 3] = ["user", "username", "account"];
 ///
 /// Field names are matched against common conventions (`timestamp`/`@timestamp`,
 /// `level`/`severity`, `message`/`msg`, `ip`/`remote_addr`, ...). Unknown or
-/// missing fields fall back to inference from the message text. Returns `None`
-/// for lines that are not a JSON object.
+/// missing fields fall back to inference from the message text. Records from
+/// `journalctl -o json` are recognized and mapped by the journald parser, and
+/// records shaped like a built-in profile (ECS, bunyan, pino, zap, Serilog) are
+/// mapped with that profile.
+/// Returns `None` for lines that are not a JSON object.
 pub fn parse_json_line(line: &str) -> Option<LogEntry> {
+    parse_json_line_with(line, None)
+}
+
+/// Parse a JSON-object log line using an explicit field mapping profile.
+///
+/// Paths in the profile are tried before the conventional top-level keys, and
+/// every extra field the profile maps is carried into `LogEntry::fields`.
+/// Without a profile this behaves like `parse_json_line`.
+pub fn parse_json_line_with(line: &str, profile: Option<&JsonProfile>) -> Option<LogEntry> {
     let trimmed = line.trim();
     if !trimmed.starts_with('{') || !trimmed.ends_with('}') {
         return None;
@@ -28,46 +43,73 @@ pub fn parse_json_line(line: &str) -> Option<LogEntry> {
     let value: Value = serde_json::from_str(trimmed).ok()?;
     let obj = value.as_object()?;
 
-    let timestamp = TIMESTAMP_KEYS
-        .iter()
-        .find_map(|k| obj.get(*k))
+    if profile.is_none()
+        && let Some(entry) = parse_journald_record(obj)
+    {
+        return Some(entry);
+    }
+
+    let detected;
+    let profile = match profile {
+        Some(p) => Some(p),
+        None => {
+            detected = JsonProfile::detect(&value);
+            detected.as_ref()
+        }
+    };
+    let mapped = |select: fn(&JsonProfile) -> &Vec<String>| {
+        profile.and_then(|p| JsonProfile::resolve(&value, select(p)))
+    };
+    let conventional = |keys: &[&str]| keys.iter().find_map(|k| obj.get(*k));
+
+    let timestamp = mapped(|p| &p.timestamp)
+        .or_else(|| conventional(&TIMESTAMP_KEYS))
         .map(json_value_to_string)
         .unwrap_or_default();
 
-    let message = MESSAGE_KEYS
-        .iter()
-        .find_map(|k| obj.get(*k))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
+    let message = mapped(|p| &p.message)
+        .map(render_value)
+        .or_else(|| conventional(&MESSAGE_KEYS).and_then(|v| v.as_str()).map(|s| s.to_string()))
         .unwrap_or_else(|| trimmed.to_string());
 
-    let level = LEVEL_KEYS
-        .iter()
-        .find_map(|k| obj.get(*k))
-        .and_then(|v| v.as_str())
-        .map(normalize_level)
+    let level = mapped(|p| &p.level)
+        .or_else(|| conventional(&LEVEL_KEYS))
+        .and_then(|v| match v {
+            Value::String(s) => Some(normalize_level(s)),
+            Value::Number(n) => n.as_u64().map(numeric_level),
+            _ => None,
+        })
+        .or_else(|| profile.and_then(|p| p.default_level.as_deref()).map(normalize_level))
         .unwrap_or_else(|| infer_log_level(&message));
 
-    let ip_address = IP_KEYS
-        .iter()
-        .find_map(|k| obj.get(*k))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
+    let ip_address = mapped(|p| &p.ip)
+        .or_else(|| conventional(&IP_KEYS))
+        .map(render_value)
+        .filter(|s| !s.is_empty())
         .or_else(|| extract_ip_address(&message));
 
-    let username = USER_KEYS
-        .iter()
-        .find_map(|k| obj.get(*k))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
+    let username = mapped(|p| &p.user)
+        .or_else(|| conventional(&USER_KEYS))
+        .filter(|v| v.is_string() || v.is_number())
+        .map(render_value)
+        .filter(|s| !s.is_empty())
         .or_else(|| extract_username(&message));
 
+    let mut fields = BTreeMap::new();
+    if let Some(profile) = profile {
+        fields = profile.extract_fields(&value);
+        if !profile.name.is_empty() {
+            fields.insert("json.profile".to_string(), profile.name.clone());
+        }
+    }
+
     Some(LogEntry {
         timestamp,
         level,
         ip_address,
         username,
         message,
+        fields,
     })
 }
 
@@ -86,11 +128,26 @@ fn normalize_level(raw: &str) -> String {
     match upper.as_str() {
         "WARNING" => "WARN".to_string(),
         "ERR" => "ERROR".to_string(),
-        "CRIT" | "FATAL" | "EMERGENCY" | "PANIC" => "CRITICAL".to_string(),
+        "INFORMATION" => "INFO".to_string(),
+        "VERBOSE" => "TRACE".to_string(),
+        "CRIT" | "FATAL" | "EMERGENCY" | "PANIC" | "DPANIC" => "CRITICAL".to_string(),
         _ => upper,
     }
 }
 
+/// Map bunyan/pino numeric levels (10 = trace ... 60 = fatal) onto the common set.
+fn numeric_level(level: u64) -> String {
+    match level {
+        0..=19 => "TRACE",
+        20..=29 => "DEBUG",
+        30..=39 => "INFO",
+        40..=49 => "WARN",
+        50..=59 => "ERROR",
+        _ => "CRITICAL",
+    }
+    .to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,6 +182,69 @@ mod tests {
         assert_eq!(entry.ip_address, Some("203.0.113.9".to_string()));
     }
 
+    #[test]
+    fn maps_journald_exports() {
+        let line = r#"{"__REALTIME_TIMESTAMP":"1740047445000000","PRIORITY":"3","_HOSTNAME":"web01","SYSLOG_IDENTIFIER":"sshd","MESSAGE":"Failed password for root from 203.0.113.5 port 22 ssh2"}"#;
+        let entry = parse_json_line(line).expect("should parse");
+        assert_eq!(entry.timestamp, "2025-02-20T10:30:45+00:00");
+        assert_eq!(entry.level, "ERROR");
+        assert_eq!(entry.username, Some("root".to_string()));
+        assert_eq!(entry.message, "web01 sshd: Failed password for root from 203.0.113.5 port 22 ssh2");
+    }
+
+    #[test]
+    fn maps_nested_ecs_fields() {
+        let line = r#"{"@timestamp":"2025-02-20T10:30:45Z","log":{"level":"warn"},"message":"login failed","source":{"ip":"198.51.100.4"},"user":{"name":"alice"},"http":{"request":{"method":"POST"},"response":{"status_code":401}},"url":{"path":"/login"},"ecs":{"version":"8.11"}}"#;
+        let entry = parse_json_line(line).expect("should parse");
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.ip_address, Some("198.51.100.4".to_string()));
+        assert_eq!(entry.username, Some("alice".to_string()));
+        assert_eq!(entry.fields.get("http.method"), Some(&"POST".to_string()));
+        assert_eq!(entry.fields.get("http.status"), Some(&"401".to_string()));
+        assert_eq!(entry.fields.get("http.path"), Some(&"/login".to_string()));
+        assert_eq!(entry.fields.get("json.profile"), Some(&"ecs".to_string()));
+    }
+
+    #[test]
+    fn maps_pino_numeric_levels_and_request_fields() {
+        let line = r#"{"level":50,"time":1740047445000,"pid":42,"hostname":"api-1","req":{"method":"GET","url":"/admin","remoteAddress":"203.0.113.7"},"msg":"request errored"}"#;
+        let entry = parse_json_line(line).expect("should parse");
+        assert_eq!(entry.level, "ERROR");
+        assert_eq!(entry.ip_address, Some("203.0.113.7".to_string()));
+        assert_eq!(entry.fields.get("http.path"), Some(&"/admin".to_string()));
+        assert_eq!(entry.fields.get("process.pid"), Some(&"42".to_string()));
+    }
+
+    #[test]
+    fn serilog_defaults_to_information() {
+        let line = r#"{"@t":"2025-02-20T10:30:45Z","@mt":"User {UserName} signed in","UserName":"bob","SourceContext":"Auth"}"#;
+        let entry = parse_json_line(line).expect("should parse");
+        assert_eq!(entry.level, "INFO");
+        assert_eq!(entry.username, Some("bob".to_string()));
+        assert_eq!(entry.fields.get("logger"), Some(&"Auth".to_string()));
+    }
+
+    #[test]
+    fn applies_custom_profile_paths() {
+        let profile = JsonProfile::from_spec(
+            r#"
+            name = "checkout"
+            ip = ["ctx.client.addr", "/ctx/forwarded_for/0"]
+            user = "ctx.user.login"
+
+            [fields]
+            "http.method" = "ctx.request.method"
+            "#,
+        )
+        .unwrap();
+        let line = r#"{"ts":"2025-02-20T10:30:45Z","level":"info","message":"checkout","ctx":{"forwarded_for":["192.0.2.50"],"user":{"login":"carol"},"request":{"method":"PUT"}}}"#;
+        let entry = parse_json_line_with(line, Some(&profile)).expect("should parse");
+        assert_eq!(entry.ip_address, Some("192.0.2.50".to_string()));
+        assert_eq!(entry.username, Some("carol".to_string()));
+        assert_eq!(entry.fields.get("http.method"), Some(&"PUT".to_string()));
+        assert_eq!(entry.fields.get("json.profile"), Some(&"checkout".to_string()));
+    }
+
     #[test]
     fn rejects_non_json_lines() {
         assert!(parse_json_line("plain text").is_none());
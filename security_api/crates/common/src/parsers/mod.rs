@@ -2,36 +2,148 @@
 // Structured parsers for different log formats
 
 pub mod apache;
+pub mod container;
+pub mod diagnostics;
+pub mod firewall;
 pub mod generic;
+pub mod journald;
+pub mod json_profile;
 pub mod jsonl;
+pub mod privilege;
 pub mod syslog;
+pub mod timestamp;
 
 pub use apache::{ApacheLog, parse_apache_combined};
+pub use container::{
+    ContainerLine, ContainerReassembler, parse_container_line, parse_container_line_with,
+    unwrap_container_line,
+};
+pub use diagnostics::{Rejection, diagnose_line};
+pub use firewall::{FirewallEvent, parse_firewall_line, parse_firewall_message};
 pub use generic::parse_generic_log;
-pub use jsonl::parse_json_line;
+pub use json_profile::JsonProfile;
+pub use jsonl::{parse_json_line, parse_json_line_with};
+pub use privilege::{PrivilegeEvent, parse_privilege_message};
 pub use syslog::parse_syslog;
+pub use timestamp::parse_timestamp;
 
-use crate::LogEntry;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{FormatQuality, LogEntry, ParseError, ParsingInfo};
+use container::{ContainerRuntime, classify_container_line};
+use generic::parse_generic_structured;
+
+/// Rejected lines kept as samples per error category unless configured otherwise
+pub const DEFAULT_ERROR_SAMPLE_LIMIT: usize = 10;
+
+/// Per-source parsing options
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Field mapping applied to JSON-lines records. When unset, records shaped
+    /// like a built-in profile are mapped with it automatically.
+    pub json_profile: Option<JsonProfile>,
+    /// Only accept recognized formats; free text that would otherwise fall
+    /// through to minimal parsing is rejected with a diagnostic
+    pub strict: bool,
+    /// Maximum number of rejected lines kept as samples per error category
+    pub error_sample_limit: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            json_profile: None,
+            strict: false,
+            error_sample_limit: DEFAULT_ERROR_SAMPLE_LIMIT,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Whether an entry produced by `format` is accepted under these options
+    pub fn accepts(&self, format: LineFormat) -> bool {
+        !self.strict || format.is_recognized()
+    }
+}
+
+/// Which strategy of the unified parser produced an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFormat {
+    Apache,
+    Json,
+    Syslog,
+    /// Generic timestamp (+ level) + message
+    Timestamped,
+    /// Bare firewall record (netfilter/UFW/filterlog) without a log header
+    Firewall,
+    /// Minimal parsing of free text
+    Freeform,
+}
+
+impl LineFormat {
+    /// Freeform text is parsed for IPs and keywords but is not a recognized format
+    pub fn is_recognized(&self) -> bool {
+        *self != LineFormat::Freeform
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineFormat::Apache => "apache",
+            LineFormat::Json => "json",
+            LineFormat::Syslog => "syslog",
+            LineFormat::Timestamped => "timestamped",
+            LineFormat::Firewall => "firewall",
+            LineFormat::Freeform => "freeform",
+        }
+    }
+}
 
 /// Unified log parser that tries multiple formats with fallback
 /// 
 /// Parsing strategy:
+/// 0. Unwrap container runtime records (Docker json-file / CRI) and parse
+///    the inner line with the strategies below
 /// 1. Try Apache/nginx Combined Log Format (most specific; nginx's default
 ///    "combined" access-log format is identical to Apache's)
-/// 2. Try JSON-lines (one JSON object per line, structured loggers)
+/// 2. Try JSON-lines (one JSON object per line, structured loggers and
+///    `journalctl -o json` exports)
 /// 3. Try RFC 3164 syslog / auth.log format
 /// 4. Try generic structured formats (timestamp + level + message)
-/// 5. Fall back to minimal parsing (extract IPs and keywords)
+/// 5. Try a bare firewall record (iptables/UFW kernel line, pfSense filterlog)
+/// 6. Fall back to minimal parsing (extract IPs and keywords)
+///
+/// Entries from strategies 2-4 whose message is a firewall record also get
+/// `firewall.*` fields, with the packet source as their IP address.
+/// Those whose message is a sudo, su or pkexec record get `privilege.*`
+/// fields, with the invoking user as their username.
 /// 
 /// This ensures NO log lines are lost - every line gets analyzed
 pub fn parse_log_line_unified(line: &str) -> Option<LogEntry> {
+    parse_log_line_with(line, &ParseOptions::default())
+}
+
+/// `parse_log_line_unified` with per-source options (e.g. a JSON field mapping).
+/// In strict mode lines that only minimal parsing could handle return `None`.
+pub fn parse_log_line_with(line: &str, options: &ParseOptions) -> Option<LogEntry> {
+    classify_log_line(line, options)
+        .filter(|(_, format)| options.accepts(*format))
+        .map(|(entry, _)| entry)
+}
+
+/// Parse a line and report which strategy recognized it
+pub fn classify_log_line(line: &str, options: &ParseOptions) -> Option<(LogEntry, LineFormat)> {
     if line.trim().is_empty() {
         return None;
     }
 
+    // Strategy 0: Container runtime wrapper around the real log line
+    if let Some(container) = unwrap_container_line(line) {
+        return classify_container_line(&container, options);
+    }
+
     // Strategy 1: Try Apache/nginx Combined format first
     if let Ok(apache_log) = parse_apache_combined(line) {
-        return Some(LogEntry {
+        let entry = LogEntry {
             timestamp: apache_log.timestamp.to_rfc3339(),
             level: if apache_log.status >= 500 {
                 "CRITICAL".to_string()
@@ -49,27 +161,263 @@ pub fn parse_log_line_unified(line: &str) -> Option<LogEntry> {
                 apache_log.status,
                 apache_log.threat_type.as_deref().unwrap_or("Normal")
             ),
-        });
+            fields: apache_log.http_fields(),
+        };
+        return Some((entry, LineFormat::Apache));
     }
 
     // Strategy 2: JSON-lines (structured loggers)
-    if let Some(entry) = parse_json_line(line) {
-        return Some(entry);
+    if let Some(mut entry) = parse_json_line_with(line, options.json_profile.as_ref()) {
+        firewall::enrich(&mut entry);
+        privilege::enrich(&mut entry);
+        return Some((entry, LineFormat::Json));
     }
 
     // Strategy 3: RFC 3164 syslog / auth.log
-    if let Some(entry) = parse_syslog(line) {
-        return Some(entry);
+    if let Some(mut entry) = parse_syslog(line) {
+        firewall::enrich(&mut entry);
+        privilege::enrich(&mut entry);
+        return Some((entry, LineFormat::Syslog));
+    }
+
+    // Strategy 4: Generic timestamped formats
+    if let Some(mut entry) = parse_generic_structured(line) {
+        firewall::enrich(&mut entry);
+        privilege::enrich(&mut entry);
+        return Some((entry, LineFormat::Timestamped));
     }
 
-    // Strategy 4 & 5: Use generic parser with fallback
-    parse_generic_log(line)
+    // Strategy 5: Firewall record without a header
+    if let Some(entry) = parse_firewall_line(line) {
+        return Some((entry, LineFormat::Firewall));
+    }
+
+    // Strategy 6: Minimal parsing fallback
+    parse_generic_log(line).map(|entry| (entry, LineFormat::Freeform))
+}
+
+/// Entries and parsing statistics for a whole log file
+pub struct ParsedContent {
+    pub entries: Vec<LogEntry>,
+    /// 1-based source line number of each entry (parallel to `entries`)
+    pub line_numbers: Vec<usize>,
+    /// Parser strategy that produced each entry (parallel to `entries`)
+    pub formats: Vec<LineFormat>,
+    pub parsing_info: ParsingInfo,
+}
+
+/// Parse every line of a log file with `parse_log_line_unified`.
+///
+/// Works across lines where a single line is not enough: container runtime
+/// records split into partial chunks are reassembled before parsing. A
+/// reassembled record is numbered at the line of its first chunk, and its
+/// chunks count as parsed or skipped together once the record is accepted or
+/// rejected.
+pub fn parse_content(content: &str) -> ParsedContent {
+    parse_content_with(content, &ParseOptions::default())
+}
+
+/// A container record still waiting for its final chunk
+struct PendingRecord {
+    start: usize,
+    chunks: usize,
+    first_line: String,
+}
+
+/// Accepted entries and rejection diagnostics, as lines are settled
+struct Collector<'a> {
+    options: &'a ParseOptions,
+    entries: Vec<LogEntry>,
+    line_numbers: Vec<usize>,
+    formats: Vec<LineFormat>,
+    parsed_lines: usize,
+    errors: Vec<ParseError>,
+    error_counts: BTreeMap<String, usize>,
+    quality: FormatQuality,
+}
+
+impl Collector<'_> {
+    /// Accept or reject one record spanning `lines` source lines, the first
+    /// of which is `line_number` with the text `line`. `inner` is the
+    /// application line inside any container wrapper.
+    fn settle(
+        &mut self,
+        parsed: Option<(LogEntry, LineFormat)>,
+        line_number: usize,
+        lines: usize,
+        line: &str,
+        inner: &str,
+    ) {
+        match parsed.filter(|(_, format)| self.options.accepts(*format)) {
+            Some((entry, format)) => {
+                self.parsed_lines += lines;
+                record_format_quality(&mut self.quality, format, &entry);
+                self.entries.push(entry);
+                self.line_numbers.push(line_number);
+                self.formats.push(format);
+            }
+            None => {
+                // Columns refer to the application line inside any container wrapper
+                let rejection = diagnose_line(inner);
+                let category = rejection.category();
+                let count = self.error_counts.entry(category.clone()).or_insert(0);
+                *count += 1;
+                if *count <= self.options.error_sample_limit {
+                    self.errors.push(ParseError {
+                        line_number,
+                        line_content: truncate_line(line, 100),
+                        error_type: rejection.error_type(),
+                        suggestion: rejection.suggestion(),
+                        category,
+                        parser: rejection.parser.to_string(),
+                        column: rejection.column,
+                        expected: rejection.expected,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// `parse_content` with per-source options.
+///
+/// Rejected lines are diagnosed (closest parser, column, expectation) and
+/// counted per category; at most `error_sample_limit` samples are kept for
+/// each category.
+pub fn parse_content_with(content: &str, options: &ParseOptions) -> ParsedContent {
+    let mut collector = Collector {
+        options,
+        entries: Vec::new(),
+        line_numbers: Vec::new(),
+        formats: Vec::new(),
+        parsed_lines: 0,
+        errors: Vec::new(),
+        error_counts: BTreeMap::new(),
+        quality: FormatQuality {
+            perfect_format: 0,
+            alternative_format: 0,
+            fallback_format: 0,
+        },
+    };
+    let mut total_lines = 0;
+    let mut reassembler = ContainerReassembler::new();
+    let mut pending: HashMap<(ContainerRuntime, String), PendingRecord> = HashMap::new();
+
+    for line in content.lines() {
+        total_lines += 1;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Container records are unwrapped here rather than in the unified
+        // parser so that partial chunks can be joined across lines.
+        let Some(chunk) = unwrap_container_line(line) else {
+            collector.settle(classify_log_line(line, options), total_lines, 1, line, line);
+            continue;
+        };
+        let key = (chunk.runtime, chunk.stream.clone());
+        match reassembler.push(chunk) {
+            Some(complete) => {
+                let parsed = classify_container_line(&complete, options);
+                match pending.remove(&key) {
+                    Some(record) => {
+                        let lines = record.chunks + 1;
+                        collector.settle(parsed, record.start, lines, &record.first_line, &complete.log);
+                    }
+                    None => collector.settle(parsed, total_lines, 1, line, &complete.log),
+                }
+            }
+            None => {
+                let record = pending.entry(key).or_insert_with(|| PendingRecord {
+                    start: total_lines,
+                    chunks: 0,
+                    first_line: line.to_string(),
+                });
+                record.chunks += 1;
+            }
+        }
+    }
+
+    // Chunks whose final record never arrived (truncated file): parse what we
+    // have, at the line the record started on
+    let mut leftovers: Vec<(PendingRecord, ContainerLine)> = reassembler
+        .finish()
+        .into_iter()
+        .filter_map(|line| Some((pending.remove(&(line.runtime, line.stream.clone()))?, line)))
+        .collect();
+    leftovers.sort_by_key(|(record, _)| record.start);
+    for (record, leftover) in leftovers {
+        let parsed = classify_container_line(&leftover, options);
+        collector.settle(parsed, record.start, record.chunks, &record.first_line, &leftover.log);
+    }
+
+    let parsed_lines = collector.parsed_lines;
+    ParsedContent {
+        entries: collector.entries,
+        line_numbers: collector.line_numbers,
+        formats: collector.formats,
+        parsing_info: ParsingInfo {
+            total_lines,
+            parsed_lines,
+            skipped_lines: total_lines - parsed_lines,
+            strict: options.strict,
+            errors: collector.errors,
+            error_counts: collector.error_counts,
+            format_quality: collector.quality,
+        },
+    }
+}
+
+/// Track format quality for a parsed line
+fn record_format_quality(quality: &mut FormatQuality, format: LineFormat, entry: &LogEntry) {
+    if format == LineFormat::Apache {
+        quality.perfect_format += 1; // Apache format
+    } else if entry.timestamp.contains('-') && !entry.level.is_empty() {
+        quality.alternative_format += 1; // Generic structured format
+    } else {
+        quality.fallback_format += 1; // Minimal parsing
+    }
+}
+
+/// Shorten a line for display in parse errors without splitting a character
+fn truncate_line(line: &str, max_chars: usize) -> String {
+    if line.chars().count() > max_chars {
+        format!("{}...", line.chars().take(max_chars).collect::<String>())
+    } else {
+        line.to_string()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn syslog_firewall_lines_carry_firewall_fields() {
+        let line = "Feb 20 10:30:45 gw kernel: [UFW BLOCK] IN=eth0 OUT= SRC=203.0.113.5 DST=10.0.0.2 PROTO=TCP SPT=40000 DPT=445";
+        let (entry, format) = classify_log_line(line, &ParseOptions::default()).unwrap();
+        assert_eq!(format, LineFormat::Syslog);
+        assert_eq!(entry.timestamp, "Feb 20 10:30:45");
+        assert_eq!(entry.ip_address.as_deref(), Some("203.0.113.5"));
+        assert_eq!(entry.fields["firewall.dst_port"], "445");
+
+        let bare = "IN=eth0 OUT= SRC=203.0.113.5 DST=10.0.0.2 PROTO=TCP SPT=40000 DPT=445";
+        let (_, format) = classify_log_line(bare, &ParseOptions::default()).unwrap();
+        assert_eq!(format, LineFormat::Firewall);
+    }
+
+    #[test]
+    fn syslog_su_lines_carry_privilege_fields() {
+        let line = "Feb 20 10:30:45 web01 su: (to root) alice on pts/0";
+        let (entry, format) = classify_log_line(line, &ParseOptions::default()).unwrap();
+        assert_eq!(format, LineFormat::Syslog);
+        assert_eq!(entry.username.as_deref(), Some("alice"));
+        assert_eq!(entry.fields["privilege.tool"], "su");
+        assert_eq!(entry.fields["privilege.target_user"], "root");
+        assert_eq!(entry.fields["privilege.outcome"], "success");
+    }
+
     #[test]
     fn unified_dispatches_nginx_combined_to_apache_parser() {
         // nginx's default "combined" format is identical to Apache's.
@@ -79,6 +427,16 @@ mod tests {
         assert_eq!(entry.level, "INFO");
     }
 
+    #[test]
+    fn apache_entries_carry_http_fields() {
+        let line = r#"203.0.113.10 - - [15/Dec/2025:17:19:00 +0000] "POST /login HTTP/1.1" 401 15 "-" "curl/8.0""#;
+        let entry = parse_log_line_unified(line).expect("should parse");
+        assert_eq!(entry.fields.get("http.method"), Some(&"POST".to_string()));
+        assert_eq!(entry.fields.get("http.path"), Some(&"/login".to_string()));
+        assert_eq!(entry.fields.get("http.status"), Some(&"401".to_string()));
+        assert_eq!(entry.fields.get("http.user_agent"), Some(&"curl/8.0".to_string()));
+    }
+
     #[test]
     fn unified_dispatches_json_lines() {
         let line = r#"{"timestamp":"2025-02-20T10:30:45Z","level":"error","message":"Authentication failed","ip":"10.0.0.1"}"#;
@@ -96,6 +454,101 @@ mod tests {
         assert_eq!(entry.ip_address, Some("203.0.113.5".to_string()));
     }
 
+    #[test]
+    fn unified_unwraps_container_records() {
+        let line = "2025-02-20T10:30:45.1Z stderr F Feb 20 10:30:45 web01 sshd[1234]: Failed password for root from 203.0.113.5 port 22 ssh2";
+        let entry = parse_log_line_unified(line).expect("should parse");
+        assert_eq!(entry.username, Some("root".to_string()));
+        assert_eq!(entry.ip_address, Some("203.0.113.5".to_string()));
+        assert_eq!(entry.fields.get("container.stream"), Some(&"stderr".to_string()));
+    }
+
+    #[test]
+    fn parse_content_reassembles_partial_container_lines() {
+        let content = "\
+2025-02-20T10:30:45.1Z stdout P 203.0.113.10 - - [20/Feb/2025:10:30:45 +0000] \"GET /index.html \n\
+2025-02-20T10:30:45.1Z stdout F HTTP/1.1\" 200 512 \"-\" \"Mozilla/5.0\"\n\
+2025-02-20 10:30:46 [ERROR] Failed login from 192.168.1.100\n";
+        let parsed = parse_content(content);
+        assert_eq!(parsed.entries.len(), 2);
+        assert_eq!(parsed.line_numbers, vec![1, 3]);
+        assert_eq!(parsed.parsing_info.total_lines, 3);
+        assert_eq!(parsed.parsing_info.parsed_lines, 3);
+        assert_eq!(parsed.parsing_info.format_quality.perfect_format, 1);
+        assert_eq!(parsed.entries[0].ip_address, Some("203.0.113.10".to_string()));
+    }
+
+    #[test]
+    fn parse_content_keeps_start_line_of_truncated_container_lines() {
+        let content = "\
+2025-02-20T10:30:45.1Z stderr P Failed password for root \n\
+2025-02-20 10:30:46 [INFO] Server started on port 8080\n\
+2025-02-20T10:30:47.1Z stderr P from 203.0.113.5 port 22 ssh2\n\
+2025-02-20 10:30:48 [INFO] Health check ok\n";
+        let parsed = parse_content(content);
+        assert_eq!(parsed.entries.len(), 3);
+        assert_eq!(parsed.line_numbers, vec![2, 4, 1]);
+        assert!(parsed.entries[2].message.contains("Failed password for root from 203.0.113.5"));
+    }
+
+    #[test]
+    fn strict_mode_rejects_container_records_once_at_their_start_line() {
+        let content = "\
+2025-02-20T10:30:45.1Z stdout P something happened \n\
+2025-02-20T10:30:45.2Z stdout F on the server\n\
+2025-02-20 10:30:46 [ERROR] Failed login from 192.168.1.100\n\
+2025-02-20T10:30:47.1Z stderr P another free text \n\
+2025-02-20T10:30:47.2Z stderr P line cut off\n";
+        let options = ParseOptions { strict: true, ..Default::default() };
+        let parsed = parse_content_with(content, &options);
+        let info = &parsed.parsing_info;
+        assert_eq!(parsed.line_numbers, vec![3]);
+        assert_eq!((info.parsed_lines, info.skipped_lines), (1, 4));
+        assert_eq!(info.error_counts.get("unrecognized_format"), Some(&2));
+        let lines: Vec<usize> = info.errors.iter().map(|e| e.line_number).collect();
+        assert_eq!(lines, vec![1, 4]);
+        assert!(info.errors[1].line_content.contains("another free text"));
+    }
+
+    #[test]
+    fn parse_content_applies_json_profile() {
+        let options = ParseOptions {
+            json_profile: JsonProfile::from_spec(r#"ip = "ctx.client.addr""#).ok(),
+            ..Default::default()
+        };
+        let content = r#"{"ts":"2025-02-20T10:30:45Z","level":"error","msg":"denied","ctx":{"client":{"addr":"10.1.2.3"}}}"#;
+        let parsed = parse_content_with(content, &options);
+        assert_eq!(parsed.entries[0].ip_address, Some("10.1.2.3".to_string()));
+        assert_eq!(parse_content(content).entries[0].ip_address, None);
+    }
+
+    #[test]
+    fn strict_mode_rejects_free_text_with_diagnostics() {
+        let content = "\
+2025-02-20 10:30:45 [ERROR] Failed login from 192.168.1.100
+something happened on the server
+another free text line
+203.0.113.10 - - [15/Dec/2025:17:19:00 +0000] \"GET / HTTP/1.1\" OK 15 \"-\" \"curl\"
+";
+        let lenient = parse_content(content);
+        assert_eq!(lenient.parsing_info.parsed_lines, 4);
+        assert!(lenient.parsing_info.errors.is_empty());
+
+        let options = ParseOptions { strict: true, error_sample_limit: 1, ..Default::default() };
+        let strict = parse_content_with(content, &options);
+        let info = &strict.parsing_info;
+        assert!(info.strict);
+        assert_eq!(info.parsed_lines, 1);
+        assert_eq!(info.error_counts.get("unrecognized_format"), Some(&2));
+        assert_eq!(info.error_counts.get("malformed_apache"), Some(&1));
+        // One sample per category
+        assert_eq!(info.errors.len(), 2);
+        let apache = info.errors.iter().find(|e| e.parser == "apache").unwrap();
+        assert_eq!(apache.line_number, 4);
+        assert_eq!(apache.column, 64);
+        assert_eq!(apache.expected, "numeric status code");
+    }
+
     #[test]
     fn unified_falls_back_to_generic() {
         let line = "2025-02-20 10:30:45 [ERROR] Failed login from 192.168.1.100";
@@ -0,0 +1,309 @@
+// sudo, su and pkexec event parser
+// Handles the privilege-change records these tools write to auth.log/secure,
+// whether they arrive as syslog, journald or generic lines:
+//   sudo:    alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/bin/ls
+//   sudo:    alice : 3 incorrect password attempts ; TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/bin/ls
+//   sudo:    mallory : user NOT in sudoers ; TTY=pts/1 ; PWD=/tmp ; USER=root ; COMMAND=/bin/bash
+//   su:      (to root) alice on pts/0
+//   su[812]: FAILED su for root by alice
+//   pkexec[9]: alice: Executing command [USER=root] [TTY=/dev/pts/0] [CWD=/home/alice] [COMMAND=/usr/bin/bash]
+//   sudo:    pam_unix(sudo:auth): authentication failure; logname=alice uid=1000 euid=0 tty=/dev/pts/0 ruser=alice rhost=  user=alice
+//   su:      pam_unix(su:session): session opened for user root(uid=0) by alice(uid=1000)
+
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::LogEntry;
+
+/// One use of sudo, su or pkexec
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivilegeEvent {
+    /// `sudo`, `su` or `pkexec`
+    pub tool: &'static str,
+    /// Invoking user
+    pub user: Option<String>,
+    /// User the command runs as
+    pub target_user: Option<String>,
+    /// Terminal without the `/dev/` prefix
+    pub tty: Option<String>,
+    /// Working directory
+    pub pwd: Option<String>,
+    pub command: Option<String>,
+    /// `success`, `auth_failure` (wrong or missing password),
+    /// `not_in_sudoers`, `not_allowed` (sudoers or polkit refused the
+    /// command), `session_opened` or `session_closed`
+    pub outcome: &'static str,
+}
+
+impl PrivilegeEvent {
+    /// Structured fields for `LogEntry::fields` (`privilege.*`)
+    pub fn fields(&self) -> BTreeMap<String, String> {
+        let mut fields = BTreeMap::new();
+        let mut set = |name: &str, value: Option<String>| {
+            if let Some(value) = value {
+                fields.insert(format!("privilege.{}", name), value);
+            }
+        };
+        set("tool", Some(self.tool.to_string()));
+        set("user", self.user.clone());
+        set("target_user", self.target_user.clone());
+        set("tty", self.tty.clone());
+        set("pwd", self.pwd.clone());
+        set("command", self.command.clone());
+        set("outcome", Some(self.outcome.to_string()));
+        fields
+    }
+
+    /// The user got the target user's privileges (or a session as them)
+    pub fn escalated(&self) -> bool {
+        matches!(self.outcome, "success" | "session_opened")
+    }
+
+    fn new(tool: &'static str, outcome: &'static str) -> Self {
+        PrivilegeEvent {
+            tool,
+            user: None,
+            target_user: None,
+            tty: None,
+            pwd: None,
+            command: None,
+            outcome,
+        }
+    }
+}
+
+/// Find a sudo, su or pkexec record in `text` (`host tool[pid]: body`, or the
+/// body on its own after `tool:`)
+pub fn parse_privilege_message(text: &str) -> Option<PrivilegeEvent> {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    let header = HEADER.get_or_init(|| {
+        Regex::new(r"(?:^|\s)(sudo|su|pkexec)(?:\[\d+\])?:\s+(.+)$").expect("privilege header pattern")
+    });
+    let caps = header.captures(text)?;
+    let body = caps.get(2)?.as_str().trim();
+    match caps.get(1)?.as_str() {
+        "sudo" => parse_pam("sudo", body).or_else(|| parse_sudo(body)),
+        "su" => parse_pam("su", body).or_else(|| parse_su(body)),
+        _ => parse_pkexec(body),
+    }
+}
+
+/// Add `privilege.*` fields to an entry whose message is a sudo, su or
+/// pkexec record, and make the invoking user its username
+pub fn enrich(entry: &mut LogEntry) {
+    if let Some(event) = parse_privilege_message(&entry.message) {
+        if event.user.is_some() {
+            entry.username = event.user.clone();
+        }
+        entry.fields.extend(event.fields());
+    }
+}
+
+/// `alice : [problem ; ]TTY=... ; PWD=... ; USER=... ; COMMAND=...`
+fn parse_sudo(body: &str) -> Option<PrivilegeEvent> {
+    let (user, rest) = body.split_once(" : ")?;
+    let user = user.trim();
+    if user.is_empty() || user.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let mut event = PrivilegeEvent::new("sudo", "success");
+    event.user = Some(user.to_string());
+    event.target_user = Some("root".to_string());
+    let mut problem = String::new();
+    let mut parts = rest.split(" ; ");
+    while let Some(part) = parts.next() {
+        match part.split_once('=') {
+            // The command is last and may itself contain " ; "
+            Some(("COMMAND", command)) => {
+                let remainder: Vec<&str> = parts.by_ref().collect();
+                let command = std::iter::once(command).chain(remainder).collect::<Vec<_>>().join(" ; ");
+                event.command = Some(command.trim().to_string());
+            }
+            Some(("TTY", tty)) => event.tty = Some(tty.trim_start_matches("/dev/").to_string()),
+            Some(("PWD", pwd)) => event.pwd = Some(pwd.to_string()),
+            Some(("USER", target)) => event.target_user = Some(target.to_string()),
+            Some((key, _)) if key.chars().all(|c| c.is_ascii_uppercase()) => {}
+            _ => problem = part.to_lowercase(),
+        }
+    }
+    if event.command.is_none() && event.tty.is_none() {
+        return None;
+    }
+    event.outcome = if problem.is_empty() {
+        "success"
+    } else if problem.contains("not in sudoers") {
+        "not_in_sudoers"
+    } else if problem.contains("password") {
+        "auth_failure"
+    } else {
+        "not_allowed"
+    };
+    Some(event)
+}
+
+/// `(to root) alice on pts/0`, `Successful su for root by alice`,
+/// `FAILED su for root by alice` and the old `+ pts/0 alice:root`
+fn parse_su(body: &str) -> Option<PrivilegeEvent> {
+    static PATTERNS: OnceLock<[Regex; 3]> = OnceLock::new();
+    let [to, verdict, short] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"^\(to (\S+)\) (\S+) on (\S+)").expect("su pattern"),
+            Regex::new(r"^(?i)(successful|failed|bad) su for (\S+) by (\S+)").expect("su verdict pattern"),
+            Regex::new(r"^([+-]) (\S+) (\S+?):(\S+)$").expect("su short pattern"),
+        ]
+    });
+
+    let mut event = PrivilegeEvent::new("su", "success");
+    if let Some(caps) = to.captures(body) {
+        event.target_user = Some(caps[1].to_string());
+        event.user = Some(caps[2].to_string());
+        event.tty = Some(caps[3].trim_start_matches("/dev/").to_string());
+    } else if let Some(caps) = verdict.captures(body) {
+        if !caps[1].eq_ignore_ascii_case("successful") {
+            event.outcome = "auth_failure";
+        }
+        event.target_user = Some(caps[2].to_string());
+        event.user = Some(caps[3].to_string());
+    } else if let Some(caps) = short.captures(body) {
+        if &caps[1] == "-" {
+            event.outcome = "auth_failure";
+        }
+        event.tty = Some(caps[2].trim_start_matches("/dev/").to_string());
+        event.user = Some(caps[3].to_string());
+        event.target_user = Some(caps[4].to_string());
+    } else {
+        return None;
+    }
+    Some(event)
+}
+
+/// `alice: Executing command [USER=root] [TTY=...] [CWD=...] [COMMAND=...]`
+/// or `alice: Error executing command as another user: Not authorized [...]`
+fn parse_pkexec(body: &str) -> Option<PrivilegeEvent> {
+    static PAIR: OnceLock<Regex> = OnceLock::new();
+    let pair = PAIR.get_or_init(|| Regex::new(r"\[([A-Z]+)=([^\]]*)\]").expect("pkexec pair pattern"));
+    let (user, rest) = body.split_once(": ")?;
+    let outcome = if rest.starts_with("Executing command") {
+        "success"
+    } else if rest.starts_with("Error executing command") {
+        if rest.to_lowercase().contains("not authorized") { "not_allowed" } else { "auth_failure" }
+    } else {
+        return None;
+    };
+
+    let mut event = PrivilegeEvent::new("pkexec", outcome);
+    event.user = Some(user.to_string());
+    for caps in pair.captures_iter(rest) {
+        let value = caps[2].to_string();
+        match &caps[1] {
+            "USER" => event.target_user = Some(value),
+            "TTY" => event.tty = Some(value.trim_start_matches("/dev/").to_string()),
+            "CWD" => event.pwd = Some(value),
+            "COMMAND" => event.command = Some(value),
+            _ => {}
+        }
+    }
+    Some(event)
+}
+
+/// PAM records of the tool's own service: authentication failures and
+/// session open/close
+fn parse_pam(tool: &'static str, body: &str) -> Option<PrivilegeEvent> {
+    static PATTERNS: OnceLock<[Regex; 3]> = OnceLock::new();
+    let [pam, pair, session] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"^pam_\w+\([\w-]+:(\w+)\):\s*(.*)$").expect("pam pattern"),
+            Regex::new(r"\b(logname|ruser|user|tty)=(\S*)").expect("pam pair pattern"),
+            Regex::new(r"session (opened|closed) for user (\w[\w.-]*)(?:\(uid=\d+\))?(?: by (\w[\w.-]*))?")
+                .expect("pam session pattern"),
+        ]
+    });
+    let caps = pam.captures(body)?;
+    let message = caps.get(2)?.as_str();
+
+    if &caps[1] == "session" {
+        let caps = session.captures(message)?;
+        let mut event = PrivilegeEvent::new(tool, if &caps[1] == "opened" { "session_opened" } else { "session_closed" });
+        event.target_user = Some(caps[2].to_string());
+        event.user = caps.get(3).map(|m| m.as_str().to_string());
+        return Some(event);
+    }
+    if !message.starts_with("authentication failure") {
+        return None;
+    }
+
+    let pairs: BTreeMap<&str, &str> = pair
+        .captures_iter(message)
+        .filter_map(|caps| Some((caps.get(1)?.as_str(), caps.get(2)?.as_str())))
+        .filter(|(_, value)| !value.is_empty())
+        .collect();
+    let invoker = pairs.get("ruser").or_else(|| pairs.get("logname")).map(|u| u.to_string());
+    let mut event = PrivilegeEvent::new(tool, "auth_failure");
+    event.tty = pairs.get("tty").map(|t| t.trim_start_matches("/dev/").to_string());
+    if tool == "su" {
+        // su authenticates as the target user
+        event.user = invoker;
+        event.target_user = pairs.get("user").map(|u| u.to_string());
+    } else {
+        event.user = invoker.or_else(|| pairs.get("user").map(|u| u.to_string()));
+        event.target_user = Some("root".to_string());
+    }
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(message: &str) -> PrivilegeEvent {
+        parse_privilege_message(message).expect("should parse")
+    }
+
+    #[test]
+    fn parses_sudo_commands_and_refusals() {
+        let ok = event("web01 sudo: alice : TTY=/dev/pts/0 ; PWD=/home/alice ; USER=postgres ; COMMAND=/usr/bin/psql -c select 1 ; select 2");
+        assert_eq!(ok.outcome, "success");
+        assert_eq!(ok.user.as_deref(), Some("alice"));
+        assert_eq!(ok.target_user.as_deref(), Some("postgres"));
+        assert_eq!(ok.tty.as_deref(), Some("pts/0"));
+        assert_eq!(ok.pwd.as_deref(), Some("/home/alice"));
+        assert_eq!(ok.command.as_deref(), Some("/usr/bin/psql -c select 1 ; select 2"));
+
+        let denied = event("web01 sudo: mallory : user NOT in sudoers ; TTY=pts/1 ; PWD=/tmp ; USER=root ; COMMAND=/bin/bash");
+        assert_eq!(denied.outcome, "not_in_sudoers");
+        let failed = event("web01 sudo: alice : 3 incorrect password attempts ; TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/bin/ls");
+        assert_eq!(failed.outcome, "auth_failure");
+        assert!(!failed.escalated());
+
+        let pam = event("web01 sudo: pam_unix(sudo:auth): authentication failure; logname=alice uid=1000 euid=0 tty=/dev/pts/0 ruser=alice rhost=  user=alice");
+        assert_eq!((pam.outcome, pam.user.as_deref(), pam.tty.as_deref()), ("auth_failure", Some("alice"), Some("pts/0")));
+    }
+
+    #[test]
+    fn parses_su_and_pkexec() {
+        let to = event("web01 su: (to root) alice on pts/0");
+        assert_eq!((to.user.as_deref(), to.target_user.as_deref(), to.outcome), (Some("alice"), Some("root"), "success"));
+        let failed = event("web01 su[812]: FAILED su for root by bob");
+        assert_eq!((failed.user.as_deref(), failed.outcome), (Some("bob"), "auth_failure"));
+        let short = event("web01 su: - pts/2 carol:root");
+        assert_eq!((short.user.as_deref(), short.target_user.as_deref(), short.outcome), (Some("carol"), Some("root"), "auth_failure"));
+        let pam = event("web01 su: pam_unix(su:auth): authentication failure; logname=dave uid=1001 euid=0 tty=pts/3 ruser=dave rhost=  user=root");
+        assert_eq!((pam.user.as_deref(), pam.target_user.as_deref()), (Some("dave"), Some("root")));
+        let session = event("web01 su: pam_unix(su-l:session): session opened for user root(uid=0) by alice(uid=1000)");
+        assert_eq!((session.outcome, session.user.as_deref()), ("session_opened", Some("alice")));
+
+        let pk = event("web01 pkexec[9]: alice: Executing command [USER=root] [TTY=/dev/pts/0] [CWD=/home/alice] [COMMAND=/usr/bin/bash]");
+        assert_eq!((pk.tool, pk.outcome, pk.command.as_deref()), ("pkexec", "success", Some("/usr/bin/bash")));
+        let refused = event("web01 pkexec[9]: eve: Error executing command as another user: Not authorized [USER=root] [TTY=/dev/pts/4] [CWD=/tmp] [COMMAND=/bin/sh]");
+        assert_eq!(refused.outcome, "not_allowed");
+    }
+
+    #[test]
+    fn ignores_other_messages() {
+        assert!(parse_privilege_message("web01 bash: sudo: command not found").is_none());
+        assert!(parse_privilege_message("web01 sudo: pam_unix(sudo:account): expired password").is_none());
+        assert!(parse_privilege_message("web01 sshd[1]: Accepted password for su from 198.51.100.1").is_none());
+    }
+}
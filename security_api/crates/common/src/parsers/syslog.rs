@@ -4,6 +4,7 @@
 //   Feb 20 10:30:45 myhost sudo: alice : TTY=pts/0 ; PWD=/home/alice ; COMMAND=/bin/ls
 
 use regex::Regex;
+use std::collections::BTreeMap;
 
 use crate::LogEntry;
 use super::generic::{extract_ip_address, infer_log_level};
@@ -35,6 +36,7 @@ pub fn parse_syslog(line: &str) -> Option<LogEntry> {
         ip_address,
         username,
         message,
+        fields: BTreeMap::new(),
     })
 }
 
@@ -44,7 +46,7 @@ pub fn parse_syslog(line: &str) -> Option<LogEntry> {
 /// - "Invalid user admin from ..."
 /// - "Accepted publickey for deploy from ..."
 /// - "sudo: alice : TTY=..."
-fn extract_syslog_username(body: &str) -> Option<String> {
+pub(crate) fn extract_syslog_username(body: &str) -> Option<String> {
     let patterns = [
         r"(?:Failed|Accepted)\s+\S+\s+for\s+(?:invalid user\s+)?([A-Za-z0-9_.-]+)",
         r"[Ii]nvalid user\s+([A-Za-z0-9_.-]+)",
@@ -53,12 +55,11 @@ fn extract_syslog_username(body: &str) -> Option<String> {
     ];
 
     for pattern in &patterns {
-        if let Ok(re) = Regex::new(pattern) {
-            if let Some(caps) = re.captures(body) {
-                if let Some(m) = caps.get(1) {
-                    return Some(m.as_str().to_string());
-                }
-            }
+        if let Ok(re) = Regex::new(pattern)
+            && let Some(caps) = re.captures(body)
+            && let Some(m) = caps.get(1)
+        {
+            return Some(m.as_str().to_string());
         }
     }
 
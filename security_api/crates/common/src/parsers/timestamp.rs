@@ -0,0 +1,113 @@
+// Timestamp normalization
+// Parsers keep `LogEntry::timestamp` as the text found in the log. Anything
+// that reasons about time (windows, ordering, gaps) converts it here.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// Naive formats, tried in order; they are taken to be UTC
+const NAIVE_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y/%m/%dT%H:%M:%S%.f",
+];
+
+/// Year assumed for syslog timestamps, which carry none. A leap year, so
+/// `Feb 29` parses; results are only meaningful relative to each other.
+const SYSLOG_YEAR: i32 = 2000;
+
+/// Parse a log timestamp into UTC.
+///
+/// Accepts RFC 3339 (`2025-02-20T10:30:45Z`, `...+01:00`), `YYYY-MM-DD HH:MM:SS`
+/// with `-` or `/` and an optional offset, Apache `15/Dec/2025:17:19:00 +0000`,
+/// syslog `Feb 20 10:30:45` and epoch seconds or milliseconds.
+/// Timestamps without an offset are treated as UTC.
+pub fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim().trim_start_matches('[').trim_end_matches(']').trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%.f%z", "%d/%b/%Y:%H:%M:%S %z"] {
+        if let Ok(dt) = DateTime::parse_from_str(text, format) {
+            return Some(dt.with_timezone(&Utc));
+        }
+    }
+    let naive = text.trim_end_matches('Z');
+    for format in NAIVE_FORMATS {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(naive, format) {
+            return Some(Utc.from_utc_datetime(&dt));
+        }
+    }
+
+    // Digits only: `f64` parsing alone would take "nan", "inf" and exponents
+    if is_epoch(text)
+        && let Ok(epoch) = text.parse::<f64>()
+        && epoch.is_finite()
+    {
+        // Heuristic: anything past 1e11 is milliseconds (1e11 s is year 5138)
+        let millis = if epoch.abs() >= 1e11 { epoch } else { epoch * 1000.0 };
+        return DateTime::from_timestamp_millis(millis as i64);
+    }
+
+    parse_syslog_timestamp(text)
+}
+
+/// ASCII digits with at most one `.` (`1740047445`, `1740047445.123`)
+fn is_epoch(text: &str) -> bool {
+    text.bytes().any(|b| b.is_ascii_digit())
+        && text.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+        && text.bytes().filter(|&b| b == b'.').count() <= 1
+}
+
+fn parse_syslog_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let mut parts = text.split_whitespace();
+    let (month, day, time) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let date = NaiveDate::parse_from_str(&format!("{} {} {}", SYSLOG_YEAR, month, day), "%Y %b %d").ok()?;
+    let time = chrono::NaiveTime::parse_from_str(time, "%H:%M:%S").ok()?;
+    Some(Utc.from_utc_datetime(&date.and_time(time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rfc3339(text: &str) -> Option<String> {
+        parse_timestamp(text).map(|dt| dt.to_rfc3339())
+    }
+
+    #[test]
+    fn parses_common_log_timestamps() {
+        assert_eq!(rfc3339("2025-02-20T10:30:45Z").as_deref(), Some("2025-02-20T10:30:45+00:00"));
+        assert_eq!(rfc3339("2025-02-20T11:30:45+01:00").as_deref(), Some("2025-02-20T10:30:45+00:00"));
+        assert_eq!(rfc3339("2025-02-20 10:30:45").as_deref(), Some("2025-02-20T10:30:45+00:00"));
+        assert_eq!(rfc3339("[2025/02/20 10:30:45]").as_deref(), Some("2025-02-20T10:30:45+00:00"));
+        assert_eq!(rfc3339("15/Dec/2025:18:19:00 +0100").as_deref(), Some("2025-12-15T17:19:00+00:00"));
+        assert_eq!(rfc3339("1740047445").as_deref(), Some("2025-02-20T10:30:45+00:00"));
+        assert_eq!(rfc3339("1740047445000").as_deref(), Some("2025-02-20T10:30:45+00:00"));
+    }
+
+    #[test]
+    fn syslog_timestamps_are_comparable() {
+        let a = parse_timestamp("Feb 20 10:30:45").unwrap();
+        let b = parse_timestamp("Feb 20 10:31:15").unwrap();
+        assert_eq!((b - a).num_seconds(), 30);
+        assert!(parse_timestamp("Feb 29 00:00:00").is_some());
+    }
+
+    #[test]
+    fn rejects_missing_or_garbage_timestamps() {
+        assert!(parse_timestamp("").is_none());
+        assert!(parse_timestamp("yesterday").is_none());
+        for token in ["nan", "NaN", "inf", "-inf", "infinity", "1e12", "1.2.3", "."] {
+            assert!(parse_timestamp(token).is_none(), "{}", token);
+        }
+        assert!(parse_timestamp("1740047445.5").is_some());
+    }
+}
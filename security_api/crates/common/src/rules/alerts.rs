@@ -0,0 +1,432 @@
+// Threshold alerting
+// Alert rules count matching entries per IP in a sliding time window over the
+// entry timestamps ("5 failed logins from one IP within 10 minutes"). Entries
+// without an IP or a parseable timestamp cannot be placed in a window and are
+// not counted.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::allowlist::{Allowlist, Subject, SuppressionReport};
+use super::{Detector, FieldSource, RuleSet};
+use crate::cvss::Severity;
+use crate::parsers::parse_timestamp;
+use crate::{Alert, AlertRule, LogEntry};
+
+/// The alert rules enabled out of the box
+pub const DEFAULT_ALERTS: &str = include_str!("default_alerts.toml");
+
+/// What an alert rule counts
+#[derive(Debug, Clone, PartialEq)]
+enum AlertCondition {
+    Rule(String),
+    Equals(String, String),
+    NotEquals(String, String),
+    Contains(String, String),
+}
+
+impl AlertCondition {
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if let Some(id) = text.strip_prefix("rule:") {
+            let id = id.trim();
+            if id.is_empty() {
+                return Err("`rule:` needs a detection rule id".to_string());
+            }
+            return Ok(Self::Rule(id.to_string()));
+        }
+
+        for (op, build) in [
+            ("==", Self::Equals as fn(String, String) -> Self),
+            ("!=", Self::NotEquals),
+            (" contains ", Self::Contains),
+        ] {
+            if let Some((field, value)) = text.split_once(op) {
+                let field = field.trim();
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                if field.is_empty() || value.is_empty() {
+                    break;
+                }
+                return Ok(build(field.to_string(), value.to_lowercase()));
+            }
+        }
+        Err(format!(
+            "cannot parse condition '{}' (expected rule:<id>, <field> == <value>, \
+             <field> != <value> or <field> contains <value>)",
+            text
+        ))
+    }
+
+    fn matches(&self, entry: &LogEntry, rules: &RuleSet) -> bool {
+        let field = |name: &str| entry.field(name).map(|v| v.to_lowercase());
+        match self {
+            Self::Rule(id) => rules.get(id).is_some_and(|rule| rule.detect(entry).is_some()),
+            Self::Equals(name, value) => field(name).is_some_and(|v| v == *value),
+            Self::NotEquals(name, value) => field(name).is_some_and(|v| v != *value),
+            Self::Contains(name, value) => field(name).is_some_and(|v| v.contains(value.as_str())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CompiledAlertRule {
+    rule: AlertRule,
+    condition: AlertCondition,
+    severity: Severity,
+}
+
+impl CompiledAlertRule {
+    fn compile(rule: AlertRule) -> Result<Self, String> {
+        let context = |e: String| format!("alert rule '{}': {}", rule.name, e);
+        if rule.threshold == 0 {
+            return Err(context("threshold must be at least 1".to_string()));
+        }
+        if rule.timeframe_minutes == 0 {
+            return Err(context("timeframe_minutes must be at least 1".to_string()));
+        }
+        let condition = AlertCondition::parse(&rule.condition).map_err(context)?;
+        let severity = serde_json::from_value(serde_json::Value::String(rule.severity.clone()))
+            .map_err(|_| context(format!("unknown severity '{}'", rule.severity)))?;
+        Ok(Self { rule, condition, severity })
+    }
+
+    /// Rule ids an allowlist entry can name to keep events out of this rule:
+    /// `alert:<name>`, and the detection rule of a `rule:<id>` condition
+    fn suppression_ids(&self) -> Vec<String> {
+        let mut ids = vec![format!("alert:{}", self.rule.name)];
+        if let AlertCondition::Rule(id) = &self.condition {
+            ids.push(id.clone());
+        }
+        ids
+    }
+
+    fn evaluate(
+        &self,
+        entries: &[LogEntry],
+        times: &[Option<DateTime<Utc>>],
+        rules: &RuleSet,
+        allowlist: &Allowlist,
+        suppressed: &mut SuppressionReport,
+    ) -> Vec<(DateTime<Utc>, Alert)> {
+        let ids = self.suppression_ids();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+
+        // Matching events per IP, in time order
+        let mut by_ip: BTreeMap<&str, Vec<(DateTime<Utc>, usize)>> = BTreeMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            if let (Some(ip), Some(time)) = (entry.ip_address.as_deref(), times[index])
+                && self.condition.matches(entry, rules)
+            {
+                if let Some(name) = allowlist.suppressing(&Subject::of(entry), &ids) {
+                    suppressed.alert_events += 1;
+                    suppressed.record(name);
+                    continue;
+                }
+                by_ip.entry(ip).or_default().push((time, index));
+            }
+        }
+
+        let window = Duration::minutes(i64::from(self.rule.timeframe_minutes));
+        let mut alerts = Vec::new();
+        for (ip, mut events) in by_ip {
+            events.sort_by_key(|(time, _)| *time);
+            let mut start = 0;
+            for end in 0..events.len() {
+                while events[end].0 - events[start].0 > window {
+                    start += 1;
+                }
+                let count = end + 1 - start;
+                if count < self.rule.threshold {
+                    continue;
+                }
+
+                let (first, last) = (&entries[events[start].1], &entries[events[end].1]);
+                alerts.push((
+                    events[end].0,
+                    Alert {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        severity: self.severity.as_str().to_string(),
+                        title: self.rule.name.clone(),
+                        description: format!(
+                            "{} events matching `{}` from {} between {} and {} (threshold {} in {} min)",
+                            count,
+                            self.rule.condition,
+                            ip,
+                            first.timestamp,
+                            last.timestamp,
+                            self.rule.threshold,
+                            self.rule.timeframe_minutes
+                        ),
+                        timestamp: last.timestamp.clone(),
+                        ip_address: Some(ip.to_string()),
+                        triggered_by: self.rule.name.clone(),
+                    },
+                ));
+                // Start a fresh window so a sustained burst is not re-reported
+                // on every following event
+                start = end + 1;
+            }
+        }
+        alerts
+    }
+}
+
+/// An ordered collection of alert rules
+#[derive(Debug, Clone, Default)]
+pub struct AlertEngine {
+    rules: Vec<CompiledAlertRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertFile {
+    #[serde(default)]
+    alerts: Vec<AlertRule>,
+}
+
+impl AlertEngine {
+    /// The built-in alert rules
+    pub fn default_pack() -> Self {
+        Self::from_toml_str(DEFAULT_ALERTS).expect("built-in alert rules are valid")
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self, String> {
+        let file: AlertFile = toml::from_str(text).map_err(|e| format!("Invalid TOML alert rules: {}", e))?;
+        Self::compile(file.alerts)
+    }
+
+    pub fn from_yaml_str(text: &str) -> Result<Self, String> {
+        let file: AlertFile = serde_yaml::from_str(text).map_err(|e| format!("Invalid YAML alert rules: {}", e))?;
+        Self::compile(file.alerts)
+    }
+
+    /// Load a `.toml`, `.yaml` or `.yml` alert rule file
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read alert rule file {}: {}", path.display(), e))?;
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+        let result = match ext.as_str() {
+            "yaml" | "yml" => Self::from_yaml_str(&text),
+            "toml" => Self::from_toml_str(&text),
+            _ => Err("expected a .toml, .yaml or .yml file".to_string()),
+        };
+        result.map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Default alert rules plus the given files, in order
+    pub fn with_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, String> {
+        let mut engine = Self::default_pack();
+        for path in paths {
+            engine.extend(Self::from_file(path.as_ref())?);
+        }
+        Ok(engine)
+    }
+
+    fn compile(rules: Vec<AlertRule>) -> Result<Self, String> {
+        let rules = rules.into_iter().map(CompiledAlertRule::compile).collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Add alert rules; a rule whose name already exists replaces it in place
+    pub fn extend(&mut self, other: AlertEngine) {
+        for rule in other.rules {
+            match self.rules.iter_mut().find(|r| r.rule.name == rule.rule.name) {
+                Some(existing) => *existing = rule,
+                None => self.rules.push(rule),
+            }
+        }
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &AlertRule> {
+        self.rules.iter().map(|r| &r.rule)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Fail if a `rule:<id>` condition names a rule that is not in `rules`
+    pub fn check(&self, rules: &RuleSet) -> Result<(), String> {
+        for compiled in &self.rules {
+            if let AlertCondition::Rule(id) = &compiled.condition
+                && rules.get(id).is_none()
+            {
+                return Err(format!(
+                    "alert rule '{}': unknown detection rule '{}'",
+                    compiled.rule.name, id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Alerts raised by the entries, using the process-wide detection rules
+    /// and allowlist; allowlisted events are counted in `suppressed`
+    pub fn evaluate(&self, entries: &[LogEntry], suppressed: &mut SuppressionReport) -> Vec<Alert> {
+        self.evaluate_allowing(entries, super::active(), super::allowlist::active(), suppressed)
+    }
+
+    /// Alerts raised by the entries, ordered by the time they fired
+    pub fn evaluate_with(&self, entries: &[LogEntry], rules: &RuleSet) -> Vec<Alert> {
+        self.evaluate_allowing(entries, rules, &Allowlist::default(), &mut SuppressionReport::default())
+    }
+
+    /// `evaluate_with`, leaving events the allowlist suppresses out of the
+    /// windows
+    pub fn evaluate_allowing(
+        &self,
+        entries: &[LogEntry],
+        rules: &RuleSet,
+        allowlist: &Allowlist,
+        suppressed: &mut SuppressionReport,
+    ) -> Vec<Alert> {
+        let times: Vec<Option<DateTime<Utc>>> = entries.iter().map(|e| parse_timestamp(&e.timestamp)).collect();
+        let mut alerts: Vec<(DateTime<Utc>, Alert)> = self
+            .rules
+            .iter()
+            .flat_map(|rule| rule.evaluate(entries, &times, rules, allowlist, suppressed))
+            .collect();
+        alerts.sort_by_key(|(time, _)| *time);
+        alerts.into_iter().map(|(_, alert)| alert).collect()
+    }
+}
+
+static ACTIVE_ALERTS: OnceLock<AlertEngine> = OnceLock::new();
+
+/// Install the process-wide alert rules. Must be called before the first log
+/// is analyzed; fails if alert rules are already in use.
+pub fn install(engine: AlertEngine) -> Result<(), String> {
+    ACTIVE_ALERTS
+        .set(engine)
+        .map_err(|_| "alert rules are already initialized".to_string())
+}
+
+/// The process-wide alert rules (the default pack unless `install` was called)
+pub fn active() -> &'static AlertEngine {
+    ACTIVE_ALERTS.get_or_init(AlertEngine::default_pack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn entry(timestamp: &str, ip: &str, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: timestamp.to_string(),
+            level: "INFO".to_string(),
+            ip_address: Some(ip.to_string()),
+            username: None,
+            message: message.to_string(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    fn failed_logins(ip: &str, minutes: &[u32]) -> Vec<LogEntry> {
+        minutes
+            .iter()
+            .map(|m| entry(&format!("2025-02-20 10:{:02}:00", m), ip, "Failed password for root"))
+            .collect()
+    }
+
+    #[test]
+    fn fires_when_threshold_is_reached_within_window() {
+        let entries = failed_logins("203.0.113.5", &[0, 2, 4, 6, 8]);
+        let alerts = AlertEngine::default_pack().evaluate_with(&entries, &RuleSet::default_pack());
+        assert_eq!(alerts.len(), 1);
+        let alert = &alerts[0];
+        assert_eq!(alert.triggered_by, "Repeated failed logins");
+        assert_eq!(alert.ip_address.as_deref(), Some("203.0.113.5"));
+        assert_eq!(alert.severity, "High");
+        assert_eq!(alert.timestamp, "2025-02-20 10:08:00");
+        assert!(uuid::Uuid::parse_str(&alert.id).is_ok());
+    }
+
+    #[test]
+    fn spread_out_events_do_not_fire() {
+        // Five failures, but never five within ten minutes
+        let entries = failed_logins("203.0.113.5", &[0, 6, 12, 18, 24]);
+        let alerts = AlertEngine::default_pack().evaluate_with(&entries, &RuleSet::default_pack());
+        assert!(alerts.is_empty());
+    }
+
+    #[test]
+    fn counts_each_ip_separately() {
+        let mut entries = failed_logins("203.0.113.5", &[0, 1, 2]);
+        entries.extend(failed_logins("198.51.100.7", &[0, 1, 2]));
+        let alerts = AlertEngine::default_pack().evaluate_with(&entries, &RuleSet::default_pack());
+        assert!(alerts.is_empty());
+    }
+
+    #[test]
+    fn allowlisted_events_stay_out_of_windows() {
+        let allowlist = Allowlist::from_toml_str(
+            r#"
+            [[allow]]
+            name = "pentest"
+            justification = "Scheduled credential audit"
+            cidrs = ["203.0.113.0/24"]
+            rule_ids = ["alert:Repeated failed logins"]
+            "#,
+        )
+        .unwrap();
+        let mut entries = failed_logins("203.0.113.5", &[0, 2, 4, 6, 8]);
+        entries.extend(failed_logins("198.51.100.7", &[0, 2, 4, 6, 8]));
+
+        let mut suppressed = SuppressionReport::default();
+        let alerts =
+            AlertEngine::default_pack().evaluate_allowing(&entries, &RuleSet::default_pack(), &allowlist, &mut suppressed);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].ip_address.as_deref(), Some("198.51.100.7"));
+        assert_eq!(suppressed.alert_events, 5);
+        assert_eq!(suppressed.by_entry.get("pentest"), Some(&5));
+    }
+
+    #[test]
+    fn field_conditions_and_window_restart() {
+        let engine = AlertEngine::from_toml_str(
+            r#"
+            [[alerts]]
+            name = "404s"
+            condition = "http.status == 404"
+            threshold = 2
+            timeframe_minutes = 1
+            severity = "low"
+            "#,
+        )
+        .unwrap();
+        let entries: Vec<LogEntry> = (0..5)
+            .map(|s| {
+                let mut e = entry(&format!("2025-02-20T10:00:{:02}Z", s), "10.0.0.1", "GET /x");
+                e.fields.insert("http.status".to_string(), "404".to_string());
+                e
+            })
+            .collect();
+        // Five events: fires at the 2nd and 4th, the 5th starts a new window
+        let alerts = engine.evaluate_with(&entries, &RuleSet::default_pack());
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts[0].description.starts_with("2 events matching `http.status == 404` from 10.0.0.1"));
+    }
+
+    #[test]
+    fn rejects_bad_alert_rules() {
+        let bad = |condition: &str, threshold: usize| {
+            AlertEngine::from_toml_str(&format!(
+                "[[alerts]]\nname = \"x\"\ncondition = \"{}\"\nthreshold = {}\ntimeframe_minutes = 1\nseverity = \"high\"\n",
+                condition, threshold
+            ))
+        };
+        assert!(bad("message ~ boom", 1).is_err());
+        assert!(bad("rule:log.failed_login", 0).is_err());
+        let unknown = bad("rule:log.nope", 1).unwrap();
+        assert!(unknown.check(&RuleSet::default_pack()).unwrap_err().contains("log.nope"));
+        assert!(AlertEngine::default_pack().check(&RuleSet::default_pack()).is_ok());
+    }
+}
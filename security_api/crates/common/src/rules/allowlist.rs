@@ -0,0 +1,425 @@
+// Allowlists and suppression
+// Health checkers, in-house vulnerability scanners and office NAT addresses
+// trip the same detections every day. Allowlist entries name what to
+// suppress: source CIDRs, user agents, request paths and usernames select
+// the traffic, rule ids select the detections (all of them when omitted).
+// Criteria of different kinds must all hold; any value within one kind will
+// do. An entry can carry a justification and an expiry date, after which it
+// stops applying and is reported as expired.
+//
+// Nothing is dropped silently: every suppressed finding, report, alert event
+// and high-risk IP is counted per entry in a `SuppressionReport`.
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::LogEntry;
+
+/// Rule id under which high-risk IP ranking is suppressed
+pub const HIGH_RISK_RULE: &str = "high_risk_ip";
+
+/// One allowlist entry as written in the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AllowEntry {
+    pub name: String,
+    #[serde(default)]
+    pub justification: Option<String>,
+    /// Last day the entry applies (`YYYY-MM-DD`)
+    #[serde(default, deserialize_with = "date")]
+    pub expires: Option<NaiveDate>,
+    /// Source addresses, as CIDRs or single IPs
+    #[serde(default)]
+    pub cidrs: Vec<String>,
+    /// Substrings of the user agent (case-insensitive)
+    #[serde(default)]
+    pub user_agents: Vec<String>,
+    /// Request path prefixes
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Usernames (case-insensitive)
+    #[serde(default)]
+    pub usernames: Vec<String>,
+    /// Detection rule ids (`http.scanner`), prefixes ending in `*`
+    /// (`rate.*`), alert rules as `alert:<name>`, or `high_risk_ip`
+    #[serde(default)]
+    pub rule_ids: Vec<String>,
+}
+
+/// What a detection is about, for matching against the allowlist
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Subject<'a> {
+    pub ip: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+    pub path: Option<&'a str>,
+    pub username: Option<&'a str>,
+}
+
+impl<'a> Subject<'a> {
+    /// The source, user agent, path and user of a log entry
+    pub fn of(entry: &'a LogEntry) -> Self {
+        Subject {
+            ip: entry.ip_address.as_deref(),
+            user_agent: entry.fields.get("http.user_agent").map(String::as_str),
+            path: entry.fields.get("http.path").map(String::as_str),
+            username: entry.username.as_deref(),
+        }
+    }
+
+    /// Only a source address
+    pub fn ip(ip: &'a str) -> Self {
+        Subject { ip: Some(ip), ..Default::default() }
+    }
+}
+
+/// An IPv4 or IPv6 network
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Cidr {
+    pub(crate) network: IpAddr,
+    pub(crate) prefix: u8,
+}
+
+impl Cidr {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let (address, prefix) = match text.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+            None => (text.trim(), None),
+        };
+        let network: IpAddr = address.parse().ok()?;
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(bits);
+        (prefix <= bits).then_some(Cidr { network, prefix })
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        let shift = |bits: u32| bits.saturating_sub(u32::from(self.prefix));
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(shift(32)).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(shift(128)).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CompiledEntry {
+    entry: AllowEntry,
+    cidrs: Vec<Cidr>,
+}
+
+impl CompiledEntry {
+    fn compile(mut entry: AllowEntry) -> Result<Self, String> {
+        if entry.name.trim().is_empty() {
+            return Err("allowlist entry without a name".to_string());
+        }
+        let context = |e: String| format!("allowlist entry '{}': {}", entry.name, e);
+        if entry.cidrs.is_empty()
+            && entry.user_agents.is_empty()
+            && entry.paths.is_empty()
+            && entry.usernames.is_empty()
+            && entry.rule_ids.is_empty()
+        {
+            return Err(context("needs at least one of cidrs, user_agents, paths, usernames or rule_ids".to_string()));
+        }
+        let cidrs = entry
+            .cidrs
+            .iter()
+            .map(|text| Cidr::parse(text).ok_or_else(|| context(format!("invalid CIDR '{}'", text))))
+            .collect::<Result<_, _>>()?;
+        for list in [&mut entry.user_agents, &mut entry.usernames] {
+            for value in list.iter_mut() {
+                *value = value.to_lowercase();
+            }
+        }
+        Ok(Self { entry, cidrs })
+    }
+
+    fn is_expired(&self, today: NaiveDate) -> bool {
+        self.entry.expires.is_some_and(|last_day| today > last_day)
+    }
+
+    fn matches(&self, subject: &Subject, rule_ids: &[&str]) -> bool {
+        let entry = &self.entry;
+        let ip_ok = self.cidrs.is_empty()
+            || subject
+                .ip
+                .and_then(|ip| ip.parse::<IpAddr>().ok())
+                .is_some_and(|ip| self.cidrs.iter().any(|cidr| cidr.contains(&ip)));
+        let agent_ok = entry.user_agents.is_empty()
+            || subject.user_agent.map(str::to_lowercase).is_some_and(|ua| entry.user_agents.iter().any(|a| ua.contains(a.as_str())));
+        let path_ok = entry.paths.is_empty() || subject.path.is_some_and(|path| entry.paths.iter().any(|p| path.starts_with(p.as_str())));
+        let user_ok = entry.usernames.is_empty()
+            || subject.username.map(str::to_lowercase).is_some_and(|user| entry.usernames.contains(&user));
+        let rule_ok = entry.rule_ids.is_empty() || rule_ids.iter().any(|id| entry.rule_ids.iter().any(|pattern| rule_matches(pattern, id)));
+        ip_ok && agent_ok && path_ok && user_ok && rule_ok
+    }
+}
+
+fn rule_matches(pattern: &str, id: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => id.starts_with(prefix),
+        None => pattern == id,
+    }
+}
+
+/// Counts of everything an allowlist suppressed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SuppressionReport {
+    /// Findings removed
+    pub findings: usize,
+    /// Behavioral reports removed (auth attacks, scans, rate anomalies,
+    /// exfiltration, content discovery)
+    pub reports: usize,
+    /// Entries kept out of alert rule windows
+    pub alert_events: usize,
+    /// IPs that would otherwise be ranked high risk
+    pub high_risk_ips: usize,
+    /// Everything above, per allowlist entry
+    pub by_entry: BTreeMap<String, usize>,
+    /// Entries past their expiry date, which no longer apply
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expired: Vec<String>,
+}
+
+impl SuppressionReport {
+    pub fn total(&self) -> usize {
+        self.findings + self.reports + self.alert_events + self.high_risk_ips
+    }
+
+    /// Count one suppression against an entry
+    pub fn record(&mut self, entry: &str) {
+        *self.by_entry.entry(entry.to_string()).or_insert(0) += 1;
+    }
+
+    /// Add the counts of another report (e.g. alerts to the analyzer's)
+    pub fn merge(&mut self, other: SuppressionReport) {
+        self.findings += other.findings;
+        self.reports += other.reports;
+        self.alert_events += other.alert_events;
+        self.high_risk_ips += other.high_risk_ips;
+        for (entry, count) in other.by_entry {
+            *self.by_entry.entry(entry).or_insert(0) += count;
+        }
+        for name in other.expired {
+            if !self.expired.contains(&name) {
+                self.expired.push(name);
+            }
+        }
+    }
+}
+
+/// An ordered collection of allowlist entries
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    entries: Vec<CompiledEntry>,
+    /// Day expiry is judged against; today when unset
+    today: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AllowlistFile {
+    #[serde(default)]
+    allow: Vec<AllowEntry>,
+}
+
+impl Allowlist {
+    pub fn from_toml_str(text: &str) -> Result<Self, String> {
+        let file: AllowlistFile = toml::from_str(text).map_err(|e| format!("Invalid TOML allowlist: {}", e))?;
+        Self::compile(file.allow)
+    }
+
+    pub fn from_yaml_str(text: &str) -> Result<Self, String> {
+        let file: AllowlistFile = serde_yaml::from_str(text).map_err(|e| format!("Invalid YAML allowlist: {}", e))?;
+        Self::compile(file.allow)
+    }
+
+    /// Load a `.toml`, `.yaml` or `.yml` allowlist file
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read allowlist file {}: {}", path.display(), e))?;
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+        let result = match ext.as_str() {
+            "yaml" | "yml" => Self::from_yaml_str(&text),
+            "toml" => Self::from_toml_str(&text),
+            _ => Err("expected a .toml, .yaml or .yml file".to_string()),
+        };
+        result.map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The entries of the given files, in order
+    pub fn with_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, String> {
+        let mut list = Self::default();
+        for path in paths {
+            list.extend(Self::from_file(path.as_ref())?);
+        }
+        Ok(list)
+    }
+
+    fn compile(entries: Vec<AllowEntry>) -> Result<Self, String> {
+        let entries = entries.into_iter().map(CompiledEntry::compile).collect::<Result<_, _>>()?;
+        Ok(Self { entries, today: None })
+    }
+
+    /// Add entries; an entry whose name already exists replaces it in place
+    pub fn extend(&mut self, other: Allowlist) {
+        for entry in other.entries {
+            match self.entries.iter_mut().find(|e| e.entry.name == entry.entry.name) {
+                Some(existing) => *existing = entry,
+                None => self.entries.push(entry),
+            }
+        }
+    }
+
+    /// Judge expiry against `day` instead of today
+    pub fn as_of(mut self, day: NaiveDate) -> Self {
+        self.today = Some(day);
+        self
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &AllowEntry> {
+        self.entries.iter().map(|e| &e.entry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn today(&self) -> NaiveDate {
+        self.today.unwrap_or_else(|| Utc::now().date_naive())
+    }
+
+    /// Name of the first entry in effect that suppresses any of `rule_ids`
+    /// for `subject`
+    pub fn suppressing(&self, subject: &Subject, rule_ids: &[&str]) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let today = self.today();
+        self.entries
+            .iter()
+            .find(|e| !e.is_expired(today) && e.matches(subject, rule_ids))
+            .map(|e| e.entry.name.as_str())
+    }
+
+    /// Names of the entries past their expiry date
+    pub fn expired(&self) -> Vec<String> {
+        let today = self.today();
+        self.entries.iter().filter(|e| e.is_expired(today)).map(|e| e.entry.name.clone()).collect()
+    }
+}
+
+static ACTIVE_ALLOWLIST: OnceLock<Allowlist> = OnceLock::new();
+
+/// Install the process-wide allowlist. Must be called before the first log is
+/// analyzed; fails if the allowlist is already in use.
+pub fn install(allowlist: Allowlist) -> Result<(), String> {
+    ACTIVE_ALLOWLIST
+        .set(allowlist)
+        .map_err(|_| "allowlist is already initialized".to_string())
+}
+
+/// The process-wide allowlist (empty unless `install` was called)
+pub fn active() -> &'static Allowlist {
+    ACTIVE_ALLOWLIST.get_or_init(Allowlist::default)
+}
+
+/// `YYYY-MM-DD`, quoted or as a TOML date
+fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DateValue {
+        Text(String),
+        Toml(toml::value::Datetime),
+    }
+    let text = match Option::<DateValue>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(DateValue::Text(text)) => text,
+        Some(DateValue::Toml(date)) => date.to_string(),
+    };
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("invalid date '{}' (expected YYYY-MM-DD)", text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+[[allow]]
+name = "office-nat"
+justification = "Staff browse through the office NAT"
+cidrs = ["198.51.100.0/24", "2001:db8::/32"]
+rule_ids = ["rate.*", "high_risk_ip"]
+
+[[allow]]
+name = "uptime-monitor"
+expires = 2030-01-31
+user_agents = ["UptimeRobot/"]
+paths = ["/healthz"]
+
+[[allow]]
+name = "old-pentest"
+expires = "2024-06-30"
+cidrs = ["203.0.113.9"]
+"#;
+
+    fn list() -> Allowlist {
+        Allowlist::from_toml_str(SAMPLE).unwrap().as_of(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+    }
+
+    #[test]
+    fn matches_all_criteria_of_an_entry() {
+        let list = list();
+        let office = Subject::ip("198.51.100.77");
+        assert_eq!(list.suppressing(&office, &["rate.burst"]), Some("office-nat"));
+        assert_eq!(list.suppressing(&office, &[HIGH_RISK_RULE]), Some("office-nat"));
+        assert_eq!(list.suppressing(&office, &["http.sql_injection"]), None);
+        assert_eq!(list.suppressing(&Subject::ip("2001:db8:1::5"), &["rate.flood"]), Some("office-nat"));
+        assert_eq!(list.suppressing(&Subject::ip("198.51.101.1"), &["rate.burst"]), None);
+
+        let probe = Subject {
+            ip: Some("192.0.2.10"),
+            user_agent: Some("Mozilla/5.0+(compatible; UptimeRobot/2.0)"),
+            path: Some("/healthz?full=1"),
+            username: None,
+        };
+        assert_eq!(list.suppressing(&probe, &["http.scanner"]), Some("uptime-monitor"));
+        let elsewhere = Subject { path: Some("/admin"), ..probe };
+        assert_eq!(list.suppressing(&elsewhere, &["http.scanner"]), None);
+    }
+
+    #[test]
+    fn expired_entries_stop_applying() {
+        let list = list();
+        assert_eq!(list.suppressing(&Subject::ip("203.0.113.9"), &["http.scanner"]), None);
+        assert_eq!(list.expired(), vec!["old-pentest"]);
+        let earlier = list.as_of(NaiveDate::from_ymd_opt(2024, 6, 30).unwrap());
+        assert_eq!(earlier.suppressing(&Subject::ip("203.0.113.9"), &["http.scanner"]), Some("old-pentest"));
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let err = Allowlist::from_toml_str("[[allow]]\nname = \"x\"\ncidrs = [\"10.0.0.0/33\"]").unwrap_err();
+        assert!(err.contains("invalid CIDR '10.0.0.0/33'"), "{}", err);
+        let err = Allowlist::from_toml_str("[[allow]]\nname = \"everything\"\njustification = \"too broad\"").unwrap_err();
+        assert!(err.contains("needs at least one of"), "{}", err);
+        let err = Allowlist::from_yaml_str("allow:\n  - name: x\n    cidrs: [10.0.0.1]\n    expires: soon\n").unwrap_err();
+        assert!(err.contains("invalid date 'soon'"), "{}", err);
+    }
+}
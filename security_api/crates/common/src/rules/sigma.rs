@@ -0,0 +1,762 @@
+// Sigma rule support
+// Compiles Sigma (https://sigmahq.io) `detection` blocks into matchers over
+// LogEntry and its structured fields. Supported: selections (maps, lists of
+// maps, keyword lists), the `contains`, `startswith`, `endswith`, `re` and
+// `all` modifiers, `*`/`?` wildcards, and conditions built from `and`, `or`,
+// `not`, parentheses, `1 of`/`all of` with patterns or `them`. Rules using
+// anything else are reported as unsupported with the reason.
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::FieldSource;
+use crate::LogEntry;
+
+/// A compiled Sigma rule
+#[derive(Debug, Clone)]
+pub struct SigmaRule {
+    pub id: String,
+    pub title: String,
+    /// Sigma level: informational, low, medium, high or critical
+    pub level: String,
+    /// Tags as written in the rule (e.g. `attack.credential_access`, `attack.t1110`)
+    pub tags: Vec<String>,
+    pub description: String,
+    selections: BTreeMap<String, Selection>,
+    condition: Expr,
+}
+
+/// A rule file (or document) that could not be compiled
+#[derive(Debug, Clone, Serialize)]
+pub struct UnsupportedSigmaRule {
+    pub source: String,
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub reason: String,
+}
+
+/// One log line matched by a Sigma rule
+#[derive(Debug, Clone, Serialize)]
+pub struct SigmaFinding {
+    pub rule_id: String,
+    pub title: String,
+    pub level: String,
+    pub tags: Vec<String>,
+    pub line_number: usize,
+    pub ip_address: Option<String>,
+    pub username: Option<String>,
+    pub message: String,
+}
+
+/// Sigma section of an analysis result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SigmaReport {
+    pub rules_loaded: usize,
+    pub findings: Vec<SigmaFinding>,
+    pub unsupported: Vec<UnsupportedSigmaRule>,
+}
+
+/// Compiled Sigma rules plus the rules that were rejected while loading
+#[derive(Debug, Clone, Default)]
+pub struct SigmaRuleSet {
+    pub rules: Vec<SigmaRule>,
+    pub unsupported: Vec<UnsupportedSigmaRule>,
+}
+
+impl SigmaRuleSet {
+    /// Load rule files and directories (searched recursively for `.yml`/`.yaml`).
+    /// Only I/O errors fail; rules with unsupported features are collected in
+    /// `unsupported`.
+    pub fn load_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Self, String> {
+        let mut set = Self::default();
+        for path in paths {
+            set.load_path(path.as_ref())?;
+        }
+        Ok(set)
+    }
+
+    fn load_path(&mut self, path: &Path) -> Result<(), String> {
+        if path.is_dir() {
+            let mut children: Vec<_> = std::fs::read_dir(path)
+                .map_err(|e| format!("Failed to read Sigma directory {}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .collect();
+            children.sort();
+            for child in children {
+                let is_yaml = child
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e.eq_ignore_ascii_case("yml") || e.eq_ignore_ascii_case("yaml"));
+                if child.is_dir() || is_yaml {
+                    self.load_path(&child)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read Sigma rule {}: {}", path.display(), e))?;
+        self.add_yaml(&path.display().to_string(), &text);
+        Ok(())
+    }
+
+    /// Compile every YAML document in `text`; `source` names it in reports
+    pub fn add_yaml(&mut self, source: &str, text: &str) {
+        for document in serde_yaml::Deserializer::from_str(text) {
+            let doc = match serde::Deserialize::deserialize(document) {
+                Ok(Value::Null) => continue,
+                Ok(doc) => doc,
+                Err(e) => {
+                    self.unsupported.push(UnsupportedSigmaRule {
+                        source: source.to_string(),
+                        id: None,
+                        title: None,
+                        reason: format!("invalid YAML: {}", e),
+                    });
+                    return;
+                }
+            };
+            match SigmaRule::compile(&doc) {
+                Ok(rule) => self.rules.push(rule),
+                Err(reason) => self.unsupported.push(UnsupportedSigmaRule {
+                    source: source.to_string(),
+                    id: string_at(&doc, "id"),
+                    title: string_at(&doc, "title"),
+                    reason,
+                }),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.unsupported.is_empty()
+    }
+
+    /// Run every rule over the entries; `line_numbers` is parallel to `entries`
+    pub fn scan(&self, entries: &[LogEntry], line_numbers: &[usize]) -> SigmaReport {
+        let mut findings = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            for rule in self.rules.iter().filter(|r| r.matches(entry)) {
+                findings.push(SigmaFinding {
+                    rule_id: rule.id.clone(),
+                    title: rule.title.clone(),
+                    level: rule.level.clone(),
+                    tags: rule.tags.clone(),
+                    line_number: line_numbers.get(index).copied().unwrap_or(index + 1),
+                    ip_address: entry.ip_address.clone(),
+                    username: entry.username.clone(),
+                    message: entry.message.chars().take(200).collect(),
+                });
+            }
+        }
+        SigmaReport {
+            rules_loaded: self.rules.len(),
+            findings,
+            unsupported: self.unsupported.clone(),
+        }
+    }
+}
+
+static ACTIVE_SIGMA: OnceLock<SigmaRuleSet> = OnceLock::new();
+
+/// Install the process-wide Sigma rules; fails if they are already in use
+pub fn install(rules: SigmaRuleSet) -> Result<(), String> {
+    ACTIVE_SIGMA
+        .set(rules)
+        .map_err(|_| "Sigma rules are already initialized".to_string())
+}
+
+/// The process-wide Sigma rules (none unless `install` was called)
+pub fn active() -> &'static SigmaRuleSet {
+    ACTIVE_SIGMA.get_or_init(SigmaRuleSet::default)
+}
+
+impl SigmaRule {
+    /// Compile one parsed Sigma document
+    pub fn compile(doc: &Value) -> Result<Self, String> {
+        if doc.get("action").is_some() {
+            return Err("rule collections (`action`) are not supported".to_string());
+        }
+        if doc.get("correlation").is_some() {
+            return Err("correlation rules are not supported".to_string());
+        }
+        let title = string_at(doc, "title").ok_or("missing `title`")?;
+        let detection = doc
+            .get("detection")
+            .and_then(|d| d.as_mapping())
+            .ok_or("missing `detection`")?;
+
+        let mut selections = BTreeMap::new();
+        let mut conditions: Vec<String> = Vec::new();
+        for (key, value) in detection {
+            let key = key.as_str().ok_or("non-string key in `detection`")?;
+            match key {
+                "condition" => match value {
+                    Value::String(s) => conditions.push(s.clone()),
+                    Value::Sequence(items) => {
+                        for item in items {
+                            conditions.push(item.as_str().ok_or("non-string condition")?.to_string());
+                        }
+                    }
+                    _ => return Err("`condition` must be a string or list".to_string()),
+                },
+                "timeframe" => return Err("`timeframe` (aggregation windows) is not supported".to_string()),
+                name => {
+                    selections.insert(name.to_string(), Selection::compile(name, value)?);
+                }
+            }
+        }
+        if conditions.is_empty() {
+            return Err("missing `detection.condition`".to_string());
+        }
+
+        let mut parsed = Vec::new();
+        for condition in &conditions {
+            parsed.push(ConditionParser::parse(condition, &selections)?);
+        }
+        let condition = if parsed.len() == 1 { parsed.remove(0) } else { Expr::Or(parsed) };
+
+        let level = string_at(doc, "level").unwrap_or_else(|| "medium".to_string()).to_lowercase();
+        let tags = doc
+            .get("tags")
+            .and_then(|t| t.as_sequence())
+            .map(|items| items.iter().filter_map(|t| t.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+
+        Ok(SigmaRule {
+            id: string_at(doc, "id").unwrap_or_else(|| title.clone()),
+            description: string_at(doc, "description").unwrap_or_default(),
+            title,
+            level,
+            tags,
+            selections,
+            condition,
+        })
+    }
+
+    pub fn matches(&self, source: &dyn FieldSource) -> bool {
+        self.condition.eval(&self.selections, source)
+    }
+}
+
+fn string_at(doc: &Value, key: &str) -> Option<String> {
+    doc.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+/// Sigma field names commonly used by web server and Linux rules, mapped to
+/// the names `LogEntry` exposes. Other names are looked up as-is.
+fn map_field(name: &str) -> &str {
+    match name {
+        "c-ip" | "ClientIP" | "SourceIp" | "src_ip" | "source.ip" => "ip",
+        "cs-uri" | "cs-uri-query" | "cs-uri-stem" | "c-uri" | "uri" | "url" => "http.path",
+        "cs-method" | "method" => "http.method",
+        "sc-status" | "status" => "http.status",
+        "cs-user-agent" | "c-useragent" | "UserAgent" | "user_agent" => "http.user_agent",
+        "cs-referer" | "referer" => "http.referer",
+        "User" | "TargetUserName" | "user.name" | "username" => "username",
+        "Message" | "msg" => "message",
+        "dst_ip" | "DestinationIp" | "destination.ip" => "firewall.dst_ip",
+        "dst_port" | "DestinationPort" | "destination.port" => "firewall.dst_port",
+        "src_port" | "SourcePort" | "source.port" => "firewall.src_port",
+        "action" => "firewall.action",
+        other => other,
+    }
+}
+
+/// A named block under `detection`
+#[derive(Debug, Clone)]
+enum Selection {
+    /// List of keywords searched in the message (any matches)
+    Keywords(Vec<ValueMatcher>),
+    /// Alternatives (list of maps); each map is an AND of field tests
+    Maps(Vec<Vec<FieldTest>>),
+}
+
+impl Selection {
+    fn compile(name: &str, value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Mapping(_) => Ok(Selection::Maps(vec![compile_map(name, value)?])),
+            Value::Sequence(items) if items.iter().all(|v| v.is_mapping()) => items
+                .iter()
+                .map(|item| compile_map(name, item))
+                .collect::<Result<_, _>>()
+                .map(Selection::Maps),
+            Value::Sequence(items) => items
+                .iter()
+                .map(|item| {
+                    let keyword = scalar_to_string(item)
+                        .ok_or_else(|| format!("selection '{}' mixes keywords and maps", name))?;
+                    ValueMatcher::compile(&keyword, Kind::Contains)
+                })
+                .collect::<Result<_, _>>()
+                .map(Selection::Keywords),
+            other => match scalar_to_string(other) {
+                Some(keyword) => Ok(Selection::Keywords(vec![ValueMatcher::compile(&keyword, Kind::Contains)?])),
+                None => Err(format!("selection '{}' has an unsupported shape", name)),
+            },
+        }
+    }
+
+    fn matches(&self, source: &dyn FieldSource) -> bool {
+        match self {
+            Selection::Keywords(keywords) => source
+                .field("message")
+                .is_some_and(|message| keywords.iter().any(|k| k.matches(&message))),
+            Selection::Maps(alternatives) => alternatives
+                .iter()
+                .any(|tests| tests.iter().all(|t| t.matches(source))),
+        }
+    }
+}
+
+fn compile_map(name: &str, value: &Value) -> Result<Vec<FieldTest>, String> {
+    let map = value.as_mapping().ok_or_else(|| format!("selection '{}' is not a map", name))?;
+    map.iter()
+        .map(|(key, values)| {
+            let key = key.as_str().ok_or_else(|| format!("non-string field in selection '{}'", name))?;
+            FieldTest::compile(key, values)
+        })
+        .collect()
+}
+
+/// `field|modifier...: value(s)`
+#[derive(Debug, Clone)]
+struct FieldTest {
+    field: String,
+    values: Vec<ValueMatcher>,
+    /// `all` modifier: every value must match instead of any
+    require_all: bool,
+}
+
+impl FieldTest {
+    fn compile(key: &str, values: &Value) -> Result<Self, String> {
+        let mut parts = key.split('|');
+        let field = parts.next().unwrap_or_default();
+        if field.is_empty() {
+            return Err("keyword maps without a field name are not supported".to_string());
+        }
+
+        let mut kind = Kind::Equals;
+        let mut require_all = false;
+        for modifier in parts {
+            match modifier {
+                "contains" => kind = Kind::Contains,
+                "startswith" => kind = Kind::StartsWith,
+                "endswith" => kind = Kind::EndsWith,
+                "re" => kind = Kind::Regex,
+                "all" => require_all = true,
+                other => return Err(format!("unsupported modifier '{}' on field '{}'", other, field)),
+            }
+        }
+
+        let raw: Vec<Option<String>> = match values {
+            Value::Sequence(items) => items.iter().map(scalar_or_null).collect::<Result<_, _>>()?,
+            single => vec![scalar_or_null(single)?],
+        };
+        let values = raw
+            .into_iter()
+            .map(|value| match value {
+                Some(v) => ValueMatcher::compile(&v, kind),
+                None => Ok(ValueMatcher::Null),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(FieldTest {
+            field: map_field(field).to_string(),
+            values,
+            require_all,
+        })
+    }
+
+    fn matches(&self, source: &dyn FieldSource) -> bool {
+        let value = source.field(&self.field);
+        let test = |m: &ValueMatcher| match (&value, m) {
+            (None, ValueMatcher::Null) => true,
+            (Some(v), ValueMatcher::Null) => v.is_empty(),
+            (None, _) => false,
+            (Some(v), m) => m.matches(v),
+        };
+        if self.require_all {
+            self.values.iter().all(test)
+        } else {
+            self.values.iter().any(test)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Equals,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Regex,
+}
+
+/// A single value comparison; Sigma string matching is case-insensitive
+#[derive(Debug, Clone)]
+enum ValueMatcher {
+    Literal { kind: Kind, needle: String },
+    Pattern(Regex),
+    Null,
+}
+
+impl ValueMatcher {
+    fn compile(value: &str, kind: Kind) -> Result<Self, String> {
+        if kind == Kind::Regex {
+            return Regex::new(value)
+                .map(ValueMatcher::Pattern)
+                .map_err(|e| format!("invalid regex '{}': {}", value, e));
+        }
+
+        let (pattern, has_wildcard) = wildcard_to_regex(value);
+        if !has_wildcard {
+            return Ok(ValueMatcher::Literal {
+                kind,
+                needle: unescape(value).to_lowercase(),
+            });
+        }
+
+        let anchored = match kind {
+            Kind::Equals => format!("^{}$", pattern),
+            Kind::Contains => pattern,
+            Kind::StartsWith => format!("^{}", pattern),
+            Kind::EndsWith => format!("{}$", pattern),
+            Kind::Regex => unreachable!(),
+        };
+        RegexBuilder::new(&anchored)
+            .case_insensitive(true)
+            .dot_matches_new_line(true)
+            .build()
+            .map(ValueMatcher::Pattern)
+            .map_err(|e| format!("invalid wildcard value '{}': {}", value, e))
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            ValueMatcher::Literal { kind, needle } => {
+                let haystack = value.to_lowercase();
+                match kind {
+                    Kind::Equals => haystack == needle.as_str(),
+                    Kind::Contains => haystack.contains(needle.as_str()),
+                    Kind::StartsWith => haystack.starts_with(needle.as_str()),
+                    Kind::EndsWith => haystack.ends_with(needle.as_str()),
+                    Kind::Regex => false,
+                }
+            }
+            ValueMatcher::Pattern(re) => re.is_match(value),
+            ValueMatcher::Null => value.is_empty(),
+        }
+    }
+}
+
+/// Translate Sigma wildcards (`*`, `?`, with `\` escapes) into a regex body.
+/// Returns whether any unescaped wildcard was present.
+fn wildcard_to_regex(value: &str) -> (String, bool) {
+    let mut out = String::new();
+    let mut has_wildcard = false;
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('*' | '?' | '\\')) => {
+                let escaped = chars.next().unwrap_or('\\');
+                out.push_str(&regex::escape(&escaped.to_string()));
+            }
+            '*' => {
+                has_wildcard = true;
+                out.push_str(".*");
+            }
+            '?' => {
+                has_wildcard = true;
+                out.push('.');
+            }
+            other => out.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    (out, has_wildcard)
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("\\*", "*").replace("\\?", "?").replace("\\\\", "\\")
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn scalar_or_null(value: &Value) -> Result<Option<String>, String> {
+    match value {
+        Value::Null => Ok(None),
+        other => scalar_to_string(other)
+            .map(Some)
+            .ok_or_else(|| "nested values in a selection are not supported".to_string()),
+    }
+}
+
+/// Parsed `condition`
+#[derive(Debug, Clone)]
+enum Expr {
+    Selection(String),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, selections: &BTreeMap<String, Selection>, source: &dyn FieldSource) -> bool {
+        match self {
+            Expr::Selection(name) => selections.get(name).is_some_and(|s| s.matches(source)),
+            Expr::Not(inner) => !inner.eval(selections, source),
+            Expr::And(items) => items.iter().all(|e| e.eval(selections, source)),
+            Expr::Or(items) => items.iter().any(|e| e.eval(selections, source)),
+        }
+    }
+}
+
+/// Recursive-descent parser for Sigma conditions:
+///   or := and ("or" and)* ; and := not ("and" not)* ;
+///   not := "not" not | "(" or ")" | ("1"|"all") "of" pattern | name
+struct ConditionParser<'a> {
+    tokens: Vec<String>,
+    pos: usize,
+    selections: &'a BTreeMap<String, Selection>,
+}
+
+impl<'a> ConditionParser<'a> {
+    fn parse(condition: &str, selections: &'a BTreeMap<String, Selection>) -> Result<Expr, String> {
+        if condition.contains('|') {
+            return Err(format!("aggregation conditions are not supported: '{}'", condition));
+        }
+        let tokens = condition
+            .replace('(', " ( ")
+            .replace(')', " ) ")
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let mut parser = ConditionParser { tokens, pos: 0, selections };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected '{}' in condition '{}'", token, condition)),
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut items = vec![self.and()?];
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            items.push(self.and()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { Expr::Or(items) })
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut items = vec![self.not()?];
+        while self.peek() == Some("and") {
+            self.pos += 1;
+            items.push(self.not()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { Expr::And(items) })
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        match self.next().as_deref() {
+            Some("not") => Ok(Expr::Not(Box::new(self.not()?))),
+            Some("(") => {
+                let inner = self.or()?;
+                match self.next().as_deref() {
+                    Some(")") => Ok(inner),
+                    _ => Err("unbalanced parentheses in condition".to_string()),
+                }
+            }
+            Some(quantifier @ ("1" | "all")) => {
+                let quantifier = quantifier.to_string();
+                if self.next().as_deref() != Some("of") {
+                    return Err(format!("expected 'of' after '{}'", quantifier));
+                }
+                let pattern = self.next().ok_or("expected a selection pattern after 'of'")?;
+                let names = self.expand(&pattern)?;
+                Ok(if quantifier == "1" { Expr::Or(names) } else { Expr::And(names) })
+            }
+            Some(name) if self.selections.contains_key(name) => Ok(Expr::Selection(name.to_string())),
+            Some(other) if other.chars().all(|c| c.is_ascii_digit()) => {
+                Err(format!("'{} of' quantifiers other than 1 and all are not supported", other))
+            }
+            Some(other) => Err(format!("condition references unknown selection '{}'", other)),
+            None => Err("condition ends unexpectedly".to_string()),
+        }
+    }
+
+    /// `them` or a selection name pattern with a trailing/embedded `*`
+    fn expand(&self, pattern: &str) -> Result<Vec<Expr>, String> {
+        let (regex, _) = wildcard_to_regex(pattern);
+        let re = Regex::new(&format!("^{}$", regex)).map_err(|e| e.to_string())?;
+        let names: Vec<Expr> = self
+            .selections
+            .keys()
+            .filter(|name| pattern == "them" || re.is_match(name))
+            // Sigma: `them` excludes selections whose names start with '_'
+            .filter(|name| pattern != "them" || !name.starts_with('_'))
+            .map(|name| Expr::Selection(name.clone()))
+            .collect();
+        if names.is_empty() {
+            return Err(format!("'{}' matches no selection", pattern));
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str, fields: &[(&str, &str)]) -> LogEntry {
+        LogEntry {
+            timestamp: String::new(),
+            level: "INFO".to_string(),
+            ip_address: Some("203.0.113.5".to_string()),
+            username: None,
+            message: message.to_string(),
+            fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    fn compile(yaml: &str) -> Result<SigmaRule, String> {
+        SigmaRule::compile(&serde_yaml::from_str(yaml).unwrap())
+    }
+
+    const WEB_RULE: &str = r#"
+title: Webshell command execution
+id: 0a1b2c3d-0000-4000-8000-000000000001
+level: high
+tags: [attack.persistence, attack.t1505.003]
+logsource: { category: webserver }
+detection:
+  selection_path:
+    cs-uri-query|contains|all: ['cmd=', '.php']
+  selection_ua:
+    cs-user-agent|startswith: ['curl/', 'python-requests']
+  filter:
+    c-ip|startswith: '10.'
+  condition: 1 of selection* and not filter
+"#;
+
+    #[test]
+    fn compiles_selections_modifiers_and_conditions() {
+        let rule = compile(WEB_RULE).expect("supported rule");
+        assert_eq!(rule.level, "high");
+        assert_eq!(rule.tags, vec!["attack.persistence", "attack.t1505.003"]);
+
+        let hit = entry("GET", &[("http.path", "/up/s.php?cmd=id"), ("http.user_agent", "Mozilla")]);
+        assert!(rule.matches(&hit));
+
+        let only_one_of_all = entry("GET", &[("http.path", "/index.php"), ("http.user_agent", "Mozilla")]);
+        assert!(!rule.matches(&only_one_of_all));
+
+        let by_ua = entry("GET", &[("http.path", "/"), ("http.user_agent", "CURL/8.0")]);
+        assert!(rule.matches(&by_ua));
+
+        let mut internal = hit.clone();
+        internal.ip_address = Some("10.1.2.3".to_string());
+        assert!(!rule.matches(&internal));
+    }
+
+    #[test]
+    fn keywords_wildcards_and_regex() {
+        let rule = compile(
+            r#"
+title: SSH brute force keywords
+level: medium
+detection:
+  keywords:
+    - 'Failed password for * from'
+  user:
+    username|re: '^(root|admin)$'
+  condition: keywords and user
+"#,
+        )
+        .unwrap();
+        let mut e = entry("Failed password for admin from 203.0.113.5 port 22 ssh2", &[]);
+        e.username = Some("admin".to_string());
+        assert!(rule.matches(&e));
+        e.username = Some("bob".to_string());
+        assert!(!rule.matches(&e));
+        // Without an id the title identifies the rule
+        assert_eq!(rule.id, "SSH brute force keywords");
+    }
+
+    #[test]
+    fn reports_unsupported_features() {
+        let mut set = SigmaRuleSet::default();
+        set.add_yaml(
+            "rules.yml",
+            r#"
+title: Encoded payload
+id: r1
+detection:
+  selection:
+    CommandLine|base64offset|contains: 'IEX'
+  condition: selection
+---
+title: Many failures
+id: r2
+detection:
+  selection:
+    message|contains: failed
+  condition: selection | count() by c-ip > 10
+---
+title: Supported
+id: r3
+detection:
+  selection:
+    message|contains: failed
+  condition: selection
+"#,
+        );
+        assert_eq!(set.rules.len(), 1);
+        assert_eq!(set.unsupported.len(), 2);
+        assert!(set.unsupported[0].reason.contains("base64offset"));
+        assert_eq!(set.unsupported[1].id.as_deref(), Some("r2"));
+        assert!(set.unsupported[1].reason.contains("aggregation"));
+    }
+
+    #[test]
+    fn scan_produces_findings_with_line_numbers() {
+        let mut set = SigmaRuleSet::default();
+        set.add_yaml("web.yml", WEB_RULE);
+        let entries = vec![
+            entry("GET", &[("http.path", "/"), ("http.user_agent", "Mozilla")]),
+            entry("GET", &[("http.path", "/s.php?cmd=whoami"), ("http.user_agent", "Mozilla")]),
+        ];
+        let report = set.scan(&entries, &[3, 7]);
+        assert_eq!(report.rules_loaded, 1);
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].line_number, 7);
+        assert_eq!(report.findings[0].rule_id, "0a1b2c3d-0000-4000-8000-000000000001");
+    }
+
+    #[test]
+    fn rejects_unknown_selection_in_condition() {
+        let err = compile("title: t\ndetection:\n  sel:\n    message: x\n  condition: sel and missing\n").unwrap_err();
+        assert!(err.contains("unknown selection 'missing'"));
+    }
+}
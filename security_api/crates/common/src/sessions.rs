@@ -0,0 +1,276 @@
+// Web session reconstruction
+// Requests from the same IP with the same user agent belong to one visitor;
+// a gap longer than the inactivity timeout starts a new session. Each session
+// is summarized by behavioral features, which also decide whether the visitor
+// looks like a person, a declared crawler, a tool or an undeclared bot.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::parsers::ApacheLog;
+
+/// Requests further apart than this start a new session
+pub const SESSION_TIMEOUT_MINUTES: i64 = 30;
+/// Requests listed in `WebSession::activity`
+const ACTIVITY_SAMPLE: usize = 10;
+
+/// File extensions a browser fetches as page assets
+const STATIC_EXTENSIONS: &[&str] = &[
+    "css", "js", "png", "jpg", "jpeg", "gif", "svg", "ico", "webp", "woff", "woff2", "ttf", "eot", "map",
+];
+/// User-agent words of self-declared crawlers
+const CRAWLER_AGENTS: &[&str] = &["bot", "crawler", "spider", "slurp", "bingpreview", "facebookexternalhit"];
+/// User agents of HTTP libraries, command-line clients and attack tools
+const TOOL_AGENTS: &[&str] = &[
+    "curl", "wget", "python-requests", "python-urllib", "go-http-client", "java/", "libwww-perl", "okhttp",
+    "httpclient", "sqlmap", "nikto", "nmap", "masscan", "zgrab", "gobuster", "dirbuster", "wpscan", "nuclei",
+];
+
+/// One visitor's requests between two inactivity gaps
+#[derive(Debug, Clone, Serialize)]
+pub struct WebSession {
+    pub ip: String,
+    pub user_agent: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_seconds: i64,
+    pub requests: usize,
+    pub distinct_paths: usize,
+    /// Share of responses with status 400 or above
+    pub error_ratio: f64,
+    /// Share of requests for page assets (stylesheets, scripts, images, fonts)
+    pub static_ratio: f64,
+    pub robots_txt: bool,
+    /// HEAD and OPTIONS requests
+    pub head_options_requests: usize,
+    /// `human`, `crawler` (declares itself), `tool` (HTTP library or attack
+    /// tool) or `bot` (undeclared automation)
+    pub classification: String,
+    /// Why the session was not taken for a person
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bot_signals: Vec<String>,
+    /// The first requests of the session (`METHOD path status`)
+    pub activity: Vec<String>,
+}
+
+impl WebSession {
+    pub fn is_human(&self) -> bool {
+        self.classification == "human"
+    }
+
+    /// One line describing what the visitor did
+    pub fn summary(&self) -> String {
+        let mut traits = vec![
+            format!("{} requests in {}s", self.requests, self.duration_seconds),
+            format!("{} paths", self.distinct_paths),
+            format!("{:.0}% errors", self.error_ratio * 100.0),
+            format!("{:.0}% static", self.static_ratio * 100.0),
+        ];
+        if self.robots_txt {
+            traits.push("robots.txt".to_string());
+        }
+        if self.head_options_requests > 0 {
+            traits.push(format!("{} HEAD/OPTIONS", self.head_options_requests));
+        }
+        format!("{} \"{}\": {}", self.ip, self.user_agent, traits.join(", "))
+    }
+}
+
+/// Group HTTP requests into sessions with the default inactivity timeout,
+/// ordered by start time
+pub fn sessionize(logs: &[ApacheLog]) -> Vec<WebSession> {
+    sessionize_with(logs, Duration::minutes(SESSION_TIMEOUT_MINUTES))
+}
+
+/// `sessionize` with a custom inactivity timeout. Entries that are not HTTP
+/// requests (converted from other formats) are skipped.
+pub fn sessionize_with(logs: &[ApacheLog], timeout: Duration) -> Vec<WebSession> {
+    let mut by_visitor: BTreeMap<(&str, &str), Vec<&ApacheLog>> = BTreeMap::new();
+    for log in logs.iter().filter(|log| log.protocol.starts_with("HTTP")) {
+        by_visitor.entry((&log.ip, &log.user_agent)).or_default().push(log);
+    }
+
+    let mut sessions = Vec::new();
+    for mut requests in by_visitor.into_values() {
+        requests.sort_by_key(|log| log.timestamp);
+        let mut start = 0;
+        for i in 1..=requests.len() {
+            if i == requests.len() || requests[i].timestamp - requests[i - 1].timestamp > timeout {
+                sessions.push(session(&requests[start..i]));
+                start = i;
+            }
+        }
+    }
+    sessions.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.ip.cmp(&b.ip)));
+    sessions
+}
+
+fn session(requests: &[&ApacheLog]) -> WebSession {
+    let first = requests[0];
+    let last = requests[requests.len() - 1];
+    let count = requests.len();
+    let ratio = |n: usize| n as f64 / count as f64;
+
+    let paths: BTreeSet<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+    let errors = requests.iter().filter(|r| r.status >= 400).count();
+    let assets = requests.iter().filter(|r| is_static(&r.path)).count();
+    let robots_txt = requests.iter().any(|r| r.path.starts_with("/robots.txt"));
+    let head_options = requests
+        .iter()
+        .filter(|r| r.method.eq_ignore_ascii_case("HEAD") || r.method.eq_ignore_ascii_case("OPTIONS"))
+        .count();
+
+    let mut session = WebSession {
+        ip: first.ip.clone(),
+        user_agent: first.user_agent.clone(),
+        start: first.timestamp,
+        end: last.timestamp,
+        duration_seconds: (last.timestamp - first.timestamp).num_seconds(),
+        requests: count,
+        distinct_paths: paths.len(),
+        error_ratio: ratio(errors),
+        static_ratio: ratio(assets),
+        robots_txt,
+        head_options_requests: head_options,
+        classification: String::new(),
+        bot_signals: Vec::new(),
+        activity: requests
+            .iter()
+            .take(ACTIVITY_SAMPLE)
+            .map(|r| format!("{} {} {}", r.method, r.path, r.status))
+            .collect(),
+    };
+    classify(&mut session);
+    session
+}
+
+/// Assets are told apart by the extension of the path, ignoring the query
+fn is_static(path: &str) -> bool {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    path.rsplit_once('.')
+        .filter(|(stem, _)| !stem.ends_with('/'))
+        .is_some_and(|(_, ext)| STATIC_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// A declared user agent decides; otherwise two behavioral signals make a bot
+fn classify(session: &mut WebSession) {
+    let agent = session.user_agent.to_lowercase();
+    let per_minute = session.requests as f64 * 60.0 / session.duration_seconds.max(1) as f64;
+
+    let mut signals = Vec::new();
+    if session.robots_txt {
+        signals.push("fetched robots.txt".to_string());
+    }
+    if session.head_options_requests > 0 {
+        signals.push(format!("{} HEAD/OPTIONS requests", session.head_options_requests));
+    }
+    if session.requests >= 20 && session.static_ratio == 0.0 {
+        signals.push("no page assets loaded".to_string());
+    }
+    if session.requests >= 10 && session.error_ratio >= 0.5 {
+        signals.push(format!("{:.0}% error responses", session.error_ratio * 100.0));
+    }
+    if session.requests >= 10 && per_minute >= 60.0 {
+        signals.push(format!("{:.0} requests per minute", per_minute));
+    }
+
+    let declared = if agent.is_empty() || agent == "-" {
+        signals.insert(0, "no user agent".to_string());
+        Some("tool")
+    } else if let Some(word) = TOOL_AGENTS.iter().find(|w| agent.contains(*w)) {
+        signals.insert(0, format!("user agent names a tool ({})", word));
+        Some("tool")
+    } else if let Some(word) = CRAWLER_AGENTS.iter().find(|w| agent.contains(*w)) {
+        signals.insert(0, format!("user agent declares a crawler ({})", word));
+        Some("crawler")
+    } else {
+        None
+    };
+
+    session.classification = match declared {
+        Some(kind) => kind,
+        None if signals.len() >= 2 => "bot",
+        None => "human",
+    }
+    .to_string();
+    // A person can trip one signal; it is only worth showing next to others
+    if session.is_human() {
+        signals.clear();
+    }
+    session.bot_signals = signals;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn log(second: i64, ip: &str, agent: &str, method: &str, path: &str, status: u16) -> ApacheLog {
+        ApacheLog {
+            ip: ip.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap() + Duration::seconds(second),
+            method: method.to_string(),
+            path: path.to_string(),
+            protocol: "HTTP/1.1".to_string(),
+            status,
+            size: 512,
+            referer: "-".to_string(),
+            user_agent: agent.to_string(),
+            is_suspicious: false,
+            threat_type: None,
+            severity: None,
+        }
+    }
+
+    const BROWSER: &str = "Mozilla/5.0 (X11; Linux x86_64) Firefox/121.0";
+
+    #[test]
+    fn splits_on_visitor_and_inactivity() {
+        let logs = vec![
+            log(0, "192.0.2.1", BROWSER, "GET", "/", 200),
+            log(5, "192.0.2.1", BROWSER, "GET", "/style.css", 200),
+            log(30, "192.0.2.1", "curl/8.0", "GET", "/", 200),
+            // Back after 40 minutes: a new session
+            log(2430, "192.0.2.1", BROWSER, "GET", "/news?page=2", 200),
+        ];
+        let sessions = sessionize(&logs);
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[0].requests, 2);
+        assert_eq!(sessions[0].duration_seconds, 5);
+        assert_eq!(sessions[0].static_ratio, 0.5);
+        assert_eq!(sessions[0].classification, "human");
+        assert_eq!(sessions[1].classification, "tool");
+        assert_eq!(sessions[2].activity, vec!["GET /news?page=2 200"]);
+    }
+
+    #[test]
+    fn classifies_crawlers_and_undeclared_bots() {
+        let crawler = vec![
+            log(0, "66.249.66.1", "Mozilla/5.0 (compatible; Googlebot/2.1)", "GET", "/robots.txt", 200),
+            log(2, "66.249.66.1", "Mozilla/5.0 (compatible; Googlebot/2.1)", "GET", "/", 200),
+        ];
+        let sessions = sessionize(&crawler);
+        assert_eq!(sessions[0].classification, "crawler");
+        assert!(sessions[0].robots_txt);
+
+        // A browser user agent, but probing paths with HEAD and no assets
+        let probe: Vec<ApacheLog> = (0..30)
+            .map(|i| log(i, "203.0.113.9", BROWSER, "HEAD", &format!("/backup{}.zip", i), 404))
+            .collect();
+        let session = &sessionize(&probe)[0];
+        assert_eq!(session.classification, "bot");
+        assert_eq!(session.head_options_requests, 30);
+        assert_eq!(session.error_ratio, 1.0);
+        assert!(session.bot_signals.iter().any(|s| s == "no page assets loaded"));
+    }
+
+    #[test]
+    fn static_assets_are_recognized_by_extension() {
+        assert!(is_static("/assets/app.min.js?v=3"));
+        assert!(is_static("/img/Logo.PNG"));
+        assert!(!is_static("/index.php"));
+        assert!(!is_static("/.env"));
+        assert!(!is_static("/api/items"));
+    }
+}
@@ -0,0 +1,221 @@
+// Impossible travel detection
+// Successful logins to one account are put in time order and each is
+// compared with the one before it: the great-circle distance between the two
+// source IPs' geolocations over the time between the logins gives the speed
+// the user would have had to travel at. Faster than an airliner, over a
+// distance larger than city-level geolocation can be wrong by, means the two
+// logins were not made by the same person in the same place.
+//
+// A login from a VPN, proxy or hosting range is located at the exit node,
+// not at the user, so such logins are left out of the chain rather than
+// compared. Otherwise every employee switching their VPN on would be flagged.
+
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::geolocation::GeoResult;
+use crate::parsers::parse_timestamp;
+use crate::{ImpossibleTravel, Login};
+
+/// Faster than a commercial flight, including getting to and from airports
+pub const MAX_SPEED_KMH: f64 = 900.0;
+/// IP geolocation is city-level at best and can be off by a few hundred km
+const MIN_DISTANCE_KM: f64 = 500.0;
+/// Logins in the same minute are treated as a minute apart
+const MIN_HOURS: f64 = 1.0 / 60.0;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+struct Located<'a> {
+    login: &'a Login,
+    time: DateTime<Utc>,
+    geo: &'a GeoResult,
+    lat: f64,
+    lon: f64,
+}
+
+/// Find impossible travel between consecutive successful logins of each
+/// account. `geo` maps source IPs to their geolocation; logins from IPs
+/// without coordinates are skipped.
+pub fn detect(logins: &[Login], geo: &HashMap<String, GeoResult>) -> Vec<ImpossibleTravel> {
+    let mut by_account: BTreeMap<&str, Vec<(DateTime<Utc>, &Login)>> = BTreeMap::new();
+    for login in logins {
+        if let Some(time) = parse_timestamp(&login.timestamp) {
+            by_account.entry(&login.account).or_default().push((time, login));
+        }
+    }
+
+    let mut found = Vec::new();
+    for mut account_logins in by_account.into_values() {
+        account_logins.sort_by_key(|(time, _)| *time);
+        let mut previous: Option<Located> = None;
+        let mut anonymized = 0;
+        for (time, login) in account_logins {
+            let Some(location) = geo.get(&login.ip) else {
+                continue;
+            };
+            if location.is_proxy || location.is_hosting {
+                anonymized += 1;
+                continue;
+            }
+            let (Some(lat), Some(lon)) = (location.lat, location.lon) else {
+                continue;
+            };
+            let current = Located { login, time, geo: location, lat, lon };
+            if let Some(before) = &previous
+                && before.login.ip != login.ip
+                && let Some(travel) = compare(before, &current, anonymized)
+            {
+                found.push(travel);
+            }
+            previous = Some(current);
+            anonymized = 0;
+        }
+    }
+    found.sort_by_key(|travel| travel.line_number);
+    found
+}
+
+fn compare(from: &Located, to: &Located, anonymized: usize) -> Option<ImpossibleTravel> {
+    let distance_km = distance_km(from.lat, from.lon, to.lat, to.lon);
+    let hours = ((to.time - from.time).num_seconds() as f64 / 3600.0).max(MIN_HOURS);
+    let speed_kmh = distance_km / hours;
+    if distance_km < MIN_DISTANCE_KM || speed_kmh <= MAX_SPEED_KMH {
+        return None;
+    }
+
+    let from_location = place(from.geo, &from.login.ip);
+    let to_location = place(to.geo, &to.login.ip);
+    let mut description = format!(
+        "{} logged in from {} ({}) and {:.1}h later from {} ({}): {:.0} km, {:.0} km/h",
+        from.login.account, from_location, from.login.ip, hours, to_location, to.login.ip, distance_km, speed_kmh
+    );
+    if anonymized > 0 {
+        description.push_str(&format!("; {} VPN/hosting login(s) in between ignored", anonymized));
+    }
+    Some(ImpossibleTravel {
+        account: from.login.account.clone(),
+        from_ip: from.login.ip.clone(),
+        from_location,
+        from_time: from.login.timestamp.clone(),
+        to_ip: to.login.ip.clone(),
+        to_location,
+        to_time: to.login.timestamp.clone(),
+        distance_km,
+        hours,
+        speed_kmh,
+        anonymized_logins_skipped: anonymized,
+        line_number: to.login.line_number,
+        description,
+    })
+}
+
+/// "City, Country", as much of it as is known
+fn place(geo: &GeoResult, ip: &str) -> String {
+    match (&geo.city, &geo.country) {
+        (Some(city), Some(country)) => format!("{}, {}", city, country),
+        (None, Some(place)) | (Some(place), None) => place.clone(),
+        (None, None) => ip.to_string(),
+    }
+}
+
+/// Great-circle distance (haversine)
+pub fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(line: usize, time: &str, account: &str, ip: &str) -> Login {
+        Login {
+            account: account.to_string(),
+            ip: ip.to_string(),
+            timestamp: time.to_string(),
+            line_number: line,
+        }
+    }
+
+    fn located(city: &str, lat: f64, lon: f64) -> GeoResult {
+        GeoResult {
+            city: Some(city.to_string()),
+            country: Some("Somewhere".to_string()),
+            lat: Some(lat),
+            lon: Some(lon),
+            ..Default::default()
+        }
+    }
+
+    fn geo() -> HashMap<String, GeoResult> {
+        let mut vpn = located("Frankfurt", 50.11, 8.68);
+        vpn.is_hosting = true;
+        [
+            ("198.51.100.1", located("London", 51.51, -0.13)),
+            ("198.51.100.2", located("Manchester", 53.48, -2.24)),
+            ("203.0.113.7", located("Sydney", -33.87, 151.21)),
+            ("192.0.2.50", vpn),
+        ]
+        .into_iter()
+        .map(|(ip, geo)| (ip.to_string(), geo))
+        .collect()
+    }
+
+    #[test]
+    fn distance_matches_known_routes() {
+        let london_sydney = distance_km(51.51, -0.13, -33.87, 151.21);
+        assert!((london_sydney - 16_990.0).abs() < 50.0, "{}", london_sydney);
+        assert_eq!(distance_km(10.0, 20.0, 10.0, 20.0), 0.0);
+    }
+
+    #[test]
+    fn flags_logins_too_far_apart_for_the_time_between() {
+        let logins = vec![
+            login(1, "2024-03-01T09:00:00Z", "alice", "198.51.100.1"),
+            login(2, "2024-03-01T11:00:00Z", "alice", "203.0.113.7"),
+            // London to Manchester in an hour is a train ride
+            login(3, "2024-03-01T09:00:00Z", "bob", "198.51.100.1"),
+            login(4, "2024-03-01T10:00:00Z", "bob", "198.51.100.2"),
+            // London to Sydney in two days is a flight
+            login(5, "2024-03-01T09:00:00Z", "carol", "198.51.100.1"),
+            login(6, "2024-03-03T09:00:00Z", "carol", "203.0.113.7"),
+        ];
+        let found = detect(&logins, &geo());
+        assert_eq!(found.len(), 1);
+        let travel = &found[0];
+        assert_eq!(travel.account, "alice");
+        assert_eq!((travel.from_location.as_str(), travel.to_location.as_str()), ("London, Somewhere", "Sydney, Somewhere"));
+        assert_eq!(travel.hours, 2.0);
+        assert!(travel.speed_kmh > 8000.0);
+        assert_eq!(travel.line_number, 2);
+    }
+
+    #[test]
+    fn vpn_and_hosting_logins_are_left_out() {
+        let logins = vec![
+            login(1, "2024-03-01T09:00:00Z", "dave", "198.51.100.1"),
+            login(2, "2024-03-01T09:05:00Z", "dave", "192.0.2.50"),
+            login(3, "2024-03-01T09:10:00Z", "dave", "198.51.100.2"),
+            // Unknown location
+            login(4, "2024-03-01T09:12:00Z", "dave", "192.0.2.99"),
+            login(5, "2024-03-01T09:30:00Z", "dave", "203.0.113.7"),
+        ];
+        let found = detect(&logins, &geo());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].from_ip, "198.51.100.2");
+        assert_eq!(found[0].anonymized_logins_skipped, 0);
+
+        let logins = vec![
+            login(1, "2024-03-01T09:00:00Z", "erin", "198.51.100.1"),
+            login(2, "2024-03-01T09:05:00Z", "erin", "192.0.2.50"),
+            login(3, "2024-03-01T09:10:00Z", "erin", "203.0.113.7"),
+        ];
+        let found = detect(&logins, &geo());
+        assert_eq!(found[0].from_ip, "198.51.100.1");
+        assert_eq!(found[0].anonymized_logins_skipped, 1);
+        assert!(found[0].description.ends_with("1 VPN/hosting login(s) in between ignored"));
+    }
+}